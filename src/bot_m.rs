@@ -0,0 +1,202 @@
+use std::{
+    env, fs,
+    net::SocketAddr,
+    process,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::{log::LogPlugin, prelude::*};
+
+use absent_chroma::client::{
+    ClientCorePlugin,
+    bot::{BotAction, BotBehavior, BotPlugin, BotStats},
+    network::{
+        ServerAddress,
+        login::{Credentials, UserLogin},
+    },
+};
+use zeroize::Zeroizing;
+
+const TICK: Duration = Duration::from_micros(16_667);
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+const USAGE: &str = "\
+Usage: bot [options]
+  --count <n>              bots to run (default 1)
+  --server <ip:port>       server address (default: this machine, port 42069)
+  --password <password>    password of the bot<n> accounts, registered on first use
+                           (default bot-password)
+  --idle                   only handshake and ping
+  --script <path>          run the actions in a script file instead of wandering
+  --stagger <ms>           delay between bot connections (default 100)
+  --reconnect-every <s>    drop and resume each connection this often
+  --duration <s>           stop after this long";
+
+struct Options {
+    count: usize,
+    server: Option<SocketAddr>,
+    password: String,
+    behavior: BotBehavior,
+    stagger: Duration,
+    reconnect_every: Option<Duration>,
+    duration: Option<Duration>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        count: 1,
+        server: None,
+        password: "bot-password".to_string(),
+        behavior: BotBehavior::Random,
+        stagger: Duration::from_millis(100),
+        reconnect_every: None,
+        duration: None,
+    };
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+
+        match arg.as_str() {
+            "--count" => {
+                options.count = value()?.parse().map_err(|_| "Invalid --count")?;
+            }
+            "--server" => {
+                options.server = Some(value()?.parse().map_err(|_| "Invalid --server")?);
+            }
+            "--password" => options.password = value()?,
+            "--idle" => options.behavior = BotBehavior::Idle,
+            "--script" => {
+                let path = value()?;
+                let text =
+                    fs::read_to_string(&path).map_err(|error| format!("{}: {}", path, error))?;
+                let actions = BotAction::parse_script(&text)
+                    .map_err(|error| format!("{}: {}", path, error))?;
+
+                options.behavior = BotBehavior::Script(Arc::new(actions));
+            }
+            "--stagger" => {
+                let millis = value()?.parse().map_err(|_| "Invalid --stagger")?;
+                options.stagger = Duration::from_millis(millis);
+            }
+            "--reconnect-every" => {
+                let secs = value()?.parse().map_err(|_| "Invalid --reconnect-every")?;
+                options.reconnect_every = Some(Duration::from_secs(secs));
+            }
+            "--duration" => {
+                let secs = value()?.parse().map_err(|_| "Invalid --duration")?;
+                options.duration = Some(Duration::from_secs(secs));
+            }
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Runs every bot as its own app on this thread, stepping them in turn.
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let mut bots: Vec<App> = (0..options.count)
+        .map(|index| {
+            let mut app = App::new();
+
+            app.add_plugins(MinimalPlugins);
+
+            // Logging is process wide, the first bot sets it up for all of them.
+            if index == 0 {
+                app.add_plugins(LogPlugin {
+                    filter: "info,absent_chroma::client::network=warn".into(),
+                    ..default()
+                });
+            }
+
+            app.add_plugins(ClientCorePlugin);
+            app.add_plugins(BotPlugin {
+                behavior: options.behavior.clone(),
+                start_after: options.stagger * index as u32,
+                reconnect_every: options.reconnect_every,
+            });
+
+            app.insert_resource(UserLogin::LoggingIn(Credentials {
+                username: format!("bot{}", index),
+                password: Zeroizing::new(options.password.clone()),
+                register: true,
+            }));
+
+            if let Some(server) = options.server {
+                app.insert_resource(ServerAddress(server));
+            }
+
+            app.finish();
+            app.cleanup();
+
+            app
+        })
+        .collect();
+
+    info!("Running {} bots.", bots.len());
+
+    let started = Instant::now();
+    let mut last_report = started;
+
+    loop {
+        let tick_start = Instant::now();
+
+        for bot in &mut bots {
+            bot.update();
+        }
+
+        if last_report.elapsed() >= REPORT_INTERVAL {
+            last_report = Instant::now();
+            report(&bots, started.elapsed());
+        }
+
+        if options
+            .duration
+            .is_some_and(|duration| started.elapsed() >= duration)
+        {
+            report(&bots, started.elapsed());
+            break;
+        }
+
+        thread::sleep(TICK.saturating_sub(tick_start.elapsed()));
+    }
+}
+
+fn report(bots: &[App], elapsed: Duration) {
+    let mut total = BotStats::default();
+
+    for bot in bots {
+        let stats = bot.world().resource::<BotStats>();
+
+        total.connects += stats.connects;
+        total.welcomes += stats.welcomes;
+        total.drops += stats.drops;
+        total.messages_sent += stats.messages_sent;
+    }
+
+    info!(
+        "{}s => bots: {} connects: {} welcomes: {} drops: {} messages sent: {}",
+        elapsed.as_secs(),
+        bots.len(),
+        total.connects,
+        total.welcomes,
+        total.drops,
+        total.messages_sent
+    );
+}
@@ -0,0 +1,159 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use bevy::{
+    audio::{AddAudioSource, Decodable, Source},
+    prelude::*,
+};
+
+use crate::{
+    client::network::session::{ClientSession, CommLink},
+    common::{network::Role, tether::CommState},
+};
+
+const CRACKLE_SAMPLE_RATE: u32 = 22050;
+
+/// Loudest the crackle gets, once the link is lost.
+const MAX_CRACKLE_VOLUME: f32 = 0.25;
+
+/// Chance of a pop per sample at full intensity.
+const MAX_POP_CHANCE: f32 = 0.004;
+
+/// Radio crackle for Note, louder the worse the link to the partner is.
+pub struct CracklePlugin;
+
+/// Crackle loudness shared with the audio thread, as `f32` bits.
+#[derive(Resource, Clone, Default)]
+struct CrackleIntensity(Arc<AtomicU32>);
+
+impl CrackleIntensity {
+    fn set(&self, intensity: f32) {
+        self.0.store(intensity.to_bits(), Ordering::Relaxed);
+    }
+
+    fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// Endless noise source, silent while the intensity is zero.
+#[derive(Asset, TypePath)]
+struct CrackleStream {
+    intensity: CrackleIntensity,
+}
+
+struct CrackleDecoder {
+    intensity: CrackleIntensity,
+    /// xorshift state, the audio thread should not wait on anything.
+    state: u32,
+    /// Remaining loudness of the last pop, dying away sample by sample.
+    pop: f32,
+}
+
+impl CrackleDecoder {
+    /// Uniform in `0.0..1.0`.
+    fn random(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+
+        (self.state >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+impl Iterator for CrackleDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let intensity = self.intensity.get();
+
+        if intensity <= 0.0 {
+            self.pop = 0.0;
+            return Some(0.0);
+        }
+
+        if self.random() < MAX_POP_CHANCE * intensity {
+            self.pop = 1.0;
+        }
+        self.pop *= 0.97;
+
+        let hiss = (self.random() * 2.0 - 1.0) * 0.2;
+        let noise = (self.random() * 2.0 - 1.0) * self.pop;
+
+        Some((hiss + noise) * intensity * MAX_CRACKLE_VOLUME)
+    }
+}
+
+impl Source for CrackleDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        CRACKLE_SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Decodable for CrackleStream {
+    type DecoderItem = f32;
+    type Decoder = CrackleDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        CrackleDecoder {
+            intensity: self.intensity.clone(),
+            state: 0x9e37_79b9,
+            pop: 0.0,
+        }
+    }
+}
+
+impl CracklePlugin {
+    fn setup(
+        mut commands: Commands,
+        intensity: Res<CrackleIntensity>,
+        mut streams: ResMut<Assets<CrackleStream>>,
+    ) {
+        commands.spawn((
+            AudioPlayer(streams.add(CrackleStream {
+                intensity: intensity.clone(),
+            })),
+            PlaybackSettings::ONCE,
+        ));
+    }
+
+    /// Only Note hears it, Gray gets static on screen instead.
+    fn update_crackle(
+        comm: Res<CommLink>,
+        session: Res<ClientSession>,
+        intensity: Res<CrackleIntensity>,
+    ) {
+        if session.role != Some(Role::Note) || comm.state == CommState::Clear {
+            intensity.set(0.0);
+            return;
+        }
+
+        intensity.set(1.0 - comm.clarity.clamp(0.0, 1.0));
+    }
+}
+
+impl Plugin for CracklePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_audio_source::<CrackleStream>();
+        app.insert_resource(CrackleIntensity::default());
+        app.add_systems(Startup, Self::setup);
+        app.add_systems(Update, Self::update_crackle);
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use bevy::{
+    audio::{Pitch, SpatialListener, Volume},
+    prelude::*,
+};
+
+mod crackle;
+mod voice;
+
+use crate::{
+    client::{
+        network::session::{ClientSession, PartnerChat, QuickSignal},
+        world::player::Player,
+    },
+    common::{network::Role, signals::SignalDefs},
+};
+
+/// Frequency of the chat cue, in Hz.
+const CHAT_CUE_PITCH: f32 = 660.0;
+
+const EARCON_PULSE: Duration = Duration::from_millis(110);
+const EARCON_GAP: Duration = Duration::from_millis(70);
+
+/// One beep of a signal earcon, waiting for its turn.
+struct Pulse {
+    at: Duration,
+    pitch: f32,
+    translation: Vec3,
+}
+
+/// Spatialised sound cues for the things Note cannot see.
+pub struct CuesPlugin;
+
+impl CuesPlugin {
+    /// Ears go on the player, spaced like a head.
+    fn add_listener(mut commands: Commands, players: Query<Entity, Added<Player>>) {
+        for player in players.iter() {
+            commands.entity(player).insert(SpatialListener::new(0.3));
+        }
+    }
+
+    /// Note cannot see, so partner chat is a chirp from where it was said. Longer messages chirp
+    /// longer and distance makes it quieter.
+    fn play_chat_cues(
+        mut commands: Commands,
+        mut chats: MessageReader<PartnerChat>,
+        session: Res<ClientSession>,
+        mut pitches: ResMut<Assets<Pitch>>,
+    ) {
+        for chat in chats.read() {
+            if session.role != Some(Role::Note) {
+                continue;
+            }
+
+            let words = chat.text.split_whitespace().count().clamp(1, 8);
+            let duration = Duration::from_millis(120 * words as u64);
+
+            commands.spawn((
+                AudioPlayer(pitches.add(Pitch::new(CHAT_CUE_PITCH, duration))),
+                PlaybackSettings::DESPAWN
+                    .with_spatial(true)
+                    .with_volume(Volume::Linear(chat.clarity)),
+                Transform::from_translation(chat.translation),
+            ));
+        }
+    }
+
+    /// Note cannot see, so every signal is its own earcon from where it was given: the signal's
+    /// pitch, pulsed the signal's number of times.
+    fn play_signal_earcons(
+        mut commands: Commands,
+        mut signals: MessageReader<QuickSignal>,
+        session: Res<ClientSession>,
+        defs: Res<SignalDefs>,
+        time: Res<Time>,
+        mut pitches: ResMut<Assets<Pitch>>,
+        mut pending: Local<Vec<Pulse>>,
+    ) {
+        let now = time.elapsed();
+
+        for signal in signals.read() {
+            if session.role != Some(Role::Note) {
+                continue;
+            }
+
+            let Some(def) = defs.get(&signal.id) else {
+                debug!("Unknown signal {:?} from {:?}", signal.id, signal.from);
+                continue;
+            };
+
+            for pulse in 0..def.pulses {
+                pending.push(Pulse {
+                    at: now + (EARCON_PULSE + EARCON_GAP) * pulse,
+                    pitch: def.pitch,
+                    translation: signal.translation,
+                });
+            }
+        }
+
+        pending.retain(|pulse| {
+            if pulse.at > now {
+                return true;
+            }
+
+            commands.spawn((
+                AudioPlayer(pitches.add(Pitch::new(pulse.pitch, EARCON_PULSE))),
+                PlaybackSettings::DESPAWN.with_spatial(true),
+                Transform::from_translation(pulse.translation),
+            ));
+
+            false
+        });
+    }
+}
+
+impl Plugin for CuesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(voice::VoicePlugin);
+        app.add_plugins(crackle::CracklePlugin);
+        app.add_systems(
+            Update,
+            (
+                Self::add_listener,
+                Self::play_chat_cues,
+                Self::play_signal_earcons,
+            ),
+        );
+    }
+}
@@ -0,0 +1,363 @@
+use std::{
+    collections::VecDeque,
+    env,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use bevy::{
+    audio::{AddAudioSource, Decodable, Source},
+    prelude::*,
+};
+use bevy_renet::{client_connected, renet::RenetClient};
+use cpal::{
+    SampleFormat,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+};
+
+use crate::{
+    client::{
+        AppState,
+        network::{
+            encryption::{Nonce, SskStore},
+            session::{ClientSession, PartnerVoice},
+        },
+        ui::chat::chat_closed,
+    },
+    common::{
+        network::{ClientMessage, VOICE_CHANNEL},
+        voice::{FRAME_SAMPLES, SAMPLE_RATE, TestTone, VoiceEncoder, decode},
+    },
+};
+
+/// Held to talk.
+const PUSH_TO_TALK: KeyCode = KeyCode::KeyV;
+
+/// Captured audio kept while waiting to be sent, older samples are dropped.
+const MAX_CAPTURE_SAMPLES: usize = FRAME_SAMPLES * 10;
+
+/// A frame this far behind the last one is late, farther means the partner's client restarted.
+const MAX_LATE_FRAMES: u32 = 50;
+
+/// Partner audio queued for playback. More than this and the oldest is dropped to keep latency
+/// down.
+const MAX_PLAYBACK_SAMPLES: usize = FRAME_SAMPLES * 10;
+
+const FRAME_DURATION: Duration =
+    Duration::from_micros(FRAME_SAMPLES as u64 * 1_000_000 / SAMPLE_RATE as u64);
+
+/// Captures push-to-talk voice, sends it on the voice channel and plays the partner's back from
+/// where they stand.
+pub struct VoicePlugin;
+
+/// Where outgoing voice comes from. `ABSENT_CHROMA_VOICE_SOURCE=tone` swaps the microphone for a
+/// test tone.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiceSource {
+    Microphone,
+    TestTone,
+}
+
+impl VoiceSource {
+    fn from_env() -> Self {
+        match env::var("ABSENT_CHROMA_VOICE_SOURCE").as_deref() {
+            Ok("tone") => VoiceSource::TestTone,
+            _ => VoiceSource::Microphone,
+        }
+    }
+}
+
+/// Mono samples at [`SAMPLE_RATE`], filled by the capture thread.
+#[derive(Resource, Clone, Default)]
+struct CapturedSamples(Arc<Mutex<VecDeque<i16>>>);
+
+/// Partner voice waiting to be played.
+#[derive(Resource, Clone, Default)]
+struct PlaybackSamples(Arc<Mutex<VecDeque<f32>>>);
+
+/// Endless audio source reading [`PlaybackSamples`], silent while the partner is not talking.
+#[derive(Asset, TypePath)]
+struct VoiceStream {
+    samples: Arc<Mutex<VecDeque<f32>>>,
+}
+
+struct VoiceDecoder {
+    samples: Arc<Mutex<VecDeque<f32>>>,
+}
+
+impl Iterator for VoiceDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self
+            .samples
+            .lock()
+            .expect("Voice playback lock poisoned.")
+            .pop_front();
+
+        Some(sample.unwrap_or(0.0))
+    }
+}
+
+impl Source for VoiceDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Decodable for VoiceStream {
+    type DecoderItem = f32;
+    type Decoder = VoiceDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        VoiceDecoder {
+            samples: self.samples.clone(),
+        }
+    }
+}
+
+/// Emitter for partner voice, moved to wherever the last frame was said.
+#[derive(Component, Clone)]
+struct PartnerVoiceEmitter;
+
+/// Downmixes and resamples device audio to mono [`SAMPLE_RATE`], picking the nearest sample.
+struct Resampler {
+    channels: usize,
+    step: f64,
+    position: f64,
+}
+
+impl Resampler {
+    fn push(&mut self, input: &[f32], output: &mut VecDeque<i16>) {
+        for frame in input.chunks(self.channels) {
+            self.position -= 1.0;
+
+            if self.position > 0.0 {
+                continue;
+            }
+
+            self.position += self.step;
+
+            let mono = frame.iter().sum::<f32>() / frame.len() as f32;
+            output.push_back((mono.clamp(-1.0, 1.0) * i16::MAX as f32) as i16);
+        }
+
+        let excess = output.len().saturating_sub(MAX_CAPTURE_SAMPLES);
+        output.drain(..excess);
+    }
+}
+
+/// Runs the default input device on its own thread, since cpal streams cannot move between
+/// threads.
+fn start_microphone(captured: CapturedSamples) {
+    thread::spawn(move || {
+        let Some(device) = cpal::default_host().default_input_device() else {
+            warn!("No microphone found, voice is off.");
+            return;
+        };
+
+        let config = match device.default_input_config() {
+            Ok(config) => config,
+            Err(error) => {
+                warn!("Could not read the microphone config: {}", error);
+                return;
+            }
+        };
+
+        let mut resampler = Resampler {
+            channels: config.channels() as usize,
+            step: config.sample_rate().0 as f64 / SAMPLE_RATE as f64,
+            position: 0.0,
+        };
+
+        let stream_config = config.config();
+        let on_error = |error: cpal::StreamError| warn!("Microphone error: {}", error);
+        let samples = captured.0;
+
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => device.build_input_stream(
+                &stream_config,
+                move |data: &[f32], _: &_| {
+                    let mut samples = samples.lock().expect("Voice capture lock poisoned.");
+                    resampler.push(data, &mut samples);
+                },
+                on_error,
+                None,
+            ),
+            SampleFormat::I16 => device.build_input_stream(
+                &stream_config,
+                move |data: &[i16], _: &_| {
+                    let data: Vec<f32> = data
+                        .iter()
+                        .map(|sample| *sample as f32 / i16::MAX as f32)
+                        .collect();
+                    let mut samples = samples.lock().expect("Voice capture lock poisoned.");
+                    resampler.push(&data, &mut samples);
+                },
+                on_error,
+                None,
+            ),
+            format => {
+                warn!(
+                    "Unsupported microphone sample format {:?}, voice is off.",
+                    format
+                );
+                return;
+            }
+        };
+
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                warn!("Could not open the microphone: {}", error);
+                return;
+            }
+        };
+
+        if let Err(error) = stream.play() {
+            warn!("Could not start the microphone: {}", error);
+            return;
+        }
+
+        info!("Microphone open.");
+
+        // The stream stops when dropped, so this thread keeps it.
+        loop {
+            thread::park();
+        }
+    });
+}
+
+impl VoicePlugin {
+    fn setup(
+        mut commands: Commands,
+        source: Res<VoiceSource>,
+        captured: Res<CapturedSamples>,
+        playback: Res<PlaybackSamples>,
+        mut streams: ResMut<Assets<VoiceStream>>,
+    ) {
+        if *source == VoiceSource::Microphone {
+            start_microphone(captured.clone());
+        } else {
+            info!("Voice uses a test tone instead of the microphone.");
+        }
+
+        commands.spawn((
+            AudioPlayer(streams.add(VoiceStream {
+                samples: playback.0.clone(),
+            })),
+            PlaybackSettings::ONCE.with_spatial(true),
+            Transform::default(),
+            PartnerVoiceEmitter,
+        ));
+    }
+
+    /// Sends whole frames while push-to-talk is held and throws captured audio away otherwise.
+    fn send_voice(
+        keyboard: Res<ButtonInput<KeyCode>>,
+        source: Res<VoiceSource>,
+        captured: Res<CapturedSamples>,
+        mut client: ResMut<RenetClient>,
+        ssks: Res<SskStore>,
+        mut nonce_res: ResMut<Nonce>,
+        session: Res<ClientSession>,
+        time: Res<Time>,
+        mut encoder: Local<VoiceEncoder>,
+        mut tone: Local<TestTone>,
+        mut tone_due: Local<Duration>,
+    ) {
+        let mut samples = captured.0.lock().expect("Voice capture lock poisoned.");
+
+        if session.role.is_none() || !keyboard.pressed(PUSH_TO_TALK) {
+            samples.clear();
+            *tone_due = Duration::ZERO;
+            return;
+        }
+
+        if *source == VoiceSource::TestTone {
+            *tone_due += time.delta();
+
+            while *tone_due >= FRAME_DURATION {
+                *tone_due -= FRAME_DURATION;
+                samples.extend(tone.frame());
+            }
+        }
+
+        while samples.len() >= FRAME_SAMPLES {
+            let frame: Vec<i16> = samples.drain(..FRAME_SAMPLES).collect();
+
+            ClientMessage::send_encrypted_on(
+                &mut client,
+                &ssks.0,
+                &ClientMessage::Voice(encoder.encode(&frame)),
+                &mut nonce_res,
+                VOICE_CHANNEL,
+            );
+        }
+    }
+
+    /// Queues partner frames in order, dropping late and repeated ones.
+    fn play_voice(
+        mut voices: MessageReader<PartnerVoice>,
+        playback: Res<PlaybackSamples>,
+        mut emitters: Query<&mut Transform, With<PartnerVoiceEmitter>>,
+        mut last_sequence: Local<Option<u32>>,
+    ) {
+        for voice in voices.read() {
+            let sequence = voice.frame.sequence;
+
+            if last_sequence.is_some_and(|last| last.wrapping_sub(sequence) < MAX_LATE_FRAMES) {
+                continue;
+            }
+            *last_sequence = Some(sequence);
+
+            let gain = voice.gain.clamp(0.0, 1.0) / i16::MAX as f32;
+            let mut samples = playback.0.lock().expect("Voice playback lock poisoned.");
+
+            samples.extend(
+                decode(&voice.frame)
+                    .iter()
+                    .map(|sample| *sample as f32 * gain),
+            );
+
+            let excess = samples.len().saturating_sub(MAX_PLAYBACK_SAMPLES);
+            samples.drain(..excess);
+
+            for mut transform in emitters.iter_mut() {
+                transform.translation = voice.translation;
+            }
+        }
+    }
+}
+
+impl Plugin for VoicePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_audio_source::<VoiceStream>();
+        app.insert_resource(VoiceSource::from_env());
+        app.insert_resource(CapturedSamples::default());
+        app.insert_resource(PlaybackSamples::default());
+        app.add_systems(Startup, Self::setup);
+        app.add_systems(
+            Update,
+            Self::send_voice.run_if(
+                client_connected
+                    .and(in_state(AppState::InGame))
+                    .and(chat_closed),
+            ),
+        );
+        app.add_systems(Update, Self::play_voice);
+    }
+}
@@ -0,0 +1,329 @@
+use std::{sync::Arc, time::Duration};
+
+use bevy::prelude::*;
+use bevy_renet::{client_connected, renet::RenetClient};
+use rand::Rng;
+
+use crate::{
+    client::{
+        AppState,
+        network::{
+            connection::{ConnectionState, drop_connection},
+            encryption::{Nonce, SskStore},
+            session::ClientSession,
+        },
+    },
+    common::network::ClientMessage,
+};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+const PLAYER_STATE_INTERVAL: Duration = Duration::from_millis(100);
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+/// How far a random bot strays from the origin.
+const WANDER_RADIUS: f32 = 50.0;
+
+/// Step of a bot script. Scripts loop once they run out.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BotAction {
+    Wait(Duration),
+    Move(Vec3),
+    Ping,
+    /// Drops the connection and connects again, resuming the same session.
+    Reconnect,
+}
+
+impl BotAction {
+    /// Parses one action per line: `wait <ms>`, `move <x> <y> <z>`, `ping` or `reconnect`.
+    /// Blank lines and lines starting with `#` are skipped.
+    pub fn parse_script(text: &str) -> Result<Vec<BotAction>, String> {
+        let mut actions = vec![];
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let number = |word: Option<&str>| -> Result<f32, String> {
+                word.and_then(|word| word.parse().ok())
+                    .ok_or_else(|| format!("line {}: expected a number in {:?}", index + 1, line))
+            };
+
+            let action = match words.next() {
+                Some("wait") => {
+                    BotAction::Wait(Duration::from_millis(number(words.next())? as u64))
+                }
+                Some("move") => BotAction::Move(Vec3::new(
+                    number(words.next())?,
+                    number(words.next())?,
+                    number(words.next())?,
+                )),
+                Some("ping") => BotAction::Ping,
+                Some("reconnect") => BotAction::Reconnect,
+                _ => return Err(format!("line {}: unknown action {:?}", index + 1, line)),
+            };
+
+            actions.push(action);
+        }
+
+        if actions.is_empty() {
+            return Err("script has no actions".to_string());
+        }
+
+        Ok(actions)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BotBehavior {
+    /// Finishes the handshake and only pings.
+    Idle,
+    /// Wanders around, sending player state and the occasional ping.
+    Random,
+    Script(Arc<Vec<BotAction>>),
+}
+
+/// Headless client driven by a [`BotBehavior`] instead of a player. Add it on top of
+/// `MinimalPlugins` and [`ClientCorePlugin`](crate::client::ClientCorePlugin).
+///
+/// The bot connects once `start_after` has passed and reconnects whenever it loses the server.
+pub struct BotPlugin {
+    pub behavior: BotBehavior,
+    pub start_after: Duration,
+    /// Drops and resumes the connection this often, to keep handshakes going during soak tests.
+    pub reconnect_every: Option<Duration>,
+}
+
+/// Running totals for one bot.
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct BotStats {
+    pub connects: u64,
+    pub welcomes: u64,
+    pub drops: u64,
+    pub messages_sent: u64,
+}
+
+#[derive(Resource)]
+struct Bot {
+    behavior: BotBehavior,
+    reconnect_every: Option<Duration>,
+    next_connect: Option<Duration>,
+    connected_at: Duration,
+    welcomed: bool,
+    reconnect_requested: bool,
+    script_step: usize,
+    wait_until: Duration,
+    last_player_state: Duration,
+    last_ping: Duration,
+    translation: Vec3,
+}
+
+fn send(
+    client: &mut RenetClient,
+    ssks: &SskStore,
+    nonce_res: &mut Nonce,
+    stats: &mut BotStats,
+    message: &ClientMessage,
+) {
+    ClientMessage::send_encrypted(client, &ssks.0, message, nonce_res);
+    stats.messages_sent += 1;
+}
+
+impl BotPlugin {
+    fn connect(
+        mut commands: Commands,
+        mut bot: ResMut<Bot>,
+        mut stats: ResMut<BotStats>,
+        client: Option<Res<RenetClient>>,
+        state: Res<State<AppState>>,
+        connection: Res<State<ConnectionState>>,
+        time: Res<Time>,
+    ) {
+        if client.is_some() || connection.is_active() {
+            return;
+        }
+
+        // A failed attempt stays on the connect screen, leave it so entering it connects again.
+        if *state.get() == AppState::ConnectToServer {
+            commands.set_state(AppState::MainMenu);
+            return;
+        }
+
+        let Some(next_connect) = bot.next_connect else {
+            bot.next_connect = Some(time.elapsed() + RECONNECT_DELAY);
+            return;
+        };
+
+        if time.elapsed() < next_connect {
+            return;
+        }
+
+        bot.next_connect = None;
+        bot.connected_at = time.elapsed();
+        bot.welcomed = false;
+        stats.connects += 1;
+
+        commands.set_state(AppState::ConnectToServer);
+    }
+
+    /// Counts lost connections and drops the connection when the bot wants to reconnect.
+    fn watch_connection(
+        mut commands: Commands,
+        mut bot: ResMut<Bot>,
+        mut stats: ResMut<BotStats>,
+        connection: Res<State<ConnectionState>>,
+        time: Res<Time>,
+    ) {
+        if connection.is_changed()
+            && let ConnectionState::Failed(reason) | ConnectionState::Disconnected(reason) =
+                connection.get()
+        {
+            warn!("Bot lost its connection: {}", reason);
+            stats.drops += 1;
+            return;
+        }
+
+        let expired = bot
+            .reconnect_every
+            .is_some_and(|every| bot.welcomed && time.elapsed() - bot.connected_at >= every);
+
+        if *connection.get() != ConnectionState::Connected || (!expired && !bot.reconnect_requested)
+        {
+            return;
+        }
+
+        // The resume token stays so the next Hello reclaims the slot.
+        drop_connection(&mut commands, ConnectionState::Offline);
+
+        bot.reconnect_requested = false;
+        bot.next_connect = Some(time.elapsed() + RECONNECT_DELAY);
+    }
+
+    fn act(
+        mut client: ResMut<RenetClient>,
+        ssks: Res<SskStore>,
+        mut nonce_res: ResMut<Nonce>,
+        session: Res<ClientSession>,
+        mut bot: ResMut<Bot>,
+        mut stats: ResMut<BotStats>,
+        time: Res<Time>,
+    ) {
+        if session.role.is_none() {
+            return;
+        }
+
+        let now = time.elapsed();
+
+        if !bot.welcomed {
+            bot.welcomed = true;
+            bot.wait_until = now;
+            stats.welcomes += 1;
+        }
+
+        if now - bot.last_ping >= PING_INTERVAL {
+            bot.last_ping = now;
+            send(
+                &mut client,
+                &ssks,
+                &mut nonce_res,
+                &mut stats,
+                &ClientMessage::Ping,
+            );
+        }
+
+        match bot.behavior.clone() {
+            BotBehavior::Idle => {}
+
+            BotBehavior::Random => {
+                if now - bot.last_player_state < PLAYER_STATE_INTERVAL {
+                    return;
+                }
+                bot.last_player_state = now;
+
+                let mut rng = rand::rng();
+                let step = Vec3::new(
+                    rng.random_range(-1.0..1.0),
+                    0.0,
+                    rng.random_range(-1.0..1.0),
+                );
+                bot.translation = (bot.translation + step).clamp_length_max(WANDER_RADIUS);
+
+                send(
+                    &mut client,
+                    &ssks,
+                    &mut nonce_res,
+                    &mut stats,
+                    &ClientMessage::PlayerState {
+                        translation: bot.translation.to_array(),
+                    },
+                );
+            }
+
+            BotBehavior::Script(actions) => {
+                while now >= bot.wait_until {
+                    let action = &actions[bot.script_step % actions.len()];
+                    bot.script_step += 1;
+
+                    match action {
+                        BotAction::Wait(duration) => bot.wait_until = now + *duration,
+                        BotAction::Move(translation) => {
+                            bot.translation = *translation;
+                            send(
+                                &mut client,
+                                &ssks,
+                                &mut nonce_res,
+                                &mut stats,
+                                &ClientMessage::PlayerState {
+                                    translation: translation.to_array(),
+                                },
+                            );
+                        }
+                        BotAction::Ping => {
+                            send(
+                                &mut client,
+                                &ssks,
+                                &mut nonce_res,
+                                &mut stats,
+                                &ClientMessage::Ping,
+                            );
+                        }
+                        BotAction::Reconnect => {
+                            bot.reconnect_requested = true;
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Plugin for BotPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Bot {
+            behavior: self.behavior.clone(),
+            reconnect_every: self.reconnect_every,
+            next_connect: Some(self.start_after),
+            connected_at: Duration::ZERO,
+            welcomed: false,
+            reconnect_requested: false,
+            script_step: 0,
+            wait_until: Duration::ZERO,
+            last_player_state: Duration::ZERO,
+            last_ping: Duration::ZERO,
+            translation: Vec3::ZERO,
+        });
+        app.insert_resource(BotStats::default());
+        app.add_systems(
+            Update,
+            (
+                Self::connect,
+                Self::watch_connection,
+                Self::act.run_if(client_connected),
+            )
+                .chain(),
+        );
+    }
+}
@@ -3,6 +3,7 @@ use bevy_renet::client_connected;
 use bevy_renet::renet::RenetClient;
 
 use crate::client::network::encryption::{Nonce, SskStore};
+use crate::client::ui::chat::chat_closed;
 use crate::client::world::enemy::Enemy;
 use crate::client::world::player::Player;
 use crate::client::world::{MainCamera, player};
@@ -46,10 +47,6 @@ impl ControlsPlugin {
                         .single_mut()
                         .expect("Multiple Players exist.");
 
-                    if transform.translation.z >= 45. {
-                        break;
-                    }
-
                     transform.translation.z += 5. * time.delta_secs();
 
                     let mut transform = camera_transform
@@ -64,10 +61,6 @@ impl ControlsPlugin {
                         .single_mut()
                         .expect("Multiple Players exist.");
 
-                    if transform.translation.x >= 45. {
-                        break;
-                    }
-
                     transform.translation.x += 5. * time.delta_secs();
 
                     let mut transform = camera_transform
@@ -82,10 +75,6 @@ impl ControlsPlugin {
                         .single_mut()
                         .expect("Multiple Players exist.");
 
-                    if transform.translation.z <= -45. {
-                        break;
-                    }
-
                     transform.translation.z -= 5. * time.delta_secs();
 
                     let mut transform = camera_transform
@@ -100,10 +89,6 @@ impl ControlsPlugin {
                         .single_mut()
                         .expect("Multiple Players exist.");
 
-                    if transform.translation.x <= -45. {
-                        break;
-                    }
-
                     transform.translation.x -= 5. * time.delta_secs();
 
                     let mut transform = camera_transform
@@ -169,8 +154,11 @@ impl Plugin for ControlsPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
             FixedUpdate,
-            (Self::keyboard_input).run_if(in_state(AppState::InGame)),
+            (Self::keyboard_input).run_if(in_state(AppState::InGame).and(chat_closed)),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (Self::send_ping).run_if(client_connected.and(chat_closed)),
         );
-        app.add_systems(FixedUpdate, (Self::send_ping).run_if(client_connected));
     }
 }
@@ -1,13 +1,18 @@
 use bevy::{
     log::LogPlugin,
     prelude::*,
+    state::app::StatesPlugin,
     window::{ExitCondition, WindowMode, WindowResolution},
 };
+use bevy_renet::{RenetClientPlugin, netcode::NetcodeClientPlugin};
 
-use crate::client::network::login::UserLogin;
+use crate::{client::network::login::UserLogin, common::signals::SignalDefs};
 
+#[cfg(feature = "audio")]
+mod audio;
+pub mod bot;
 mod controls;
-mod network;
+pub mod network;
 mod plugins;
 mod ui;
 mod world;
@@ -51,13 +56,37 @@ impl Plugin for Create {
                 .set(log_filter_plugin),
         );
 
-        app.init_state::<AppState>();
+        app.add_plugins(ClientCorePlugin);
+        app.add_plugins(network::discovery::DiscoveryPlugin);
+        app.add_plugins(network::browser::BrowserPlugin);
 
         app.insert_resource(PreviousAppState(None));
+        app.insert_resource(SignalDefs::load_default());
+
+        app.add_plugins(plugins::SuperPlugin);
+    }
+}
+
+/// App states, login and networking, without windows, rendering or audio.
+///
+/// Enough to run a client headless, on top of `MinimalPlugins`.
+#[derive(Clone, Debug)]
+pub struct ClientCorePlugin;
+
+impl Plugin for ClientCorePlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<StatesPlugin>() {
+            app.add_plugins(StatesPlugin);
+        }
+
+        app.init_state::<AppState>();
 
         app.insert_resource(UserLogin::default());
 
-        app.add_plugins(plugins::SuperPlugin);
+        app.add_plugins(RenetClientPlugin);
+        app.add_plugins(NetcodeClientPlugin);
+
+        app.add_plugins(network::NetworkPlugin);
     }
 }
 
@@ -65,13 +94,17 @@ impl Plugin for Create {
 ///
 /// Used to control transition between menus, game, and network connection screens.
 #[derive(States, Debug, Clone, PartialEq, Eq, Hash, Default, Copy)]
-enum AppState {
+pub enum AppState {
     #[default]
     MainMenu,
     Load,
     InGame,
     Pause,
+    /// Connect screen: server address, username, recent and discovered servers.
+    ConnectMenu,
     ConnectToServer,
+    /// The logged in account's stats, fetched from the server.
+    ProfileMenu,
 }
 
 /// Resource tracking the last active [`AppState`].
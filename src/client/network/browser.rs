@@ -0,0 +1,210 @@
+use std::{
+    collections::HashMap,
+    env,
+    net::{IpAddr, SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use bevy_renet::renet::RenetClient;
+
+use crate::{
+    client::AppState,
+    common::{
+        discovery::{DISCOVERY_PORT, DiscoveryMessage},
+        master::{ListedServer, MASTER_PACKET_BYTES, MASTER_PORT, MasterMessage},
+    },
+};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+/// Servers the master server stops listing drop off after this long.
+const SERVER_TIMEOUT: Duration = Duration::from_secs(12);
+
+/// Fetches the server list from a master server while the connect screen is open and pings every
+/// listed server.
+///
+/// The master server is read from `ABSENT_CHROMA_MASTER_ADDR`, as `ip` or `ip:port`. Nothing
+/// happens when it is unset.
+pub struct BrowserPlugin;
+
+#[derive(Debug, Clone)]
+pub struct BrowsedServer {
+    pub listing: ListedServer,
+    /// Round trip of a discovery probe to the server. `None` until it answers.
+    pub ping: Option<Duration>,
+    pub last_seen: Duration,
+}
+
+/// Servers from the master server's list, in the order it sent them.
+#[derive(Resource, Default, Debug)]
+pub struct MasterServers(pub Vec<BrowsedServer>);
+
+#[derive(Resource)]
+struct BrowserSocket {
+    socket: UdpSocket,
+    master: SocketAddr,
+    /// When the last discovery probe went out to each server host.
+    probes: HashMap<IpAddr, Instant>,
+}
+
+fn master_addr() -> Option<SocketAddr> {
+    let value = env::var("ABSENT_CHROMA_MASTER_ADDR").ok()?;
+
+    if let Ok(addr) = value.parse() {
+        return Some(addr);
+    }
+
+    match value.parse::<IpAddr>() {
+        Ok(ip) => Some(SocketAddr::new(ip, MASTER_PORT)),
+        Err(_) => {
+            warn!("Ignoring invalid ABSENT_CHROMA_MASTER_ADDR: {}", value);
+            None
+        }
+    }
+}
+
+impl BrowserPlugin {
+    fn bind(mut commands: Commands) {
+        let Some(master) = master_addr() else {
+            return;
+        };
+
+        let socket = UdpSocket::bind("0.0.0.0:0").and_then(|socket| {
+            socket.set_nonblocking(true)?;
+            Ok(socket)
+        });
+
+        match socket {
+            Ok(socket) => commands.insert_resource(BrowserSocket {
+                socket,
+                master,
+                probes: HashMap::new(),
+            }),
+            Err(error) => warn!("Server browser unavailable: {}", error),
+        }
+    }
+
+    fn refresh(browser: Res<BrowserSocket>, time: Res<Time>, mut last: Local<Option<Duration>>) {
+        if last.is_some_and(|last| time.elapsed() - last < REFRESH_INTERVAL) {
+            return;
+        }
+        *last = Some(time.elapsed());
+
+        let request = MasterMessage::ListRequest { page: 0 };
+
+        if let Err(error) = browser.socket.send_to(&request.encode(), browser.master) {
+            debug!(
+                "Could not reach master server {}: {}",
+                browser.master, error
+            );
+        }
+    }
+
+    fn receive(
+        mut browser: ResMut<BrowserSocket>,
+        mut servers: ResMut<MasterServers>,
+        time: Res<Time>,
+    ) {
+        let browser = &mut *browser;
+        let mut buffer = [0u8; MASTER_PACKET_BYTES];
+        let now = time.elapsed();
+
+        while let Ok((len, from)) = browser.socket.recv_from(&mut buffer) {
+            if from == browser.master {
+                let Some(MasterMessage::ServerList {
+                    page,
+                    more,
+                    servers: listed,
+                }) = MasterMessage::decode(&buffer[..len])
+                else {
+                    continue;
+                };
+
+                if more {
+                    let request = MasterMessage::ListRequest { page: page + 1 };
+                    let _ = browser.socket.send_to(&request.encode(), browser.master);
+                }
+
+                for listing in listed {
+                    let ip = listing.addr.ip();
+
+                    match servers
+                        .0
+                        .iter()
+                        .position(|server| server.listing.addr == listing.addr)
+                    {
+                        Some(index) => {
+                            servers.bypass_change_detection().0[index].last_seen = now;
+
+                            if servers.0[index].listing != listing {
+                                servers.0[index].listing = listing;
+                            }
+                        }
+                        None => servers.0.push(BrowsedServer {
+                            listing,
+                            ping: None,
+                            last_seen: now,
+                        }),
+                    }
+
+                    // Servers answer LAN discovery probes, which doubles as a ping.
+                    let probe = SocketAddr::new(ip, DISCOVERY_PORT);
+                    if browser
+                        .socket
+                        .send_to(&DiscoveryMessage::Probe.encode(), probe)
+                        .is_ok()
+                    {
+                        browser.probes.insert(ip, Instant::now());
+                    }
+                }
+
+                continue;
+            }
+
+            let Some(DiscoveryMessage::Announcement(_)) = DiscoveryMessage::decode(&buffer[..len])
+            else {
+                continue;
+            };
+
+            let Some(sent) = browser.probes.remove(&from.ip()) else {
+                continue;
+            };
+
+            let ping = sent.elapsed();
+
+            for server in servers
+                .0
+                .iter_mut()
+                .filter(|server| server.listing.addr.ip() == from.ip())
+            {
+                server.ping = Some(ping);
+            }
+        }
+
+        // Only touch the list when something expired, the menu rebuilds it on every change.
+        if servers
+            .0
+            .iter()
+            .any(|server| now - server.last_seen > SERVER_TIMEOUT)
+        {
+            servers
+                .0
+                .retain(|server| now - server.last_seen <= SERVER_TIMEOUT);
+        }
+    }
+}
+
+impl Plugin for BrowserPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MasterServers::default());
+        app.add_systems(Startup, Self::bind);
+        app.add_systems(
+            Update,
+            (Self::refresh, Self::receive).chain().run_if(
+                resource_exists::<BrowserSocket>
+                    .and(in_state(AppState::ConnectMenu))
+                    .and(not(resource_exists::<RenetClient>)),
+            ),
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use std::{net::SocketAddr, time::Duration};
+
+use bevy::prelude::*;
+use bevy_renet::{netcode::NetcodeClientTransport, renet::RenetClient};
+
+use crate::{
+    client::{
+        AppState,
+        network::{
+            encryption::{Nonce, ReceivedNonce, SskStore},
+            session::{ClientProfile, ClientSession, CommLink, Partner},
+        },
+    },
+    common::netsim::NetworkConditioner,
+};
+
+/// How long netcode may take to accept the connection.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long the KEM handshake and the Welcome may take once netcode is connected.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where the connection to the server stands.
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ConnectionState {
+    #[default]
+    Offline,
+    /// Waiting for netcode to accept the connect token.
+    Connecting,
+    /// Connected, waiting for the KEM handshake and the Welcome.
+    Handshaking,
+    Connected,
+    /// The connection never came up.
+    Failed(String),
+    /// An established connection ended.
+    Disconnected(String),
+}
+
+impl ConnectionState {
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self,
+            ConnectionState::Connecting | ConnectionState::Handshaking | ConnectionState::Connected
+        )
+    }
+}
+
+/// The server being connected to and when the current step started.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct ConnectionAttempt {
+    pub addr: SocketAddr,
+    pub since: Duration,
+}
+
+/// Drops the transport and forgets the session keys. The server is told when possible.
+fn close_transport(world: &mut World) {
+    if let Some(mut transport) = world.get_resource_mut::<NetcodeClientTransport>() {
+        transport.disconnect();
+    }
+
+    world.remove_resource::<RenetClient>();
+    world.remove_resource::<NetcodeClientTransport>();
+    world.remove_resource::<NetworkConditioner>();
+    world.remove_resource::<ConnectionAttempt>();
+    world.insert_resource(SskStore([0u8; 32].into()));
+    world.insert_resource(Nonce([0u8; 12]));
+    world.insert_resource(ReceivedNonce::default());
+}
+
+/// Closes the connection but keeps the resume token, so connecting again reclaims the slot.
+pub fn drop_connection(commands: &mut Commands, state: ConnectionState) {
+    commands.queue(|world: &mut World| {
+        close_transport(world);
+        world.resource_mut::<ClientSession>().role = None;
+    });
+    commands.set_state(state);
+}
+
+/// Leaves the server, or stops connecting to it, on the player's request.
+pub fn disconnect(commands: &mut Commands) {
+    info!("Disconnecting from server.");
+
+    commands.queue(close_transport);
+    commands.insert_resource(ClientSession::default());
+    commands.insert_resource(Partner::default());
+    commands.insert_resource(ClientProfile::default());
+    commands.insert_resource(CommLink::default());
+    commands.set_state(ConnectionState::Offline);
+}
+
+/// Tears down the connection after the server told us to leave and returns to the main menu.
+pub fn end_connection(commands: &mut Commands, reason: String) {
+    info!("Disconnected by server: {}", reason);
+
+    commands.queue(close_transport);
+    commands.insert_resource(ClientSession::default());
+    commands.insert_resource(Partner::default());
+    commands.insert_resource(ClientProfile::default());
+    commands.insert_resource(CommLink::default());
+    commands.set_state(ConnectionState::Disconnected(reason));
+    commands.set_state(AppState::MainMenu);
+}
+
+fn lost_reason(client: &RenetClient, transport: Option<&NetcodeClientTransport>) -> String {
+    if let Some(reason) = transport.and_then(|transport| transport.disconnect_reason()) {
+        return format!("{:?}", reason);
+    }
+
+    match client.disconnect_reason() {
+        Some(reason) => format!("{:?}", reason),
+        None => "Connection lost".to_string(),
+    }
+}
+
+/// Moves the connection through its states and gives up on steps that take too long.
+pub fn track_connection(
+    mut commands: Commands,
+    state: Res<State<ConnectionState>>,
+    client: Option<Res<RenetClient>>,
+    transport: Option<Res<NetcodeClientTransport>>,
+    mut attempt: Option<ResMut<ConnectionAttempt>>,
+    session: Res<ClientSession>,
+    time: Res<Time>,
+) {
+    if !state.is_active() {
+        return;
+    }
+
+    let Some(client) = client else {
+        return;
+    };
+
+    let now = time.elapsed();
+    let waited = attempt
+        .as_ref()
+        .map_or(Duration::ZERO, |attempt| now - attempt.since);
+
+    if client.is_disconnected() {
+        let reason = lost_reason(&client, transport.as_deref());
+        warn!("Connection ended in {:?}: {}", state.get(), reason);
+
+        let next = match state.get() {
+            ConnectionState::Connected => ConnectionState::Disconnected(reason),
+            _ => ConnectionState::Failed(reason),
+        };
+
+        if *state.get() == ConnectionState::Connected {
+            commands.set_state(AppState::MainMenu);
+        }
+
+        drop_connection(&mut commands, next);
+        return;
+    }
+
+    match state.get() {
+        ConnectionState::Connecting => {
+            if client.is_connected() {
+                info!("Connected, waiting for the handshake.");
+
+                if let Some(attempt) = &mut attempt {
+                    attempt.since = now;
+                }
+                commands.set_state(ConnectionState::Handshaking);
+            } else if waited > CONNECT_TIMEOUT {
+                let reason = "Server did not answer".to_string();
+                warn!("{}", reason);
+                drop_connection(&mut commands, ConnectionState::Failed(reason));
+            }
+        }
+
+        ConnectionState::Handshaking => {
+            if session.role.is_some() {
+                commands.set_state(ConnectionState::Connected);
+                commands.set_state(AppState::MainMenu);
+            } else if waited > HANDSHAKE_TIMEOUT {
+                let reason = "Handshake timed out".to_string();
+                warn!("{}", reason);
+                drop_connection(&mut commands, ConnectionState::Failed(reason));
+            }
+        }
+
+        _ => {}
+    }
+}
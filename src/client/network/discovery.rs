@@ -0,0 +1,130 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_renet::renet::RenetClient;
+
+use crate::{
+    client::AppState,
+    common::discovery::{DISCOVERY_PORT, DiscoveryMessage, PROBE_BYTES, ServerAnnouncement},
+};
+
+const PROBE_INTERVAL: Duration = Duration::from_secs(2);
+/// Servers that stop answering drop off the list after this long.
+const SERVER_TIMEOUT: Duration = Duration::from_secs(6);
+
+/// Finds servers on the local network while the connect screen is open.
+pub struct DiscoveryPlugin;
+
+#[derive(Debug, Clone)]
+pub struct LanServer {
+    /// Game transport address: the announcement's sender with its advertised port.
+    pub addr: SocketAddr,
+    pub announcement: ServerAnnouncement,
+    pub last_seen: Duration,
+}
+
+/// Servers that answered recent discovery probes, in the order they were found.
+#[derive(Resource, Default, Debug)]
+pub struct LanServers(pub Vec<LanServer>);
+
+#[derive(Resource)]
+struct ProbeSocket(UdpSocket);
+
+impl DiscoveryPlugin {
+    fn bind(mut commands: Commands) {
+        let socket = UdpSocket::bind("0.0.0.0:0").and_then(|socket| {
+            socket.set_broadcast(true)?;
+            socket.set_nonblocking(true)?;
+            Ok(socket)
+        });
+
+        match socket {
+            Ok(socket) => commands.insert_resource(ProbeSocket(socket)),
+            Err(error) => warn!("LAN discovery unavailable: {}", error),
+        }
+    }
+
+    fn probe(socket: Res<ProbeSocket>, time: Res<Time>, mut last_probe: Local<Option<Duration>>) {
+        if last_probe.is_some_and(|last| time.elapsed() - last < PROBE_INTERVAL) {
+            return;
+        }
+        *last_probe = Some(time.elapsed());
+
+        let broadcast = SocketAddr::from(([255, 255, 255, 255], DISCOVERY_PORT));
+
+        if let Err(error) = socket
+            .0
+            .send_to(&DiscoveryMessage::Probe.encode(), broadcast)
+        {
+            debug!("Could not send discovery probe: {}", error);
+        }
+    }
+
+    fn collect_announcements(
+        socket: Res<ProbeSocket>,
+        mut servers: ResMut<LanServers>,
+        time: Res<Time>,
+    ) {
+        let mut buffer = [0u8; PROBE_BYTES];
+        let now = time.elapsed();
+
+        while let Ok((len, from)) = socket.0.recv_from(&mut buffer) {
+            let Some(DiscoveryMessage::Announcement(announcement)) =
+                DiscoveryMessage::decode(&buffer[..len])
+            else {
+                continue;
+            };
+
+            let addr = SocketAddr::new(from.ip(), announcement.port);
+
+            match servers.0.iter().position(|server| server.addr == addr) {
+                Some(index) => {
+                    // Refreshing a server only changes the list when what it says changed.
+                    servers.bypass_change_detection().0[index].last_seen = now;
+
+                    if servers.0[index].announcement != announcement {
+                        servers.0[index].announcement = announcement;
+                    }
+                }
+                None => {
+                    info!("Found LAN server {:?} at {}", announcement.name, addr);
+
+                    servers.0.push(LanServer {
+                        addr,
+                        announcement,
+                        last_seen: now,
+                    });
+                }
+            }
+        }
+
+        // Only touch the list when something expired, the menu rebuilds it on every change.
+        if servers
+            .0
+            .iter()
+            .any(|server| now - server.last_seen > SERVER_TIMEOUT)
+        {
+            servers
+                .0
+                .retain(|server| now - server.last_seen <= SERVER_TIMEOUT);
+        }
+    }
+}
+
+impl Plugin for DiscoveryPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LanServers::default());
+        app.add_systems(Startup, Self::bind);
+        app.add_systems(
+            Update,
+            (Self::probe, Self::collect_announcements).chain().run_if(
+                resource_exists::<ProbeSocket>
+                    .and(in_state(AppState::ConnectMenu))
+                    .and(not(resource_exists::<RenetClient>)),
+            ),
+        );
+    }
+}
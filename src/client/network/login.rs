@@ -1,10 +1,47 @@
 use bevy::ecs::resource::Resource;
+use zeroize::Zeroizing;
 
-use crate::common::network::UserData;
+use crate::common::network::ClientMessage;
 
-#[derive(Default, Clone, Copy, Resource)]
+#[derive(Clone)]
+pub struct Credentials {
+    pub username: String,
+    pub password: Zeroizing<String>,
+    /// Create the account on the server. Cleared once the server accepts it.
+    pub register: bool,
+}
+
+impl Credentials {
+    pub fn message(&self) -> ClientMessage {
+        let username = self.username.clone();
+        let password = self.password.to_string();
+
+        if self.register {
+            ClientMessage::Register { username, password }
+        } else {
+            ClientMessage::Login { username, password }
+        }
+    }
+}
+
+/// Account the client logs in with after every KEM handshake, so reconnecting logs in again.
+#[derive(Default, Clone, Resource)]
 pub enum UserLogin {
-    LoggedIn(UserData),
+    /// Sent, waiting for the server.
+    LoggingIn(Credentials),
+    /// Accepted by the server on the current connection.
+    LoggedIn(Credentials),
     #[default]
     NotLoggedIn,
 }
+
+impl UserLogin {
+    pub fn credentials(&self) -> Option<&Credentials> {
+        match self {
+            UserLogin::LoggingIn(credentials) | UserLogin::LoggedIn(credentials) => {
+                Some(credentials)
+            }
+            UserLogin::NotLoggedIn => None,
+        }
+    }
+}
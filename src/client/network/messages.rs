@@ -1,17 +1,33 @@
 use bevy::prelude::*;
 use bevy_renet::renet::{DefaultChannel, RenetClient};
-use cryptoxide::chacha20poly1305::ChaCha20Poly1305;
 use fips203::traits::SerDes;
 
 use crate::{
-    client::network::encryption::{Nonce, SskStore, get_ciphertext},
-    common::network::{ClientMessage, NETWORK_CHANNELS, ServerMessage},
+    client::{
+        network::{
+            connection::{ConnectionState, drop_connection, end_connection},
+            encryption::{Nonce, ReceivedNonce, SskStore, get_ciphertext},
+            login::UserLogin,
+            session::{
+                ClientProfile, ClientSession, CommLink, Partner, PartnerChat, PartnerVoice,
+                QuickSignal, ServerNotice,
+            },
+        },
+        world::player::Player,
+    },
+    common::{
+        encryption::{Direction, decrypt},
+        network::{ClientMessage, NETWORK_CHANNELS, ServerMessage},
+        world::WorldSeed,
+    },
 };
 
 pub fn receive_kem_messages(
     mut client: ResMut<RenetClient>,
+    mut commands: Commands,
     mut ssks: ResMut<SskStore>,
     mut nonce_res: ResMut<Nonce>,
+    mut received_res: ResMut<ReceivedNonce>,
 ) {
     let channel_id = 3;
     while let Some(message) = client.receive_message(channel_id) {
@@ -32,6 +48,13 @@ pub fn receive_kem_messages(
 
                 ssks.0 = ssk.into_bytes().into();
                 nonce_res.0 = [0u8; 12];
+                received_res.0 = Default::default();
+            }
+
+            ServerMessage::Kicked { reason } => {
+                end_connection(&mut commands, reason);
+
+                return;
             }
 
             _ => {}
@@ -44,38 +67,38 @@ pub fn receive_encrypted(
     mut commands: Commands,
     ssks: Res<SskStore>,
     mut nonce_res: ResMut<Nonce>,
+    mut received_res: ResMut<ReceivedNonce>,
+    mut session: ResMut<ClientSession>,
+    mut partner: ResMut<Partner>,
+    mut notice: ResMut<ServerNotice>,
+    mut login: ResMut<UserLogin>,
+    mut profile: ResMut<ClientProfile>,
+    mut comm: ResMut<CommLink>,
+    mut chats: MessageWriter<PartnerChat>,
+    mut voices: MessageWriter<PartnerVoice>,
+    mut signals: MessageWriter<QuickSignal>,
+    mut player: Query<&mut Transform, With<Player>>,
+    time: Res<Time>,
 ) {
     for channel_id in NETWORK_CHANNELS {
         if channel_id == 3 {
             continue;
         }
         while let Some(message) = client.receive_message(channel_id) {
-            let key = &*ssks.0;
-
-            let mut output = vec![0u8; message.len() - 16];
-
-            let nonce = &nonce_res.0;
+            let Some(plaintext) = decrypt(&ssks.0, Direction::ServerToClient, &message) else {
+                warn!(
+                    "Could not decrypt server message on channel {}.",
+                    channel_id
+                );
+                continue;
+            };
 
-            let aad = &[0u8; 0];
-
-            let mut cipher = ChaCha20Poly1305::new(key, nonce, aad);
-
-            cipher.decrypt(
-                &message[..message.len() - 16],
-                &mut output,
-                &message[message.len() - 16..],
-            );
-
-            for i in (0..12).rev() {
-                if nonce_res.0[i] == 255 {
-                    nonce_res.0[i] = 0;
-                } else {
-                    nonce_res.0[i] += 1;
-                    break;
-                }
+            if !received_res.0.advance(channel_id, &message) {
+                warn!("Dropped replayed server message on channel {}.", channel_id);
+                continue;
             }
 
-            let message = output;
+            let message = plaintext;
 
             let (server_message, _) = bincode::decode_from_slice::<ServerMessage, _>(
                 &message,
@@ -100,6 +123,139 @@ pub fn receive_encrypted(
 
                     commands.insert_resource(SskStore(ssk.into_bytes().into()));
                 }
+
+                ServerMessage::HandshakeComplete => {
+                    info!("KEM handshake complete.");
+
+                    let Some(credentials) = login.credentials().cloned() else {
+                        drop_connection(
+                            &mut commands,
+                            ConnectionState::Failed("Not logged in".to_string()),
+                        );
+                        return;
+                    };
+
+                    ClientMessage::send_encrypted(
+                        &mut client,
+                        &ssks.0,
+                        &credentials.message(),
+                        &mut nonce_res,
+                    );
+
+                    *login = UserLogin::LoggingIn(credentials);
+                }
+
+                ServerMessage::LoginAccepted { username } => {
+                    info!("Logged in as {}.", username);
+
+                    if let UserLogin::LoggingIn(credentials) = &*login {
+                        let mut credentials = credentials.clone();
+                        credentials.register = false;
+                        *login = UserLogin::LoggedIn(credentials);
+                    }
+
+                    let hello = ClientMessage::Hello {
+                        resume_token: session.resume_token.as_deref().copied(),
+                    };
+
+                    ClientMessage::send_encrypted(&mut client, &ssks.0, &hello, &mut nonce_res);
+                }
+
+                ServerMessage::LoginRejected { reason } => {
+                    warn!("Login rejected: {}", reason);
+
+                    *login = UserLogin::NotLoggedIn;
+                    drop_connection(&mut commands, ConnectionState::Failed(reason));
+
+                    return;
+                }
+
+                ServerMessage::Welcome {
+                    role,
+                    resume_token,
+                    resumed,
+                    translation,
+                    seed,
+                } => {
+                    info!(
+                        "Joined match as {:?} (resumed: {}, seed: {}).",
+                        role, resumed, seed
+                    );
+
+                    session.role = Some(role);
+                    session.resume_token = Some(resume_token.into());
+                    commands.insert_resource(WorldSeed(seed));
+
+                    if let Ok(mut transform) = player.single_mut() {
+                        transform.translation = Vec3::from_array(translation);
+                    }
+                }
+
+                ServerMessage::PartnerStatus(status) => {
+                    info!("Partner status: {:?}", status);
+
+                    partner.status = status;
+                    partner.since = time.elapsed();
+                }
+
+                ServerMessage::Notice(text) => {
+                    info!("Server notice: {}", text);
+
+                    *notice = ServerNotice {
+                        text,
+                        received: time.elapsed(),
+                    };
+                }
+
+                ServerMessage::Profile(received) => {
+                    profile.0 = Some(received);
+                }
+
+                ServerMessage::Chat {
+                    text,
+                    clarity,
+                    translation,
+                } => {
+                    chats.write(PartnerChat {
+                        text,
+                        clarity,
+                        translation: Vec3::from_array(translation),
+                    });
+                }
+
+                ServerMessage::Signal {
+                    from,
+                    id,
+                    translation,
+                } => {
+                    signals.write(QuickSignal {
+                        from,
+                        id,
+                        translation: Vec3::from_array(translation),
+                    });
+                }
+
+                ServerMessage::Comm { state, clarity } => {
+                    *comm = CommLink { state, clarity };
+                }
+
+                ServerMessage::Voice {
+                    frame,
+                    gain,
+                    translation,
+                } => {
+                    voices.write(PartnerVoice {
+                        frame,
+                        gain,
+                        translation: Vec3::from_array(translation),
+                    });
+                }
+
+                ServerMessage::Shutdown { reason } | ServerMessage::Kicked { reason } => {
+                    end_connection(&mut commands, reason);
+
+                    return;
+                }
             }
         }
     }
@@ -1,13 +1,13 @@
 use std::{
     net::{SocketAddr, UdpSocket},
-    time::{Duration, SystemTime},
+    time::SystemTime,
 };
 
 use bevy::prelude::*;
 use bevy_renet::{
     client_connected,
     netcode::{ClientAuthentication, ConnectToken, NetcodeClientTransport},
-    renet::{ChannelConfig, ConnectionConfig, RenetClient},
+    renet::RenetClient,
 };
 use local_ip_address::local_ip;
 use rand::TryRngCore;
@@ -17,44 +17,57 @@ use crate::{
     client::{
         AppState,
         network::{
-            encryption::{Nonce, SskStore},
+            connection::{ConnectionAttempt, ConnectionState, track_connection},
+            encryption::{Nonce, ReceivedNonce, SskStore},
             login::UserLogin,
             messages::{receive_encrypted, receive_kem_messages},
+            session::{
+                ClientProfile, ClientSession, CommLink, Partner, PartnerChat, PartnerVoice,
+                QuickSignal, ServerNotice, request_profile, send_player_state,
+            },
         },
     },
-    common::network::{UserData, get_private_key_env},
+    common::{
+        netsim::{LinkConditions, NetworkConditioner},
+        network::{SERVER_PORT, UserData, connection_config, get_private_key_env},
+    },
 };
+pub mod browser;
+pub mod connection;
+pub mod discovery;
 pub mod encryption;
 pub mod login;
 pub mod messages;
+pub mod recent;
+pub mod session;
 
 pub struct NetworkPlugin;
 
+/// Server to connect to. Without it the client looks for a server on its own machine.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct ServerAddress(pub SocketAddr);
+
 impl NetworkPlugin {
-    fn connect_to_server(mut commands: Commands, user: Res<UserLogin>) {
-        let mut connection_config = ConnectionConfig::default();
-
-        connection_config
-            .client_channels_config
-            .push(ChannelConfig {
-                channel_id: 3,
-                send_type: bevy_renet::renet::SendType::ReliableOrdered {
-                    resend_time: Duration::from_millis(300),
-                },
-                max_memory_usage_bytes: 5 * 1024 * 1024,
-            });
-
-        connection_config
-            .server_channels_config
-            .push(ChannelConfig {
-                channel_id: 3,
-                send_type: bevy_renet::renet::SendType::ReliableOrdered {
-                    resend_time: Duration::from_millis(300),
-                },
-                max_memory_usage_bytes: 5 * 1024 * 1024,
-            });
-
-        let client = RenetClient::new(connection_config);
+    fn connect_to_server(
+        mut commands: Commands,
+        user: Res<UserLogin>,
+        server_address: Option<Res<ServerAddress>>,
+        transport: Option<ResMut<NetcodeClientTransport>>,
+        mut session: ResMut<ClientSession>,
+        time: Res<Time>,
+    ) {
+        // Leave a previous server properly before replacing its transport.
+        if let Some(mut transport) = transport {
+            transport.disconnect();
+        }
+
+        let Some(credentials) = user.credentials() else {
+            warn!("Cannot connect without logging in.");
+            commands.set_state(ConnectionState::Failed("Not logged in".to_string()));
+            return;
+        };
+
+        let client = RenetClient::new(connection_config());
 
         commands.insert_resource(client);
 
@@ -64,15 +77,30 @@ impl NetworkPlugin {
 
         let client_id = rand::rngs::OsRng.try_next_u64().unwrap_or_default();
 
-        let server_addr = SocketAddr::new(local_ip().expect("Cannot find local ip."), 42069);
+        let server_addr = match server_address {
+            Some(address) => address.0,
+            None => SocketAddr::new(local_ip().expect("Cannot find local ip."), SERVER_PORT),
+        };
+
+        let mut server_addresses = vec![server_addr];
 
-        let mut private_key = get_private_key_env();
+        // The transport tries token addresses in order, so a local conditioner goes first. The
+        // real address stays in the token because the server only accepts its own addresses.
+        if let Some(conditions) = LinkConditions::from_env() {
+            let bind = SocketAddr::from(([127, 0, 0, 1], 0));
 
-        let user_data = match *user {
-            UserLogin::LoggedIn(data) => data.0,
+            match NetworkConditioner::spawn(bind, server_addr, conditions) {
+                Ok(conditioner) => {
+                    server_addresses.insert(0, conditioner.local_addr());
+                    commands.insert_resource(conditioner);
+                }
+                Err(error) => warn!("Could not start network conditioner: {}", error),
+            }
+        }
 
-            UserLogin::NotLoggedIn => UserData::from_str("Anon").0,
-        };
+        let mut private_key = get_private_key_env();
+
+        let user_data = UserData::from_str(&credentials.username).0;
 
         let connect_token = ConnectToken::generate(
             current_time,
@@ -80,7 +108,7 @@ impl NetworkPlugin {
             30,
             client_id,
             2 * 60,
-            vec![server_addr],
+            server_addresses,
             Some(&user_data),
             &private_key,
         )
@@ -97,7 +125,15 @@ impl NetworkPlugin {
 
         commands.insert_resource(transport);
 
-        commands.set_state(AppState::MainMenu);
+        info!("Connecting to {}", server_addr);
+
+        // A role belongs to one connection, the Welcome hands it out again.
+        session.role = None;
+        commands.insert_resource(ConnectionAttempt {
+            addr: server_addr,
+            since: time.elapsed(),
+        });
+        commands.set_state(ConnectionState::Connecting);
     }
 }
 
@@ -105,10 +141,34 @@ impl Plugin for NetworkPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(SskStore([0u8; 32].into()));
         app.insert_resource(Nonce([0u8; 12]));
+        app.insert_resource(ReceivedNonce::default());
+        app.insert_resource(ClientSession::default());
+        app.insert_resource(Partner::default());
+        app.insert_resource(ServerNotice::default());
+        app.insert_resource(ClientProfile::default());
+        app.insert_resource(CommLink::default());
+        app.add_message::<PartnerChat>();
+        app.add_message::<PartnerVoice>();
+        app.add_message::<QuickSignal>();
+        app.init_state::<ConnectionState>();
         app.add_systems(OnEnter(AppState::ConnectToServer), Self::connect_to_server);
+        app.add_systems(
+            OnEnter(AppState::ProfileMenu),
+            request_profile.run_if(in_state(ConnectionState::Connected)),
+        );
         app.add_systems(
             Update,
             (receive_kem_messages, receive_encrypted).run_if(client_connected),
         );
+        app.add_systems(
+            Update,
+            track_connection
+                .after(receive_encrypted)
+                .run_if(resource_exists::<RenetClient>),
+        );
+        app.add_systems(
+            Update,
+            send_player_state.run_if(client_connected.and(in_state(AppState::InGame))),
+        );
     }
 }
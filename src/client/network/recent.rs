@@ -0,0 +1,90 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+
+/// Entries kept in the recent servers file, newest first.
+pub const MAX_RECENT_SERVERS: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentServer {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+}
+
+/// Servers the player connected to before, stored one per line as `host port username`.
+///
+/// The file is `recent_servers.txt` in the working directory, or `ABSENT_CHROMA_RECENT_SERVERS`.
+#[derive(Resource, Debug, Default)]
+pub struct RecentServers {
+    path: PathBuf,
+    pub servers: Vec<RecentServer>,
+}
+
+impl RecentServers {
+    pub fn load_default() -> Self {
+        let path = env::var("ABSENT_CHROMA_RECENT_SERVERS")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from("recent_servers.txt"));
+
+        Self::load(&path)
+    }
+
+    /// Reads the file at `path`. A missing file is an empty list and bad lines are skipped.
+    pub fn load(path: &Path) -> Self {
+        let servers = match fs::read_to_string(path) {
+            Ok(contents) => contents
+                .lines()
+                .filter_map(|line| {
+                    let mut words = line.split_whitespace();
+
+                    Some(RecentServer {
+                        host: words.next()?.to_string(),
+                        port: words.next()?.parse().ok()?,
+                        username: words.next()?.to_string(),
+                    })
+                })
+                .take(MAX_RECENT_SERVERS)
+                .collect(),
+            Err(error) => {
+                if error.kind() != io::ErrorKind::NotFound {
+                    warn!(
+                        "Could not read recent servers {}: {}",
+                        path.display(),
+                        error
+                    );
+                }
+                vec![]
+            }
+        };
+
+        Self {
+            path: path.to_path_buf(),
+            servers,
+        }
+    }
+
+    /// Moves `server` to the top of the list and writes the file.
+    pub fn remember(&mut self, server: RecentServer) {
+        self.servers.retain(|recent| *recent != server);
+        self.servers.insert(0, server);
+        self.servers.truncate(MAX_RECENT_SERVERS);
+
+        let contents: String = self
+            .servers
+            .iter()
+            .map(|server| format!("{} {} {}\n", server.host, server.port, server.username))
+            .collect();
+
+        if let Err(error) = fs::write(&self.path, contents) {
+            warn!(
+                "Could not save recent servers {}: {}",
+                self.path.display(),
+                error
+            );
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_renet::renet::RenetClient;
+use zeroize::Zeroizing;
+
+use crate::{
+    client::{
+        network::encryption::{Nonce, SskStore},
+        world::player::Player,
+    },
+    common::{
+        network::{ClientMessage, PartnerStatus, PlayerProfile, ResumeToken, Role},
+        tether::CommState,
+        voice::VoiceFrame,
+    },
+};
+
+const PLAYER_STATE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Role and resume token handed out by the server for the current match.
+///
+/// The token survives a dropped connection so the next [`ClientMessage::Hello`] can reclaim
+/// the same slot.
+#[derive(Resource, Default)]
+pub struct ClientSession {
+    pub role: Option<Role>,
+    pub resume_token: Option<Zeroizing<ResumeToken>>,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct Partner {
+    pub status: PartnerStatus,
+    /// When `status` last changed, used to count down [`PartnerStatus::Reconnecting`].
+    pub since: Duration,
+}
+
+/// Latest [`ServerMessage::Notice`](crate::common::network::ServerMessage::Notice) from the
+/// server operator.
+#[derive(Resource, Default, Debug)]
+pub struct ServerNotice {
+    pub text: String,
+    pub received: Duration,
+}
+
+/// How well the partner can be reached, as last sent by the server.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct CommLink {
+    pub state: CommState,
+    /// 1.0 while clear, falling to 0.0 when lost.
+    pub clarity: f32,
+}
+
+impl Default for CommLink {
+    fn default() -> Self {
+        Self {
+            state: CommState::Clear,
+            clarity: 1.0,
+        }
+    }
+}
+
+/// Chat from the partner, as much of it as made it across the distance.
+#[derive(Message, Debug, Clone)]
+pub struct PartnerChat {
+    pub text: String,
+    pub clarity: f32,
+    /// Where the partner was when they said it.
+    pub translation: Vec3,
+}
+
+/// A quick signal from either player, to be shown to Gray and played to Note.
+#[derive(Message, Debug, Clone)]
+pub struct QuickSignal {
+    pub from: Role,
+    pub id: String,
+    pub translation: Vec3,
+}
+
+/// A frame of partner voice, with the loudness the distance left it.
+#[derive(Message, Debug, Clone)]
+pub struct PartnerVoice {
+    pub frame: VoiceFrame,
+    pub gain: f32,
+    pub translation: Vec3,
+}
+
+/// The logged in account's profile, as last sent by the server. `None` while it is on its way.
+#[derive(Resource, Default, Debug)]
+pub struct ClientProfile(pub Option<PlayerProfile>);
+
+/// Asks the server for a fresh profile.
+pub fn request_profile(
+    mut client: ResMut<RenetClient>,
+    ssks: Res<SskStore>,
+    mut nonce_res: ResMut<Nonce>,
+    mut profile: ResMut<ClientProfile>,
+) {
+    profile.0 = None;
+
+    ClientMessage::send_encrypted(
+        &mut client,
+        &ssks.0,
+        &ClientMessage::ProfileRequest,
+        &mut nonce_res,
+    );
+}
+
+pub fn send_player_state(
+    mut client: ResMut<RenetClient>,
+    ssks: Res<SskStore>,
+    mut nonce_res: ResMut<Nonce>,
+    session: Res<ClientSession>,
+    player: Query<&Transform, With<Player>>,
+    time: Res<Time>,
+    mut last_sent: Local<Duration>,
+) {
+    if session.role.is_none() || time.elapsed() - *last_sent < PLAYER_STATE_INTERVAL {
+        return;
+    }
+
+    let Ok(transform) = player.single() else {
+        return;
+    };
+
+    *last_sent = time.elapsed();
+
+    ClientMessage::send_encrypted(
+        &mut client,
+        &ssks.0,
+        &ClientMessage::PlayerState {
+            translation: transform.translation.to_array(),
+        },
+        &mut nonce_res,
+    );
+}
@@ -1,9 +1,8 @@
 use bevy::prelude::*;
-use bevy_renet::RenetClientPlugin;
-use bevy_renet::netcode::NetcodeClientPlugin;
 
+#[cfg(feature = "audio")]
+use super::audio;
 use super::controls;
-use super::network;
 use super::ui;
 use super::world;
 
@@ -14,10 +13,7 @@ impl Plugin for SuperPlugin {
         app.add_plugins(ui::UiPlugin);
         app.add_plugins(world::WorldPlugin);
         app.add_plugins(controls::ControlsPlugin);
-
-        app.add_plugins(RenetClientPlugin);
-        app.add_plugins(NetcodeClientPlugin);
-
-        app.add_plugins(network::NetworkPlugin);
+        #[cfg(feature = "audio")]
+        app.add_plugins(audio::CuesPlugin);
     }
 }
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::client::{AppState, PreviousAppState, ui::UiLabelType};
+use crate::client::{AppState, PreviousAppState, network::connection::disconnect, ui::UiLabelType};
 
 pub fn listen_ui_input(
     query: Query<(&UiLabelType, &Interaction), Changed<Interaction>>,
@@ -27,7 +27,15 @@ pub fn listen_ui_input(
 
                 UiLabelType::Connect => {
                     app_state_log.0 = Some(AppState::MainMenu);
-                    commands.set_state(AppState::ConnectToServer);
+                    commands.set_state(AppState::ConnectMenu);
+                }
+
+                UiLabelType::Profile => {
+                    commands.set_state(AppState::ProfileMenu);
+                }
+
+                UiLabelType::Disconnect => {
+                    disconnect(&mut commands);
                 }
 
                 UiLabelType::Exit => {
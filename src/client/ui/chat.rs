@@ -0,0 +1,200 @@
+use std::time::Duration;
+
+use bevy::{
+    input::{
+        ButtonState,
+        keyboard::{Key, KeyboardInput},
+    },
+    prelude::*,
+};
+use bevy_renet::renet::RenetClient;
+
+use crate::{
+    client::network::{
+        encryption::{Nonce, SskStore},
+        session::{ClientSession, PartnerChat},
+    },
+    common::{
+        chat::{MAX_CHAT_CHARS, sanitize},
+        network::{ClientMessage, Role},
+    },
+};
+
+const BUBBLE_DURATION: Duration = Duration::from_secs(6);
+
+/// Bubbles kept on screen at once, oldest dropped first.
+const MAX_BUBBLES: usize = 5;
+
+/// Message being typed. Enter opens it, Enter again sends it and Escape throws it away.
+#[derive(Resource, Default, Debug)]
+pub struct ChatInput {
+    pub open: bool,
+    pub text: String,
+}
+
+/// Movement and menu keys are left alone while a message is being typed.
+pub fn chat_closed(input: Res<ChatInput>) -> bool {
+    !input.open
+}
+
+#[derive(Component, Clone)]
+pub struct ChatInputText;
+
+/// Column of partner chat bubbles on Gray's HUD.
+#[derive(Component, Clone)]
+pub struct ChatBubbles;
+
+#[derive(Component, Clone)]
+pub struct ChatBubble {
+    expires: Duration,
+}
+
+pub fn type_chat(
+    mut keys: MessageReader<KeyboardInput>,
+    mut input: ResMut<ChatInput>,
+    mut buttons: ResMut<ButtonInput<KeyCode>>,
+    client: Option<ResMut<RenetClient>>,
+    ssks: Res<SskStore>,
+    mut nonce_res: ResMut<Nonce>,
+    session: Res<ClientSession>,
+) {
+    let mut client = client.filter(|client| client.is_connected() && session.role.is_some());
+
+    for key in keys.read() {
+        if key.state != ButtonState::Pressed {
+            continue;
+        }
+
+        if !input.open {
+            if key.logical_key == Key::Enter {
+                input.open = true;
+            }
+            continue;
+        }
+
+        match &key.logical_key {
+            Key::Character(text) => {
+                for c in text.chars().filter(|c| !c.is_control()) {
+                    if input.text.chars().count() < MAX_CHAT_CHARS {
+                        input.text.push(c);
+                    }
+                }
+            }
+
+            Key::Space => {
+                if input.text.chars().count() < MAX_CHAT_CHARS {
+                    input.text.push(' ');
+                }
+            }
+
+            Key::Backspace => {
+                input.text.pop();
+            }
+
+            Key::Enter => {
+                if let (Some(text), Some(client)) = (sanitize(&input.text), client.as_mut()) {
+                    ClientMessage::send_encrypted(
+                        client,
+                        &ssks.0,
+                        &ClientMessage::Chat { text },
+                        &mut nonce_res,
+                    );
+                }
+
+                *input = ChatInput::default();
+            }
+
+            Key::Escape => {
+                // Still held next frame, where it would open the menu.
+                buttons.reset(KeyCode::Escape);
+                *input = ChatInput::default();
+            }
+
+            _ => {}
+        }
+    }
+}
+
+pub fn show_chat_input(
+    input: Res<ChatInput>,
+    mut query: Query<(&mut Text, &mut Visibility), With<ChatInputText>>,
+) {
+    if !input.is_changed() {
+        return;
+    }
+
+    let Ok((mut text, mut visibility)) = query.single_mut() else {
+        return;
+    };
+
+    text.0 = format!("Say: {}_", input.text);
+    *visibility = if input.open {
+        Visibility::Visible
+    } else {
+        Visibility::Hidden
+    };
+}
+
+/// Gray cannot hear, so partner chat shows up as text, fainter the farther away it was said.
+pub fn show_chat_bubbles(
+    mut commands: Commands,
+    mut chats: MessageReader<PartnerChat>,
+    session: Res<ClientSession>,
+    time: Res<Time>,
+    columns: Query<Entity, With<ChatBubbles>>,
+    bubbles: Query<(Entity, &ChatBubble)>,
+) {
+    let now = time.elapsed();
+
+    let mut live: Vec<(Entity, Duration)> = bubbles
+        .iter()
+        .filter_map(|(entity, bubble)| {
+            if bubble.expires <= now {
+                commands.entity(entity).despawn();
+                None
+            } else {
+                Some((entity, bubble.expires))
+            }
+        })
+        .collect();
+
+    let Ok(column) = columns.single() else {
+        return;
+    };
+
+    for chat in chats.read() {
+        if session.role != Some(Role::Gray) {
+            continue;
+        }
+
+        let bubble = commands
+            .spawn((
+                Text::new(chat.text.clone()),
+                TextFont {
+                    font_size: 30.,
+                    ..default()
+                },
+                TextColor(Color::WHITE.with_alpha(0.4 + 0.6 * chat.clarity)),
+                Node {
+                    padding: UiRect::axes(Val::Px(12.), Val::Px(6.)),
+                    max_width: Val::Px(800.),
+                    ..default()
+                },
+                BackgroundColor(Color::BLACK.with_alpha(0.6)),
+                BorderRadius::all(Val::Px(8.)),
+                ChatBubble {
+                    expires: now + BUBBLE_DURATION,
+                },
+            ))
+            .id();
+
+        commands.entity(column).add_child(bubble);
+        live.push((bubble, now + BUBBLE_DURATION));
+    }
+
+    live.sort_by_key(|(_, expires)| *expires);
+
+    for (entity, _) in live.iter().take(live.len().saturating_sub(MAX_BUBBLES)) {
+        commands.entity(*entity).despawn();
+    }
+}
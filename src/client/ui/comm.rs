@@ -0,0 +1,101 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use rand::Rng;
+
+use crate::{
+    client::network::session::{ClientSession, CommLink},
+    common::{network::Role, tether::CommState},
+};
+
+/// Size of the static texture, stretched over the whole screen.
+const STATIC_WIDTH: u32 = 160;
+const STATIC_HEIGHT: u32 = 90;
+
+/// Static opacity once the link is lost. Gray still has to see where it is going.
+const MAX_STATIC_ALPHA: f32 = 0.6;
+
+/// Link state in the corner, for both players.
+#[derive(Component, Clone)]
+pub struct CommText;
+
+/// Full-screen static Gray sees while the link to Note is breaking up.
+#[derive(Component, Clone)]
+pub struct StaticOverlay;
+
+pub fn static_image() -> Image {
+    Image::new_fill(
+        Extent3d {
+            width: STATIC_WIDTH,
+            height: STATIC_HEIGHT,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
+
+pub fn show_comm_state(
+    comm: Res<CommLink>,
+    mut query: Query<(&mut Text, &mut TextColor, &mut Visibility), With<CommText>>,
+) {
+    if !comm.is_changed() {
+        return;
+    }
+
+    let Ok((mut text, mut color, mut visibility)) = query.single_mut() else {
+        return;
+    };
+
+    match comm.state {
+        CommState::Clear => *visibility = Visibility::Hidden,
+        CommState::Degraded => {
+            text.0 = format!("Signal weak ({:.0}%)", comm.clarity * 100.);
+            color.0 = Color::srgb(1., 0.8, 0.);
+            *visibility = Visibility::Visible;
+        }
+        CommState::Lost => {
+            text.0 = "Signal lost".to_string();
+            color.0 = Color::srgb(1., 0.3, 0.3);
+            *visibility = Visibility::Visible;
+        }
+    }
+}
+
+/// Fresh noise every frame, thicker the worse the link. Note hears a crackle instead.
+pub fn show_static(
+    comm: Res<CommLink>,
+    session: Res<ClientSession>,
+    mut overlays: Query<(&mut ImageNode, &mut Visibility), With<StaticOverlay>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Ok((mut node, mut visibility)) = overlays.single_mut() else {
+        return;
+    };
+
+    if session.role != Some(Role::Gray) || comm.state == CommState::Clear {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+
+    *visibility = Visibility::Visible;
+    node.color = Color::WHITE.with_alpha((1. - comm.clarity.clamp(0., 1.)) * MAX_STATIC_ALPHA);
+
+    let Some(data) = images
+        .get_mut(&node.image)
+        .and_then(|image| image.data.as_mut())
+    else {
+        return;
+    };
+
+    let mut rng = rand::rng();
+
+    for pixel in data.chunks_exact_mut(4) {
+        let value: u8 = rng.random();
+        pixel[..3].fill(value);
+    }
+}
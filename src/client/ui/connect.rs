@@ -0,0 +1,563 @@
+use std::net::{SocketAddr, ToSocketAddrs};
+
+use bevy::{
+    input::{
+        ButtonState,
+        keyboard::{Key, KeyboardInput},
+    },
+    prelude::*,
+};
+use local_ip_address::local_ip;
+use zeroize::Zeroizing;
+
+use crate::{
+    client::{
+        AppState,
+        network::{
+            ServerAddress,
+            browser::MasterServers,
+            discovery::LanServers,
+            login::{Credentials, UserLogin},
+            recent::{RecentServer, RecentServers},
+        },
+    },
+    common::{
+        account::{MAX_PASSWORD_CHARS, MAX_USERNAME_CHARS, validate_password, validate_username},
+        network::SERVER_PORT,
+    },
+};
+
+const MAX_HOST_CHARS: usize = 253;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConnectField {
+    Host,
+    Port,
+    Username,
+    Password,
+}
+
+impl ConnectField {
+    fn iter() -> impl Iterator<Item = ConnectField> {
+        [
+            ConnectField::Host,
+            ConnectField::Port,
+            ConnectField::Username,
+            ConnectField::Password,
+        ]
+        .into_iter()
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            ConnectField::Host => "Host",
+            ConnectField::Port => "Port",
+            ConnectField::Username => "Username",
+            ConnectField::Password => "Password",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            ConnectField::Host => ConnectField::Port,
+            ConnectField::Port => ConnectField::Username,
+            ConnectField::Username => ConnectField::Password,
+            ConnectField::Password => ConnectField::Host,
+        }
+    }
+
+    fn accepts(&self, c: char) -> bool {
+        match self {
+            ConnectField::Port => c.is_ascii_digit(),
+            ConnectField::Password => !c.is_control(),
+            _ => !c.is_whitespace() && !c.is_control(),
+        }
+    }
+}
+
+/// What the player typed on the connect screen, kept between visits.
+#[derive(Resource)]
+pub struct ConnectForm {
+    pub host: String,
+    pub port: String,
+    pub username: String,
+    pub password: Zeroizing<String>,
+    focused: ConnectField,
+    error: Option<String>,
+}
+
+impl ConnectForm {
+    fn value(&self, field: ConnectField) -> &str {
+        match field {
+            ConnectField::Host => &self.host,
+            ConnectField::Port => &self.port,
+            ConnectField::Username => &self.username,
+            ConnectField::Password => &self.password,
+        }
+    }
+
+    fn value_mut(&mut self, field: ConnectField) -> &mut String {
+        match field {
+            ConnectField::Host => &mut self.host,
+            ConnectField::Port => &mut self.port,
+            ConnectField::Username => &mut self.username,
+            ConnectField::Password => &mut self.password,
+        }
+    }
+
+    fn fill(&mut self, server: &RecentServer) {
+        self.host = server.host.clone();
+        self.port = server.port.to_string();
+        self.username = server.username.clone();
+        self.error = None;
+    }
+
+    /// Checks every field and resolves the host.
+    pub fn validate(&self) -> Result<(SocketAddr, RecentServer), String> {
+        let host = self.host.trim();
+
+        if host.is_empty() {
+            return Err("Enter a host name or address.".to_string());
+        }
+
+        let port = self
+            .port
+            .trim()
+            .parse::<u16>()
+            .ok()
+            .filter(|port| *port != 0)
+            .ok_or_else(|| "Port must be a number from 1 to 65535.".to_string())?;
+
+        let username = self.username.trim();
+
+        if username.is_empty() {
+            return Err("Enter a username.".to_string());
+        }
+
+        validate_username(username)?;
+        validate_password(&self.password)?;
+
+        let addresses: Vec<SocketAddr> = (host, port)
+            .to_socket_addrs()
+            .map_err(|error| format!("Could not resolve {}: {}", host, error))?
+            .collect();
+
+        // The transport binds an IPv4 socket, so prefer IPv4 results.
+        let addr = addresses
+            .iter()
+            .find(|addr| addr.is_ipv4())
+            .or(addresses.first())
+            .copied()
+            .ok_or_else(|| format!("{} has no addresses.", host))?;
+
+        Ok((
+            addr,
+            RecentServer {
+                host: host.to_string(),
+                port,
+                username: username.to_string(),
+            },
+        ))
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct ConnectScreen;
+
+#[derive(Component, Clone)]
+pub struct ConnectErrorText;
+
+/// Holds one button per server found on the local network.
+#[derive(Component, Clone)]
+pub struct LanServerList;
+
+/// Holds one button per server listed by the master server.
+#[derive(Component, Clone)]
+pub struct MasterServerList;
+
+#[derive(Component, Clone)]
+pub enum ConnectButton {
+    Field(ConnectField),
+    Recent(usize),
+    /// A discovered or listed server, fills in host and port.
+    Server(SocketAddr),
+    Join,
+    Register,
+    Back,
+}
+
+fn button(label: String, font_size: f32, color: &str, action: ConnectButton) -> impl Bundle {
+    (
+        Text::new(label),
+        TextFont {
+            font_size,
+            ..default()
+        },
+        TextColor(Color::Srgba(Srgba::hex(color).unwrap())),
+        Node {
+            padding: UiRect::all(Val::Px(4.)),
+            border: UiRect::all(Val::Px(2.)),
+            ..default()
+        },
+        BorderColor::all(Color::NONE),
+        action,
+        Button,
+        Visibility::Inherited,
+    )
+}
+
+fn heading(label: &str) -> impl Bundle {
+    (
+        Text::new(label),
+        TextFont {
+            font_size: 32.,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Visibility::Inherited,
+    )
+}
+
+pub fn load_connect_form(mut commands: Commands) {
+    let recent = RecentServers::load_default();
+
+    let mut form = ConnectForm {
+        host: local_ip().map(|ip| ip.to_string()).unwrap_or_default(),
+        port: SERVER_PORT.to_string(),
+        username: String::new(),
+        password: Zeroizing::new(String::new()),
+        focused: ConnectField::Host,
+        error: None,
+    };
+
+    if let Some(last) = recent.servers.first() {
+        form.fill(last);
+    }
+
+    commands.insert_resource(form);
+    commands.insert_resource(recent);
+}
+
+pub fn spawn_connect_screen(mut commands: Commands, recent: Res<RecentServers>) {
+    let column = Node {
+        flex_direction: FlexDirection::Column,
+        row_gap: Val::Px(8.),
+        ..default()
+    };
+
+    commands
+        .spawn((
+            ConnectScreen,
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Row,
+                column_gap: Val::Px(60.),
+                padding: UiRect::all(Val::Px(40.)),
+                ..default()
+            },
+            BackgroundColor(Color::Srgba(Srgba::hex("171717").unwrap())),
+            GlobalZIndex(1),
+        ))
+        .with_children(|parent| {
+            parent.spawn(column.clone()).with_children(|form| {
+                form.spawn((
+                    Text::new("Connect"),
+                    TextFont {
+                        font_size: 64.,
+                        ..default()
+                    },
+                    Visibility::Inherited,
+                ));
+
+                for field in ConnectField::iter() {
+                    form.spawn(button(
+                        String::new(),
+                        36.,
+                        "00ff00",
+                        ConnectButton::Field(field),
+                    ));
+                }
+
+                form.spawn((
+                    Text::new(""),
+                    TextFont {
+                        font_size: 28.,
+                        ..default()
+                    },
+                    TextColor(Color::Srgba(Srgba::hex("ff5555").unwrap())),
+                    ConnectErrorText,
+                    Visibility::Inherited,
+                ));
+
+                form.spawn(button(
+                    "Log in".to_string(),
+                    42.,
+                    "00ff00",
+                    ConnectButton::Join,
+                ));
+                form.spawn(button(
+                    "Register".to_string(),
+                    42.,
+                    "00ff00",
+                    ConnectButton::Register,
+                ));
+                form.spawn(button(
+                    "Back".to_string(),
+                    42.,
+                    "00ff00",
+                    ConnectButton::Back,
+                ));
+            });
+
+            parent.spawn(column.clone()).with_children(|lists| {
+                lists.spawn(heading("Recent"));
+
+                for (index, server) in recent.servers.iter().enumerate() {
+                    lists.spawn(button(
+                        format!("{}:{} as {}", server.host, server.port, server.username),
+                        28.,
+                        "00ffff",
+                        ConnectButton::Recent(index),
+                    ));
+                }
+
+                lists.spawn(heading("Local network"));
+                lists.spawn((LanServerList, column.clone(), Visibility::Inherited));
+
+                lists.spawn(heading("Master server"));
+                lists.spawn((MasterServerList, column.clone(), Visibility::Inherited));
+            });
+        });
+}
+
+pub fn despawn_connect_screen(mut commands: Commands, screens: Query<Entity, With<ConnectScreen>>) {
+    for screen in screens.iter() {
+        commands.entity(screen).despawn();
+    }
+}
+
+/// Validates the form and starts connecting, or shows what is wrong.
+fn submit(
+    form: &mut ConnectForm,
+    recent: &mut RecentServers,
+    commands: &mut Commands,
+    register: bool,
+) {
+    match form.validate() {
+        Ok((addr, server)) => {
+            info!("Connecting to {} as {}", addr, server.username);
+
+            commands.insert_resource(ServerAddress(addr));
+            commands.insert_resource(UserLogin::LoggingIn(Credentials {
+                username: server.username.clone(),
+                password: form.password.clone(),
+                register,
+            }));
+
+            recent.remember(server);
+            form.error = None;
+
+            commands.set_state(AppState::ConnectToServer);
+        }
+        Err(error) => form.error = Some(error),
+    }
+}
+
+pub fn listen_connect_input(
+    query: Query<(&ConnectButton, &Interaction), Changed<Interaction>>,
+    mut commands: Commands,
+    mut form: ResMut<ConnectForm>,
+    mut recent: ResMut<RecentServers>,
+) {
+    for (action, interaction) in query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match action {
+            ConnectButton::Field(field) => form.focused = *field,
+
+            ConnectButton::Recent(index) => {
+                if let Some(server) = recent.servers.get(*index) {
+                    form.fill(server);
+                }
+            }
+
+            ConnectButton::Server(addr) => {
+                form.host = addr.ip().to_string();
+                form.port = addr.port().to_string();
+                form.error = None;
+            }
+
+            ConnectButton::Join => submit(&mut form, &mut recent, &mut commands, false),
+
+            ConnectButton::Register => submit(&mut form, &mut recent, &mut commands, true),
+
+            ConnectButton::Back => commands.set_state(AppState::MainMenu),
+        }
+    }
+}
+
+pub fn type_into_fields(
+    mut keys: MessageReader<KeyboardInput>,
+    mut commands: Commands,
+    mut form: ResMut<ConnectForm>,
+    mut recent: ResMut<RecentServers>,
+) {
+    for key in keys.read() {
+        if key.state != ButtonState::Pressed {
+            continue;
+        }
+
+        let field = form.focused;
+
+        match &key.logical_key {
+            Key::Character(text) => {
+                let max = match field {
+                    ConnectField::Host => MAX_HOST_CHARS,
+                    ConnectField::Port => 5,
+                    ConnectField::Username => MAX_USERNAME_CHARS,
+                    ConnectField::Password => MAX_PASSWORD_CHARS,
+                };
+
+                let value = form.value_mut(field);
+
+                for c in text.chars().filter(|c| field.accepts(*c)) {
+                    if value.chars().count() < max {
+                        value.push(c);
+                    }
+                }
+            }
+
+            Key::Backspace => {
+                form.value_mut(field).pop();
+            }
+
+            Key::Tab => form.focused = field.next(),
+
+            Key::Enter => submit(&mut form, &mut recent, &mut commands, false),
+
+            Key::Escape => commands.set_state(AppState::MainMenu),
+
+            _ => {}
+        }
+    }
+}
+
+pub fn show_fields(
+    form: Res<ConnectForm>,
+    mut fields: Query<(&ConnectButton, &mut Text, &mut BorderColor)>,
+    mut errors: Query<&mut Text, (With<ConnectErrorText>, Without<ConnectButton>)>,
+    added: Query<(), Added<ConnectScreen>>,
+) {
+    if !form.is_changed() && added.is_empty() {
+        return;
+    }
+
+    for (action, mut text, mut border) in fields.iter_mut() {
+        let ConnectButton::Field(field) = action else {
+            continue;
+        };
+
+        let focused = *field == form.focused;
+        let cursor = if focused { "_" } else { "" };
+
+        let value = match field {
+            ConnectField::Password => "*".repeat(form.password.chars().count()),
+            _ => form.value(*field).to_string(),
+        };
+
+        text.0 = format!("{}: {}{}", field.as_str(), value, cursor);
+        *border = if focused {
+            BorderColor::all(Color::Srgba(Srgba::hex("ff00ff").unwrap()))
+        } else {
+            BorderColor::all(Color::NONE)
+        };
+    }
+
+    for mut text in errors.iter_mut() {
+        text.0 = form.error.clone().unwrap_or_default();
+    }
+}
+
+pub fn show_lan_servers(
+    mut commands: Commands,
+    servers: Res<LanServers>,
+    list: Query<(Entity, Ref<LanServerList>)>,
+) {
+    let Ok((list, marker)) = list.single() else {
+        return;
+    };
+
+    if !servers.is_changed() && !marker.is_added() {
+        return;
+    }
+
+    commands
+        .entity(list)
+        .despawn_related::<Children>()
+        .with_children(|parent| {
+            for server in &servers.0 {
+                let label = format!(
+                    "{} ({}) v{} - {} free slots",
+                    server.announcement.name,
+                    server.addr,
+                    server.announcement.version,
+                    server.announcement.free_slots
+                );
+
+                parent.spawn(button(
+                    label,
+                    28.,
+                    "00ffff",
+                    ConnectButton::Server(server.addr),
+                ));
+            }
+        });
+}
+
+pub fn show_master_servers(
+    mut commands: Commands,
+    servers: Res<MasterServers>,
+    list: Query<(Entity, Ref<MasterServerList>)>,
+) {
+    let Ok((list, marker)) = list.single() else {
+        return;
+    };
+
+    if !servers.is_changed() && !marker.is_added() {
+        return;
+    }
+
+    commands
+        .entity(list)
+        .despawn_related::<Children>()
+        .with_children(|parent| {
+            for server in &servers.0 {
+                let listing = &server.listing;
+                let ping = server
+                    .ping
+                    .map(|ping| format!("{}ms", ping.as_millis()))
+                    .unwrap_or_else(|| "?".to_string());
+
+                let label = format!(
+                    "{} ({}) v{} - {}/{} players - ping {}",
+                    listing.name,
+                    listing.addr,
+                    listing.version,
+                    listing.players,
+                    listing.max_players,
+                    ping
+                );
+
+                parent.spawn(button(
+                    label,
+                    28.,
+                    "00ffff",
+                    ConnectButton::Server(listing.addr),
+                ));
+            }
+        });
+}
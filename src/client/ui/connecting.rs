@@ -0,0 +1,138 @@
+use bevy::{
+    input::{
+        ButtonState,
+        keyboard::{Key, KeyboardInput},
+    },
+    prelude::*,
+};
+
+use crate::client::{
+    AppState,
+    network::connection::{ConnectionAttempt, ConnectionState, disconnect},
+};
+
+#[derive(Component, Clone)]
+pub struct ConnectingScreen;
+
+#[derive(Component, Clone)]
+pub struct ConnectingStatusText;
+
+/// Cancels a pending connection, or goes back to the connect screen once it failed.
+#[derive(Component, Clone)]
+pub struct ConnectingButton;
+
+pub fn spawn_connecting_screen(mut commands: Commands) {
+    commands
+        .spawn((
+            ConnectingScreen,
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(20.),
+                ..default()
+            },
+            BackgroundColor(Color::Srgba(Srgba::hex("171717").unwrap())),
+            GlobalZIndex(1),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 42.,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                ConnectingStatusText,
+                Visibility::Inherited,
+            ));
+
+            parent.spawn((
+                Text::new("Cancel"),
+                TextFont {
+                    font_size: 42.,
+                    ..default()
+                },
+                TextColor(Color::Srgba(Srgba::hex("00ff00").unwrap())),
+                Node {
+                    padding: UiRect::all(Val::Px(4.)),
+                    ..default()
+                },
+                ConnectingButton,
+                Button,
+                Visibility::Inherited,
+            ));
+        });
+}
+
+pub fn despawn_connecting_screen(
+    mut commands: Commands,
+    screens: Query<Entity, With<ConnectingScreen>>,
+) {
+    for screen in screens.iter() {
+        commands.entity(screen).despawn();
+    }
+}
+
+fn cancel(commands: &mut Commands, state: &ConnectionState) {
+    if state.is_active() {
+        disconnect(commands);
+    }
+
+    commands.set_state(AppState::ConnectMenu);
+}
+
+pub fn listen_connecting_input(
+    query: Query<&Interaction, (Changed<Interaction>, With<ConnectingButton>)>,
+    mut keys: MessageReader<KeyboardInput>,
+    mut commands: Commands,
+    state: Res<State<ConnectionState>>,
+) {
+    let escape = keys
+        .read()
+        .any(|key| key.state == ButtonState::Pressed && key.logical_key == Key::Escape);
+
+    if escape
+        || query
+            .iter()
+            .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        cancel(&mut commands, state.get());
+    }
+}
+
+pub fn show_connecting_status(
+    state: Res<State<ConnectionState>>,
+    attempt: Option<Res<ConnectionAttempt>>,
+    mut status: Query<&mut Text, (With<ConnectingStatusText>, Without<ConnectingButton>)>,
+    mut buttons: Query<&mut Text, (With<ConnectingButton>, Without<ConnectingStatusText>)>,
+) {
+    let text = match state.get() {
+        ConnectionState::Connecting => match attempt {
+            Some(attempt) => format!("Connecting to {}...", attempt.addr),
+            None => "Connecting...".to_string(),
+        },
+        ConnectionState::Handshaking => "Securing connection...".to_string(),
+        ConnectionState::Connected => "Connected".to_string(),
+        ConnectionState::Failed(reason) => format!("Could not connect: {}", reason),
+        ConnectionState::Disconnected(reason) => format!("Disconnected: {}", reason),
+        ConnectionState::Offline => String::new(),
+    };
+
+    for mut status in status.iter_mut() {
+        if status.0 != text {
+            status.0 = text.clone();
+        }
+    }
+
+    let label = if state.is_active() { "Cancel" } else { "Back" };
+
+    for mut button in buttons.iter_mut() {
+        if button.0 != label {
+            button.0 = label.to_string();
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use bevy::{camera::visibility::RenderLayers, prelude::*};
+
+use crate::{
+    client::{
+        LAYER_HUD,
+        network::session::{ClientSession, CommLink, Partner, ServerNotice},
+        ui::{
+            chat::{ChatBubbles, ChatInputText},
+            comm::{CommText, StaticOverlay, static_image},
+        },
+    },
+    common::{network::PartnerStatus, tether::CommState},
+};
+
+/// Overlay camera for in-game HUD nodes.
+///
+/// The main menu camera is the default UI camera and is deactivated in game, so HUD nodes
+/// target this camera explicitly.
+#[derive(Component, Clone)]
+pub struct HudCamera;
+
+const NOTICE_DURATION: Duration = Duration::from_secs(8);
+
+#[derive(Component, Clone)]
+pub struct PartnerStatusText;
+
+#[derive(Component, Clone)]
+pub struct NoticeText;
+
+pub fn spawn_hud(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let camera = commands
+        .spawn((
+            Camera {
+                order: 20,
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            Camera2d,
+            HudCamera,
+            RenderLayers::layer(LAYER_HUD),
+        ))
+        .id();
+
+    // Spawned first so everything else is drawn over it.
+    commands.spawn((
+        ImageNode::new(images.add(static_image())),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            ..default()
+        },
+        UiTargetCamera(camera),
+        Visibility::Hidden,
+        StaticOverlay,
+    ));
+
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 32.,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.),
+            right: Val::Px(20.),
+            ..default()
+        },
+        UiTargetCamera(camera),
+        Visibility::Hidden,
+        CommText,
+    ));
+
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 36.,
+            ..default()
+        },
+        TextColor(Color::Srgba(Srgba::hex("ffcc00").unwrap())),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.),
+            left: Val::Px(20.),
+            ..default()
+        },
+        UiTargetCamera(camera),
+        Visibility::Hidden,
+        PartnerStatusText,
+    ));
+
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 32.,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(70.),
+            left: Val::Px(20.),
+            ..default()
+        },
+        UiTargetCamera(camera),
+        Visibility::Hidden,
+        NoticeText,
+    ));
+
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(140.),
+            left: Val::Px(20.),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(8.),
+            ..default()
+        },
+        UiTargetCamera(camera),
+        ChatBubbles,
+    ));
+
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 32.,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(80.),
+            left: Val::Px(20.),
+            ..default()
+        },
+        UiTargetCamera(camera),
+        Visibility::Hidden,
+        ChatInputText,
+    ));
+}
+
+pub fn update_notice(
+    notice: Res<ServerNotice>,
+    time: Res<Time>,
+    mut query: Query<(&mut Text, &mut Visibility), With<NoticeText>>,
+) {
+    let Ok((mut text, mut visibility)) = query.single_mut() else {
+        return;
+    };
+
+    if notice.text.is_empty() || time.elapsed() - notice.received > NOTICE_DURATION {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+
+    if notice.is_changed() {
+        text.0 = format!("[Server] {}", notice.text);
+    }
+
+    *visibility = Visibility::Visible;
+}
+
+/// Says what the partner is up to, unless the link is lost and the player could not know.
+pub fn update_partner_status(
+    partner: Res<Partner>,
+    session: Res<ClientSession>,
+    link: Res<CommLink>,
+    time: Res<Time>,
+    mut query: Query<(&mut Text, &mut Visibility), With<PartnerStatusText>>,
+) {
+    let Ok((mut text, mut visibility)) = query.single_mut() else {
+        return;
+    };
+
+    if link.state == CommState::Lost {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+
+    match partner.status {
+        PartnerStatus::Reconnecting(seconds) => {
+            let elapsed = (time.elapsed() - partner.since).as_secs_f32();
+            let left = (seconds as f32 - elapsed).max(0.0);
+
+            text.0 = format!("Waiting for teammate to reconnect... {:.0}s", left);
+            *visibility = Visibility::Visible;
+        }
+
+        PartnerStatus::Absent if session.role.is_some() => {
+            text.0 = "Waiting for teammate...".to_string();
+            *visibility = Visibility::Visible;
+        }
+
+        _ => {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
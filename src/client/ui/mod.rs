@@ -1,8 +1,18 @@
 use bevy::{camera::visibility::RenderLayers, prelude::*, ui::FocusPolicy};
 
-use crate::client::{AppState, LAYER_UI};
+use crate::client::{
+    AppState, LAYER_UI,
+    network::{connection::ConnectionState, session::ClientSession},
+};
 
 mod actions;
+pub mod chat;
+mod comm;
+mod connect;
+mod connecting;
+mod hud;
+mod profile;
+mod signals;
 
 pub struct UiPlugin;
 
@@ -12,22 +22,38 @@ struct MainMenu;
 #[derive(Resource)]
 struct MainMenuReady;
 
+#[derive(Component, Clone)]
+struct MenuStatusText;
+
 #[derive(Component, Clone)]
 enum UiLabelType {
     Play,
     Connect,
+    /// Only shown while connected.
+    Profile,
+    /// Only shown while connected or connecting.
+    Disconnect,
     Exit,
 }
 
 impl UiLabelType {
     fn iter() -> impl Iterator<Item = UiLabelType> {
-        [UiLabelType::Play, UiLabelType::Connect, UiLabelType::Exit].into_iter()
+        [
+            UiLabelType::Play,
+            UiLabelType::Connect,
+            UiLabelType::Profile,
+            UiLabelType::Disconnect,
+            UiLabelType::Exit,
+        ]
+        .into_iter()
     }
 
     fn as_str(&self) -> &'static str {
         match self {
             UiLabelType::Play => "Play",
             UiLabelType::Connect => "Connect",
+            UiLabelType::Profile => "Profile",
+            UiLabelType::Disconnect => "Disconnect",
             UiLabelType::Exit => "Exit",
         }
     }
@@ -128,9 +154,58 @@ impl UiPlugin {
 
                 parent.spawn(text_bundle);
             }
+
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 28.,
+                    ..default()
+                },
+                TextColor(Color::Srgba(Srgba::hex("ff5555").unwrap())),
+                MenuStatusText,
+                base_node.clone(),
+                Visibility::Inherited,
+            ));
         });
     }
 
+    fn show_connection_status(
+        state: Res<State<ConnectionState>>,
+        session: Res<ClientSession>,
+        mut status: Query<(&mut Text, &mut TextColor), With<MenuStatusText>>,
+        mut labels: Query<(&UiLabelType, &mut Node)>,
+    ) {
+        if !state.is_changed() && !session.is_changed() {
+            return;
+        }
+
+        let (text, color) = match (state.get(), session.role) {
+            (ConnectionState::Connected, Some(role)) => {
+                (format!("Connected as {:?}", role), "55ff55")
+            }
+            (ConnectionState::Failed(reason), _) => {
+                (format!("Could not connect: {}", reason), "ff5555")
+            }
+            (ConnectionState::Disconnected(reason), _) => (reason.clone(), "ff5555"),
+            _ => (String::new(), "ff5555"),
+        };
+
+        for (mut status, mut status_color) in status.iter_mut() {
+            status.0 = text.clone();
+            status_color.0 = Color::Srgba(Srgba::hex(color).unwrap());
+        }
+
+        for (label, mut node) in labels.iter_mut() {
+            let shown = match label {
+                UiLabelType::Profile => *state.get() == ConnectionState::Connected,
+                UiLabelType::Disconnect => state.is_active(),
+                _ => continue,
+            };
+
+            node.display = if shown { Display::Flex } else { Display::None };
+        }
+    }
+
     fn show_menu(query: Option<Query<(&mut Camera, &mut Visibility), With<UiPickingCamera>>>) {
         match query {
             Some(mut camera) => {
@@ -175,6 +250,84 @@ impl Plugin for UiPlugin {
             (Self::render_main_menu, Self::set_resource).chain(),
         );
         app.add_systems(Update, actions::listen_ui_input);
+        app.add_systems(Update, Self::show_connection_status);
+
+        app.add_systems(Startup, connect::load_connect_form);
+        app.add_systems(
+            OnEnter(AppState::ConnectMenu),
+            connect::spawn_connect_screen,
+        );
+        app.add_systems(
+            OnExit(AppState::ConnectMenu),
+            connect::despawn_connect_screen,
+        );
+        app.add_systems(
+            Update,
+            (
+                connect::listen_connect_input,
+                connect::type_into_fields,
+                connect::show_fields,
+                connect::show_lan_servers,
+                connect::show_master_servers,
+            )
+                .chain()
+                .run_if(in_state(AppState::ConnectMenu)),
+        );
+
+        app.add_systems(
+            OnEnter(AppState::ConnectToServer),
+            connecting::spawn_connecting_screen,
+        );
+        app.add_systems(
+            OnExit(AppState::ConnectToServer),
+            connecting::despawn_connecting_screen,
+        );
+        app.add_systems(
+            Update,
+            (
+                connecting::listen_connecting_input,
+                connecting::show_connecting_status,
+            )
+                .chain()
+                .run_if(in_state(AppState::ConnectToServer)),
+        );
+
+        app.add_systems(
+            OnEnter(AppState::ProfileMenu),
+            profile::spawn_profile_screen,
+        );
+        app.add_systems(
+            OnExit(AppState::ProfileMenu),
+            profile::despawn_profile_screen,
+        );
+        app.add_systems(
+            Update,
+            (profile::listen_profile_input, profile::show_profile)
+                .chain()
+                .run_if(in_state(AppState::ProfileMenu)),
+        );
+
+        app.add_systems(Startup, hud::spawn_hud);
+        app.add_systems(Update, (hud::update_partner_status, hud::update_notice));
+        app.add_systems(Update, (comm::show_comm_state, comm::show_static));
+
+        app.insert_resource(chat::ChatInput::default());
+        app.add_systems(
+            Update,
+            (chat::type_chat, chat::show_chat_input)
+                .chain()
+                .run_if(in_state(AppState::InGame)),
+        );
+        app.add_systems(Update, chat::show_chat_bubbles);
+
+        app.insert_resource(signals::SignalMenu::default());
+        app.add_systems(
+            Update,
+            (signals::toggle_signal_menu, signals::point_signal_menu)
+                .chain()
+                .run_if(in_state(AppState::InGame).and(chat::chat_closed)),
+        );
+        app.add_systems(Update, signals::show_signal_markers);
 
         app.add_systems(
             OnEnter(AppState::MainMenu),
@@ -184,5 +337,21 @@ impl Plugin for UiPlugin {
             OnExit(AppState::MainMenu),
             Self::hide_menu.run_if(resource_exists::<MainMenuReady>),
         );
+
+        // The connect and profile screens are drawn by the menu camera, on top of the main menu.
+        for state in [
+            AppState::ConnectMenu,
+            AppState::ConnectToServer,
+            AppState::ProfileMenu,
+        ] {
+            app.add_systems(
+                OnEnter(state.clone()),
+                Self::show_menu.run_if(resource_exists::<MainMenuReady>),
+            );
+            app.add_systems(
+                OnExit(state),
+                Self::hide_menu.run_if(resource_exists::<MainMenuReady>),
+            );
+        }
     }
 }
@@ -0,0 +1,149 @@
+use bevy::{
+    input::{
+        ButtonState,
+        keyboard::{Key, KeyboardInput},
+    },
+    prelude::*,
+};
+
+use crate::{
+    client::{
+        AppState,
+        network::{connection::ConnectionState, session::ClientProfile},
+    },
+    common::network::{PlayerProfile, Role},
+};
+
+#[derive(Component, Clone)]
+pub struct ProfileScreen;
+
+#[derive(Component, Clone)]
+pub struct ProfileText;
+
+#[derive(Component, Clone)]
+pub struct ProfileBackButton;
+
+pub fn spawn_profile_screen(mut commands: Commands) {
+    commands
+        .spawn((
+            ProfileScreen,
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(20.),
+                padding: UiRect::all(Val::Px(40.)),
+                ..default()
+            },
+            BackgroundColor(Color::Srgba(Srgba::hex("171717").unwrap())),
+            GlobalZIndex(1),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Profile"),
+                TextFont {
+                    font_size: 64.,
+                    ..default()
+                },
+                Visibility::Inherited,
+            ));
+
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 36.,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                ProfileText,
+                Visibility::Inherited,
+            ));
+
+            parent.spawn((
+                Text::new("Back"),
+                TextFont {
+                    font_size: 42.,
+                    ..default()
+                },
+                TextColor(Color::Srgba(Srgba::hex("00ff00").unwrap())),
+                Node {
+                    padding: UiRect::all(Val::Px(4.)),
+                    ..default()
+                },
+                ProfileBackButton,
+                Button,
+                Visibility::Inherited,
+            ));
+        });
+}
+
+pub fn despawn_profile_screen(mut commands: Commands, screens: Query<Entity, With<ProfileScreen>>) {
+    for screen in screens.iter() {
+        commands.entity(screen).despawn();
+    }
+}
+
+pub fn listen_profile_input(
+    query: Query<&Interaction, (Changed<Interaction>, With<ProfileBackButton>)>,
+    mut keys: MessageReader<KeyboardInput>,
+    mut commands: Commands,
+) {
+    let escape = keys
+        .read()
+        .filter(|key| key.state == ButtonState::Pressed && key.logical_key == Key::Escape)
+        .count()
+        > 0;
+
+    if escape
+        || query
+            .iter()
+            .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        commands.set_state(AppState::MainMenu);
+    }
+}
+
+fn describe(profile: &PlayerProfile) -> String {
+    let history: Vec<&str> = profile
+        .role_history
+        .iter()
+        .rev()
+        .map(|role| match role {
+            Role::Gray => "Gray",
+            Role::Note => "Note",
+        })
+        .collect();
+
+    let history = if history.is_empty() {
+        "none yet".to_string()
+    } else {
+        history.join(", ")
+    };
+
+    format!(
+        "{}\n\nMatches played: {}\nRecent roles: {}",
+        profile.username, profile.matches_played, history
+    )
+}
+
+pub fn show_profile(
+    profile: Res<ClientProfile>,
+    connection: Res<State<ConnectionState>>,
+    mut texts: Query<&mut Text, With<ProfileText>>,
+    added: Query<(), Added<ProfileText>>,
+) {
+    if !profile.is_changed() && added.is_empty() {
+        return;
+    }
+
+    let value = match (&profile.0, connection.get()) {
+        (Some(profile), _) => describe(profile),
+        (None, ConnectionState::Connected) => "Loading...".to_string(),
+        (None, _) => "Connect to a server to see your profile.".to_string(),
+    };
+
+    for mut text in texts.iter_mut() {
+        text.0 = value.clone();
+    }
+}
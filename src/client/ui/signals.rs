@@ -0,0 +1,232 @@
+use std::{
+    f32::consts::{FRAC_PI_2, TAU},
+    time::Duration,
+};
+
+use bevy::{camera::visibility::RenderLayers, prelude::*, window::PrimaryWindow};
+use bevy_renet::renet::RenetClient;
+
+use crate::{
+    client::{
+        LAYER_WORLD,
+        network::{
+            encryption::{Nonce, SskStore},
+            session::{ClientSession, QuickSignal},
+        },
+        ui::hud::HudCamera,
+    },
+    common::{
+        network::{ClientMessage, Role},
+        signals::SignalDefs,
+    },
+};
+
+/// Held to open the radial menu. Point at a signal and let go to send it.
+const SIGNAL_KEY: KeyCode = KeyCode::KeyQ;
+
+const MENU_RADIUS: f32 = 220.;
+
+/// The cursor has to be this far from the center to pick anything.
+const DEAD_ZONE: f32 = 40.;
+
+const MARKER_DURATION: Duration = Duration::from_secs(8);
+const MARKER_HEIGHT: f32 = 6.;
+
+/// Radial quick-signal menu, open while [`SIGNAL_KEY`] is held.
+#[derive(Resource, Default, Debug)]
+pub struct SignalMenu {
+    pub open: bool,
+    /// Index into the signal definitions the cursor points at.
+    pub selected: Option<usize>,
+}
+
+#[derive(Component, Clone)]
+pub struct SignalMenuRoot;
+
+#[derive(Component, Clone)]
+pub struct SignalMenuItem(usize);
+
+/// Pillar of light where a signal was given, seen by Gray.
+#[derive(Component, Clone)]
+pub struct SignalMarker {
+    expires: Duration,
+}
+
+fn item_angle(index: usize, count: usize) -> f32 {
+    -FRAC_PI_2 + index as f32 * TAU / count as f32
+}
+
+/// Signal under `offset` from the screen center, starting at the top and going clockwise.
+fn pick(offset: Vec2, count: usize) -> Option<usize> {
+    if count == 0 || offset.length() < DEAD_ZONE {
+        return None;
+    }
+
+    let turn = (offset.y.atan2(offset.x) + FRAC_PI_2).rem_euclid(TAU);
+
+    Some((turn / (TAU / count as f32)).round() as usize % count)
+}
+
+pub fn toggle_signal_menu(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut menu: ResMut<SignalMenu>,
+    defs: Res<SignalDefs>,
+    cameras: Query<Entity, With<HudCamera>>,
+    roots: Query<Entity, With<SignalMenuRoot>>,
+    client: Option<ResMut<RenetClient>>,
+    ssks: Res<SskStore>,
+    mut nonce_res: ResMut<Nonce>,
+    session: Res<ClientSession>,
+) {
+    if keyboard.just_pressed(SIGNAL_KEY) && !menu.open && !defs.0.is_empty() {
+        let Ok(camera) = cameras.single() else {
+            return;
+        };
+
+        menu.open = true;
+        menu.selected = None;
+
+        commands
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Percent(50.),
+                    top: Val::Percent(50.),
+                    ..default()
+                },
+                UiTargetCamera(camera),
+                SignalMenuRoot,
+            ))
+            .with_children(|parent| {
+                for (index, def) in defs.0.iter().enumerate() {
+                    let angle = item_angle(index, defs.0.len());
+                    let [r, g, b] = def.color;
+
+                    parent.spawn((
+                        Text::new(def.label.clone()),
+                        TextFont {
+                            font_size: 30.,
+                            ..default()
+                        },
+                        TextColor(Color::srgb_u8(r, g, b)),
+                        TextLayout::new_with_justify(Justify::Center),
+                        Node {
+                            position_type: PositionType::Absolute,
+                            left: Val::Px(MENU_RADIUS * angle.cos() - 110.),
+                            top: Val::Px(MENU_RADIUS * angle.sin() - 20.),
+                            width: Val::Px(220.),
+                            padding: UiRect::all(Val::Px(6.)),
+                            ..default()
+                        },
+                        BackgroundColor(Color::BLACK.with_alpha(0.5)),
+                        BorderRadius::all(Val::Px(8.)),
+                        SignalMenuItem(index),
+                    ));
+                }
+            });
+    }
+
+    if !keyboard.just_released(SIGNAL_KEY) || !menu.open {
+        return;
+    }
+
+    for root in roots.iter() {
+        commands.entity(root).despawn();
+    }
+
+    let selected = menu.selected.and_then(|index| defs.0.get(index));
+    *menu = SignalMenu::default();
+
+    let (Some(def), Some(mut client)) = (selected, client) else {
+        return;
+    };
+
+    if !client.is_connected() || session.role.is_none() {
+        return;
+    }
+
+    ClientMessage::send_encrypted(
+        &mut client,
+        &ssks.0,
+        &ClientMessage::Signal { id: def.id.clone() },
+        &mut nonce_res,
+    );
+}
+
+pub fn point_signal_menu(
+    mut menu: ResMut<SignalMenu>,
+    defs: Res<SignalDefs>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut items: Query<(&SignalMenuItem, &mut BackgroundColor)>,
+) {
+    if !menu.open {
+        return;
+    }
+
+    let Ok(window) = windows.single() else {
+        return;
+    };
+
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+
+    let selected = pick(cursor - window.size() / 2., defs.0.len());
+    menu.selected = selected;
+
+    for (item, mut background) in items.iter_mut() {
+        let alpha = if Some(item.0) == selected { 0.9 } else { 0.5 };
+        background.0 = Color::BLACK.with_alpha(alpha);
+    }
+}
+
+/// Gray cannot hear, so every signal becomes a pillar of light in its color.
+pub fn show_signal_markers(
+    mut commands: Commands,
+    mut signals: MessageReader<QuickSignal>,
+    session: Res<ClientSession>,
+    defs: Res<SignalDefs>,
+    time: Res<Time>,
+    markers: Query<(Entity, &SignalMarker)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let now = time.elapsed();
+
+    for (entity, marker) in markers.iter() {
+        if marker.expires <= now {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for signal in signals.read() {
+        if session.role != Some(Role::Gray) {
+            continue;
+        }
+
+        let Some(def) = defs.get(&signal.id) else {
+            debug!("Unknown signal {:?} from {:?}", signal.id, signal.from);
+            continue;
+        };
+
+        let [r, g, b] = def.color;
+        let color = Color::srgb_u8(r, g, b);
+
+        commands.spawn((
+            Mesh3d(meshes.add(Cylinder::new(0.15, MARKER_HEIGHT))),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: color.with_alpha(0.6),
+                emissive: color.to_linear() * 4.,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            })),
+            Transform::from_translation(signal.translation + Vec3::Y * MARKER_HEIGHT / 2.),
+            RenderLayers::layer(LAYER_WORLD),
+            SignalMarker {
+                expires: now + MARKER_DURATION,
+            },
+        ));
+    }
+}
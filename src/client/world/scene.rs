@@ -1,3 +1,12 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    client::{
+        AppState, LAYER_WORLD,
+        world::{LoadState, player::Player},
+    },
+    common::world::{CHUNK_SIDE, CHUNK_SIZE, TERRAIN_Y, WorldGenerator, WorldSeed},
+};
 use avian3d::prelude::*;
 use bevy::{
     asset::RenderAssetUsages,
@@ -6,124 +15,421 @@ use bevy::{
     prelude::*,
     render::render_resource::Face,
 };
-use noiz::prelude::*;
 
-use crate::client::{AppState, LAYER_WORLD, world::LoadState};
+/// World played when not connected to a server.
+const OFFLINE_SEED: WorldSeed = WorldSeed(0);
+
+/// Chunks this many away from a player, in either axis, are kept loaded.
+const VIEW_RADIUS: i32 = 3;
+
+/// Chunks are only unloaded this far away, so walking along a chunk border does not reload them.
+const UNLOAD_RADIUS: i32 = VIEW_RADIUS + 1;
 
+/// New chunks meshed per frame while playing, nearest first, to keep frame times even.
+const MAX_CHUNKS_PER_FRAME: usize = 2;
+
+/// Meshes and colliders kept for chunks that were unloaded, oldest dropped first.
+const MAX_CACHED_CHUNKS: usize = 256;
+
+/// Streams terrain chunks in around the players and out again once they are far away.
 pub struct ScenePlugin;
 
+/// Position of a chunk on the chunk grid.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChunkCoord(pub IVec2);
+
+/// Mesh and collider of a chunk, kept after it is unloaded so coming back is free.
+struct CachedChunk {
+    mesh: Handle<Mesh>,
+    collider: Collider,
+}
+
+#[derive(Resource)]
+struct TerrainChunks {
+    generator: WorldGenerator,
+    loaded: HashMap<IVec2, Entity>,
+    cache: HashMap<IVec2, CachedChunk>,
+    /// Cached coordinates, oldest first.
+    cache_order: VecDeque<IVec2>,
+    material: Option<Handle<StandardMaterial>>,
+}
+
+impl Default for TerrainChunks {
+    fn default() -> Self {
+        Self {
+            generator: WorldGenerator::new(OFFLINE_SEED),
+            loaded: HashMap::new(),
+            cache: HashMap::new(),
+            cache_order: VecDeque::new(),
+            material: None,
+        }
+    }
+}
+
+/// Chunk containing `translation`.
+fn chunk_of(translation: Vec3) -> IVec2 {
+    (translation.xz() / CHUNK_SIZE as f32).floor().as_ivec2()
+}
+
+/// Chunks within [`VIEW_RADIUS`] of any of `centers`, nearest first.
+fn chunks_around(centers: &[IVec2]) -> Vec<IVec2> {
+    let mut wanted: Vec<IVec2> = Vec::new();
+
+    for center in centers {
+        for x in -VIEW_RADIUS..=VIEW_RADIUS {
+            for z in -VIEW_RADIUS..=VIEW_RADIUS {
+                let coord = *center + IVec2::new(x, z);
+
+                if !wanted.contains(&coord) {
+                    wanted.push(coord);
+                }
+            }
+        }
+    }
+
+    wanted.sort_by_key(|coord| {
+        centers
+            .iter()
+            .map(|center| (*coord - *center).length_squared())
+            .min()
+    });
+
+    wanted
+}
+
+/// Whether `coord` is past [`UNLOAD_RADIUS`] of every one of `centers`.
+fn beyond_unload_radius(coord: IVec2, centers: &[IVec2]) -> bool {
+    centers.iter().all(|center| {
+        let offset = (coord - *center).abs();
+        offset.x > UNLOAD_RADIUS || offset.y > UNLOAD_RADIUS
+    })
+}
+
+/// Heightfield mesh of one chunk, in chunk-local coordinates.
+///
+/// Edge vertices and normals are sampled in world space, so neighbouring chunks meet without seams.
+fn chunk_mesh(generator: &WorldGenerator, coord: IVec2) -> Mesh {
+    let side = CHUNK_SIDE;
+    let heights = generator.chunk_heights(coord);
+    let origin = (coord * CHUNK_SIZE as i32).as_vec2();
+
+    let mut positions = Vec::with_capacity(side * side);
+    let mut normals = Vec::with_capacity(side * side);
+    let mut uvs = Vec::with_capacity(side * side);
+    let mut indices = Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE * 6);
+
+    for x in 0..side {
+        for z in 0..side {
+            let world_x = origin.x + x as f32;
+            let world_z = origin.y + z as f32;
+
+            positions.push([x as f32, heights[x * side + z], z as f32]);
+
+            // Central differences for smooth normals
+            let dx = generator.height_at(world_x + 1.0, world_z)
+                - generator.height_at(world_x - 1.0, world_z);
+            let dz = generator.height_at(world_x, world_z + 1.0)
+                - generator.height_at(world_x, world_z - 1.0);
+            let normal = Vec3::new(-dx, 2.0, -dz).normalize();
+            normals.push([normal.x, normal.y, normal.z]);
+
+            uvs.push([x as f32 / CHUNK_SIZE as f32, z as f32 / CHUNK_SIZE as f32]);
+        }
+    }
+
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            let i0 = (x * side + z) as u32;
+            let i1 = (x * side + z + 1) as u32;
+            let i2 = ((x + 1) * side + z) as u32;
+            let i3 = ((x + 1) * side + z + 1) as u32;
+
+            indices.extend_from_slice(&[i0, i1, i2, i1, i3, i2]);
+        }
+    }
+
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_indices(Indices::U32(indices));
+
+    mesh
+}
+
+impl TerrainChunks {
+    /// Throws away every chunk when the world changed, e.g. after joining a server.
+    fn reseed(&mut self, commands: &mut Commands, seed: WorldSeed) {
+        if self.generator.seed() == seed {
+            return;
+        }
+
+        info!("Generating terrain for seed {}.", seed.0);
+
+        for (_, entity) in self.loaded.drain() {
+            commands.entity(entity).despawn();
+        }
+
+        self.cache.clear();
+        self.cache_order.clear();
+        self.generator = WorldGenerator::new(seed);
+    }
+
+    /// Cached mesh and collider for `coord`, generated on first use.
+    fn get_or_build(
+        &mut self,
+        coord: IVec2,
+        meshes: &mut Assets<Mesh>,
+    ) -> (Handle<Mesh>, Collider) {
+        if let Some(cached) = self.cache.get(&coord) {
+            return (cached.mesh.clone(), cached.collider.clone());
+        }
+
+        let mesh = chunk_mesh(&self.generator, coord);
+        let collider =
+            Collider::trimesh_from_mesh(&mesh).expect("Terrain chunk mesh is not a trimesh.");
+        let mesh = meshes.add(mesh);
+
+        self.cache.insert(
+            coord,
+            CachedChunk {
+                mesh: mesh.clone(),
+                collider: collider.clone(),
+            },
+        );
+        self.cache_order.push_back(coord);
+        self.evict();
+
+        (mesh, collider)
+    }
+
+    /// Drops the oldest cached chunks that are not loaded until the cache fits.
+    fn evict(&mut self) {
+        let mut checked = 0;
+
+        while self.cache.len() > MAX_CACHED_CHUNKS && checked < self.cache_order.len() {
+            let Some(coord) = self.cache_order.pop_front() else {
+                break;
+            };
+
+            if self.loaded.contains_key(&coord) {
+                self.cache_order.push_back(coord);
+                checked += 1;
+            } else {
+                self.cache.remove(&coord);
+            }
+        }
+    }
+
+    fn spawn(
+        &mut self,
+        commands: &mut Commands,
+        coord: IVec2,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<StandardMaterial>,
+    ) {
+        let (mesh, collider) = self.get_or_build(coord, meshes);
+
+        let material = self
+            .material
+            .get_or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: Color::srgb(0.4, 0.8, 0.3),
+                    perceptual_roughness: 1.,
+                    alpha_mode: AlphaMode::Blend,
+                    depth_bias: -100.,
+                    cull_mode: Some(Face::Back),
+                    thickness: 1.0,
+                    ..default()
+                })
+            })
+            .clone();
+
+        let origin = (coord * CHUNK_SIZE as i32).as_vec2();
+
+        let entity = commands
+            .spawn((
+                Mesh3d(mesh),
+                MeshMaterial3d(material),
+                Transform::from_xyz(origin.x, TERRAIN_Y, origin.y),
+                RenderLayers::layer(LAYER_WORLD),
+                RigidBody::Static,
+                collider,
+                CollisionMargin(0.01),
+                ChunkCoord(coord),
+            ))
+            .id();
+
+        self.loaded.insert(coord, entity);
+    }
+}
+
 impl ScenePlugin {
+    /// Loads everything around the spawn point up front, so the player does not land in a hole.
     fn generate_terrain(
         mut commands: Commands,
+        mut chunks: ResMut<TerrainChunks>,
+        seed: Option<Res<WorldSeed>>,
         mut materials: ResMut<Assets<StandardMaterial>>,
         mut meshes: ResMut<Assets<Mesh>>,
         mut load_state: ResMut<LoadState>,
     ) {
-        let noise = Noise::<common_noise::Perlin>::default();
+        chunks.reseed(&mut commands, seed.map_or(OFFLINE_SEED, |seed| *seed));
 
-        let width = 100;
-        let depth = 100;
-
-        let scale = 0.1;
-        let height_scale = 2.0;
-
-        let mut heights = vec![0.0; width * depth];
-        for x in 0..width {
-            for z in 0..depth {
-                let nx = x as f32 * scale;
-                let nz = z as f32 * scale;
-                let height: f32 = noise.sample(Vec2::new(nx, nz));
-                heights[x * depth + z] = height * height_scale;
+        for coord in chunks_around(&[chunk_of(Vec3::ZERO)]) {
+            if !chunks.loaded.contains_key(&coord) {
+                chunks.spawn(&mut commands, coord, &mut meshes, &mut materials);
             }
         }
 
-        let mut positions = Vec::with_capacity(width * depth);
-        let mut normals = Vec::with_capacity(width * depth);
-        let mut uvs = Vec::with_capacity(width * depth);
-        let mut indices = Vec::with_capacity((width - 1) * (depth - 1) * 6);
-
-        for x in 0..width {
-            for z in 0..depth {
-                let h = heights[x * depth + z];
-                positions.push([x as f32, h, z as f32]);
-
-                // Central differences for smooth normals
-                let h_l = if x > 0 {
-                    heights[(x - 1) * depth + z]
-                } else {
-                    h
-                };
-                let h_r = if x < width - 1 {
-                    heights[(x + 1) * depth + z]
-                } else {
-                    h
-                };
-                let h_d = if z > 0 {
-                    heights[x * depth + (z - 1)]
-                } else {
-                    h
-                };
-                let h_u = if z < depth - 1 {
-                    heights[x * depth + (z + 1)]
-                } else {
-                    h
-                };
-
-                let dx = h_r - h_l;
-                let dz = h_u - h_d;
-                let normal = Vec3::new(-dx, 2.0, -dz).normalize();
-                normals.push([normal.x, normal.y, normal.z]);
-
-                uvs.push([x as f32 / width as f32, z as f32 / depth as f32]);
-            }
+        load_state.terrain = true;
+    }
+
+    fn stream_chunks(
+        mut commands: Commands,
+        mut chunks: ResMut<TerrainChunks>,
+        seed: Option<Res<WorldSeed>>,
+        players: Query<&Transform, With<Player>>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+        mut meshes: ResMut<Assets<Mesh>>,
+    ) {
+        chunks.reseed(&mut commands, seed.map_or(OFFLINE_SEED, |seed| *seed));
+
+        let centers: Vec<IVec2> = players
+            .iter()
+            .map(|transform| chunk_of(transform.translation))
+            .collect();
+
+        if centers.is_empty() {
+            return;
         }
 
-        for x in 0..width - 1 {
-            for z in 0..depth - 1 {
-                let i0 = (x * depth + z) as u32;
-                let i1 = (x * depth + z + 1) as u32;
-                let i2 = ((x + 1) * depth + z) as u32;
-                let i3 = ((x + 1) * depth + z + 1) as u32;
+        let far: Vec<IVec2> = chunks
+            .loaded
+            .keys()
+            .filter(|coord| beyond_unload_radius(**coord, &centers))
+            .copied()
+            .collect();
 
-                indices.extend_from_slice(&[i0, i1, i2, i1, i3, i2]);
+        for coord in far {
+            if let Some(entity) = chunks.loaded.remove(&coord) {
+                commands.entity(entity).despawn();
             }
         }
 
-        // Build mesh
-        let mut mesh = Mesh::new(
-            PrimitiveTopology::TriangleList,
-            RenderAssetUsages::RENDER_WORLD,
-        );
-        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
-        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
-        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
-        mesh.insert_indices(Indices::U32(indices));
-
-        // Spawn entity
-        commands.spawn((
-            Mesh3d(meshes.add(mesh)),
-            MeshMaterial3d(materials.add(StandardMaterial {
-                base_color: Color::srgb(0.4, 0.8, 0.3),
-                perceptual_roughness: 1.,
-                alpha_mode: AlphaMode::Blend,
-                depth_bias: -100.,
-                cull_mode: Some(Face::Back),
-                thickness: 1.0,
-                ..default()
-            })),
-            Transform::from_xyz(-(width as f32) / 2.0, -2.0, -(depth as f32) / 2.0),
-            RenderLayers::layer(LAYER_WORLD),
-            RigidBody::Static,
-            ColliderConstructor::TrimeshFromMesh,
-            CollisionMargin(0.01),
-        ));
+        let missing: Vec<IVec2> = chunks_around(&centers)
+            .into_iter()
+            .filter(|coord| !chunks.loaded.contains_key(coord))
+            .take(MAX_CHUNKS_PER_FRAME)
+            .collect();
 
-        load_state.terrain = true;
+        for coord in missing {
+            chunks.spawn(&mut commands, coord, &mut meshes, &mut materials);
+        }
     }
 }
 
 impl Plugin for ScenePlugin {
     fn build(&self, app: &mut App) {
+        app.insert_resource(TerrainChunks::default());
         app.add_systems(OnEnter(AppState::Load), Self::generate_terrain);
+        app.add_systems(
+            Update,
+            Self::stream_chunks.run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(chunks: &mut TerrainChunks, coord: IVec2) {
+        chunks.cache.insert(
+            coord,
+            CachedChunk {
+                mesh: Handle::default(),
+                collider: Collider::sphere(1.0),
+            },
+        );
+        chunks.cache_order.push_back(coord);
+        chunks.evict();
+    }
+
+    #[test]
+    fn cache_drops_the_oldest_chunks_past_its_cap() {
+        let mut chunks = TerrainChunks::default();
+
+        for x in 0..MAX_CACHED_CHUNKS as i32 + 10 {
+            cache(&mut chunks, IVec2::new(x, 0));
+        }
+
+        assert_eq!(chunks.cache.len(), MAX_CACHED_CHUNKS);
+        assert_eq!(chunks.cache_order.len(), MAX_CACHED_CHUNKS);
+        assert!((0..10).all(|x| !chunks.cache.contains_key(&IVec2::new(x, 0))));
+        assert!(chunks.cache.contains_key(&IVec2::new(10, 0)));
+    }
+
+    #[test]
+    fn loaded_chunks_are_never_evicted() {
+        let mut chunks = TerrainChunks::default();
+
+        for x in 0..2 {
+            chunks.loaded.insert(IVec2::new(x, 0), Entity::PLACEHOLDER);
+        }
+
+        for x in 0..MAX_CACHED_CHUNKS as i32 * 2 {
+            cache(&mut chunks, IVec2::new(x, 0));
+        }
+
+        assert_eq!(chunks.cache.len(), MAX_CACHED_CHUNKS);
+        assert!(chunks.cache.contains_key(&IVec2::new(0, 0)));
+        assert!(chunks.cache.contains_key(&IVec2::new(1, 0)));
+        assert!(!chunks.cache.contains_key(&IVec2::new(2, 0)));
+    }
+
+    #[test]
+    fn chunks_stay_loaded_a_little_past_the_view() {
+        let centers = [IVec2::ZERO];
+
+        assert!(!beyond_unload_radius(IVec2::new(VIEW_RADIUS, 0), &centers));
+        assert!(!beyond_unload_radius(
+            IVec2::new(0, -UNLOAD_RADIUS),
+            &centers
+        ));
+        assert!(beyond_unload_radius(
+            IVec2::new(UNLOAD_RADIUS + 1, 0),
+            &centers
+        ));
+        assert!(beyond_unload_radius(
+            IVec2::new(0, -UNLOAD_RADIUS - 1),
+            &centers
+        ));
+
+        // Any player close enough keeps a chunk.
+        let far = IVec2::new(UNLOAD_RADIUS + 1, 0);
+        assert!(!beyond_unload_radius(far, &[IVec2::ZERO, far]));
+    }
+
+    #[test]
+    fn nearest_chunks_come_first() {
+        let side = (2 * VIEW_RADIUS + 1) as usize;
+        let around = chunks_around(&[IVec2::ZERO]);
+
+        assert_eq!(around.len(), side * side);
+        assert_eq!(around[0], IVec2::ZERO);
+        assert!(
+            around
+                .windows(2)
+                .all(|pair| pair[0].length_squared() <= pair[1].length_squared())
+        );
+
+        // Overlapping views load each chunk once.
+        let both = chunks_around(&[IVec2::ZERO, IVec2::new(1, 0)]);
+        assert_eq!(both.len(), side * (side + 1));
+        assert_eq!(&both[..2], &[IVec2::ZERO, IVec2::new(1, 0)]);
     }
 }
@@ -1,10 +1,8 @@
 // #![cfg_attr(windows, windows_subsystem = "windows")]
 
+use absent_chroma::client;
 use bevy::prelude::*;
 
-mod client;
-mod common;
-
 /// Main entry point for the client application
 
 fn main() {
@@ -0,0 +1,59 @@
+pub const MIN_USERNAME_CHARS: usize = 3;
+pub const MAX_USERNAME_CHARS: usize = 32;
+pub const MIN_PASSWORD_CHARS: usize = 8;
+pub const MAX_PASSWORD_CHARS: usize = 128;
+
+/// Names nobody may register, compared without case.
+pub const RESERVED_USERNAMES: [&str; 10] = [
+    "admin",
+    "administrator",
+    "anon",
+    "console",
+    "moderator",
+    "null",
+    "root",
+    "server",
+    "system",
+    "unknown",
+];
+
+/// Checks a username against the rules both the connect screen and the server enforce.
+pub fn validate_username(username: &str) -> Result<(), String> {
+    let chars = username.chars().count();
+
+    if chars < MIN_USERNAME_CHARS || chars > MAX_USERNAME_CHARS {
+        return Err(format!(
+            "Usernames are {} to {} characters.",
+            MIN_USERNAME_CHARS, MAX_USERNAME_CHARS
+        ));
+    }
+
+    if !username
+        .chars()
+        .all(|c| c.is_alphanumeric() || "-_.".contains(c))
+    {
+        return Err("Usernames may only use letters, digits, '-', '_' and '.'.".to_string());
+    }
+
+    if RESERVED_USERNAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(username))
+    {
+        return Err(format!("The username {:?} is reserved.", username));
+    }
+
+    Ok(())
+}
+
+pub fn validate_password(password: &str) -> Result<(), String> {
+    let chars = password.chars().count();
+
+    if chars < MIN_PASSWORD_CHARS || chars > MAX_PASSWORD_CHARS {
+        return Err(format!(
+            "Passwords are {} to {} characters.",
+            MIN_PASSWORD_CHARS, MAX_PASSWORD_CHARS
+        ));
+    }
+
+    Ok(())
+}
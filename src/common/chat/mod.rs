@@ -0,0 +1,36 @@
+use rand::Rng;
+
+/// Longest chat message a client may send, in characters.
+pub const MAX_CHAT_CHARS: usize = 200;
+
+/// Character that stands in for the parts of a message lost to distance.
+pub const STATIC_CHAR: char = '~';
+
+/// Replaces each visible character with [`STATIC_CHAR`] unless it survives with probability
+/// `clarity`. Whitespace is kept so the shape of the message still comes through.
+pub fn degrade(text: &str, clarity: f32, rng: &mut impl Rng) -> String {
+    if clarity >= 1.0 {
+        return text.to_string();
+    }
+
+    text.chars()
+        .map(|c| {
+            if c.is_whitespace() || rng.random::<f32>() < clarity {
+                c
+            } else {
+                STATIC_CHAR
+            }
+        })
+        .collect()
+}
+
+/// Trims a message and strips control characters, or returns `None` if it is empty or too long.
+pub fn sanitize(text: &str) -> Option<String> {
+    let text: String = text.trim().chars().filter(|c| !c.is_control()).collect();
+
+    if text.is_empty() || text.chars().count() > MAX_CHAT_CHARS {
+        return None;
+    }
+
+    Some(text)
+}
@@ -0,0 +1,82 @@
+use bincode::{Decode, Encode};
+
+/// UDP port servers listen on for LAN discovery probes.
+pub const DISCOVERY_PORT: u16 = 42070;
+
+/// Every discovery packet starts with this, so stray traffic on the port is ignored.
+const MAGIC: [u8; 4] = *b"ACD1";
+
+/// Probes are padded to this size and announcements must fit in it, so a server never answers
+/// with more bytes than it was sent.
+pub const PROBE_BYTES: usize = 256;
+
+const MAX_NAME_BYTES: usize = 64;
+
+/// What a server tells LAN clients about itself.
+#[derive(Encode, Decode, Debug, Clone, PartialEq, Eq)]
+pub struct ServerAnnouncement {
+    pub name: String,
+    pub version: String,
+    /// Port of the game transport. The address is the one the announcement came from.
+    pub port: u16,
+    pub rooms: u16,
+    pub free_slots: u16,
+}
+
+#[derive(Encode, Decode, Debug, Clone, PartialEq, Eq)]
+pub enum DiscoveryMessage {
+    Probe,
+    Announcement(ServerAnnouncement),
+}
+
+impl DiscoveryMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut packet = MAGIC.to_vec();
+
+        let mut message = self.clone();
+        if let DiscoveryMessage::Announcement(announcement) = &mut message {
+            truncate(&mut announcement.name, MAX_NAME_BYTES);
+            truncate(&mut announcement.version, MAX_NAME_BYTES);
+        }
+
+        bincode::encode_into_std_write(&message, &mut packet, bincode::config::standard())
+            .expect("Error encoding discovery message.");
+
+        if message == DiscoveryMessage::Probe {
+            packet.resize(PROBE_BYTES, 0);
+        }
+
+        packet
+    }
+
+    /// Returns `None` for anything that is not a discovery packet, including short probes.
+    pub fn decode(packet: &[u8]) -> Option<Self> {
+        let body = packet.strip_prefix(&MAGIC)?;
+
+        let (message, _) = bincode::decode_from_slice::<Self, _>(
+            body,
+            bincode::config::standard().with_limit::<PROBE_BYTES>(),
+        )
+        .ok()?;
+
+        if message == DiscoveryMessage::Probe && packet.len() < PROBE_BYTES {
+            return None;
+        }
+
+        Some(message)
+    }
+}
+
+/// Cuts `value` down to at most `max_bytes`, on a character boundary.
+pub(crate) fn truncate(value: &mut String, max_bytes: usize) {
+    if value.len() <= max_bytes {
+        return;
+    }
+
+    let mut end = max_bytes;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    value.truncate(end);
+}
@@ -1 +1,114 @@
+use std::collections::HashMap;
 
+use cryptoxide::chacha20poly1305::ChaCha20Poly1305;
+
+pub const NONCE_LEN: usize = 12;
+pub const TAG_LEN: usize = 16;
+
+/// Which side sealed a message.
+///
+/// Both directions share the same session key, so the first nonce byte is reserved for the
+/// direction to keep the two nonce sequences disjoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Direction {
+    ClientToServer = 0,
+    ServerToClient = 1,
+}
+
+pub fn increment_nonce(nonce: &mut [u8; NONCE_LEN]) {
+    for i in (1..NONCE_LEN).rev() {
+        if nonce[i] == 255 {
+            nonce[i] = 0;
+        } else {
+            nonce[i] += 1;
+            break;
+        }
+    }
+}
+
+/// Encrypts `plaintext` as `nonce || ciphertext || tag` and advances the sender's nonce.
+///
+/// The nonce travels with the message, so the receiver only has to check it moves forward with
+/// [`ReceivedNonces`].
+pub fn encrypt(
+    key: &[u8; 32],
+    nonce: &mut [u8; NONCE_LEN],
+    direction: Direction,
+    plaintext: &[u8],
+) -> Vec<u8> {
+    nonce[0] = direction as u8;
+
+    let aad = [0u8; 0];
+
+    let mut cipher = ChaCha20Poly1305::new(key, nonce, &aad);
+
+    let mut output = vec![0u8; NONCE_LEN + plaintext.len() + TAG_LEN];
+    let mut out_tag = [0u8; TAG_LEN];
+
+    output[..NONCE_LEN].copy_from_slice(nonce);
+
+    cipher.encrypt(
+        plaintext,
+        &mut output[NONCE_LEN..NONCE_LEN + plaintext.len()],
+        &mut out_tag,
+    );
+
+    output[NONCE_LEN + plaintext.len()..].copy_from_slice(&out_tag);
+
+    increment_nonce(nonce);
+
+    output
+}
+
+/// Decrypts a message produced by [`encrypt`].
+///
+/// Returns `None` if the message is truncated, was sealed for the other direction, or fails
+/// authentication.
+pub fn decrypt(key: &[u8; 32], direction: Direction, message: &[u8]) -> Option<Vec<u8>> {
+    if message.len() < NONCE_LEN + TAG_LEN || message[0] != direction as u8 {
+        return None;
+    }
+
+    let (nonce, rest) = message.split_at(NONCE_LEN);
+    let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LEN);
+
+    let aad = [0u8; 0];
+
+    let mut cipher = ChaCha20Poly1305::new(key, nonce, &aad);
+
+    let mut output = vec![0u8; ciphertext.len()];
+
+    cipher
+        .decrypt(ciphertext, &mut output, tag)
+        .then_some(output)
+}
+
+/// The last nonce accepted on each channel from one peer, to drop replayed messages.
+///
+/// A sender counts across all its channels, but the receiver drains them one at a time, so the
+/// counter only rises in order within a channel.
+#[derive(Debug, Default, Clone)]
+pub struct ReceivedNonces(HashMap<u8, [u8; NONCE_LEN]>);
+
+impl ReceivedNonces {
+    /// Accepts a message that [`decrypt`] opened on `channel_id` if its counter is above the last
+    /// one accepted there. Anything else is a replay, or too late to matter.
+    pub fn advance(&mut self, channel_id: u8, message: &[u8]) -> bool {
+        let Some(nonce) = message
+            .get(..NONCE_LEN)
+            .and_then(|nonce| <[u8; NONCE_LEN]>::try_from(nonce).ok())
+        else {
+            return false;
+        };
+
+        // Big-endian, so comparing the bytes compares the counters.
+        if self.0.get(&channel_id).is_some_and(|last| nonce <= *last) {
+            return false;
+        }
+
+        self.0.insert(channel_id, nonce);
+
+        true
+    }
+}
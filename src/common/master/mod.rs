@@ -0,0 +1,106 @@
+use std::net::SocketAddr;
+
+use bincode::{Decode, Encode};
+
+use crate::common::discovery::truncate;
+
+/// UDP port the master server listens on.
+pub const MASTER_PORT: u16 = 42080;
+
+/// Every master server packet starts with this, so stray traffic on the port is ignored.
+const MAGIC: [u8; 4] = *b"ACM1";
+
+/// Largest packet either side sends. List requests are padded to this size, so the master never
+/// answers with more bytes than it was sent.
+pub const MASTER_PACKET_BYTES: usize = 1200;
+
+/// Servers per [`MasterMessage::ServerList`] page, small enough for a full page to fit in a
+/// packet.
+pub const SERVERS_PER_PAGE: usize = 8;
+
+const MAX_NAME_BYTES: usize = 64;
+const MAX_VERSION_BYTES: usize = 16;
+
+#[derive(Encode, Decode, Debug, Clone, PartialEq, Eq)]
+pub struct ListedServer {
+    /// Game transport address: the heartbeat's sender with its advertised port.
+    pub addr: SocketAddr,
+    pub name: String,
+    pub version: String,
+    pub players: u16,
+    pub max_players: u16,
+}
+
+#[derive(Encode, Decode, Debug, Clone, PartialEq, Eq)]
+pub enum MasterMessage {
+    /// Registers a server, or keeps its registration alive. Sent from the game's IP address.
+    Heartbeat {
+        port: u16,
+        name: String,
+        version: String,
+        players: u16,
+        max_players: u16,
+    },
+    Unregister {
+        port: u16,
+    },
+    ListRequest {
+        page: u16,
+    },
+    ServerList {
+        page: u16,
+        /// Another page follows.
+        more: bool,
+        servers: Vec<ListedServer>,
+    },
+}
+
+impl MasterMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut packet = MAGIC.to_vec();
+
+        let mut message = self.clone();
+        match &mut message {
+            MasterMessage::Heartbeat { name, version, .. } => {
+                truncate(name, MAX_NAME_BYTES);
+                truncate(version, MAX_VERSION_BYTES);
+            }
+            MasterMessage::ServerList { servers, .. } => {
+                for server in servers {
+                    truncate(&mut server.name, MAX_NAME_BYTES);
+                    truncate(&mut server.version, MAX_VERSION_BYTES);
+                }
+            }
+            _ => {}
+        }
+
+        bincode::encode_into_std_write(&message, &mut packet, bincode::config::standard())
+            .expect("Error encoding master server message.");
+
+        if let MasterMessage::ListRequest { .. } = message {
+            packet.resize(MASTER_PACKET_BYTES, 0);
+        }
+
+        packet
+    }
+
+    /// Returns `None` for anything that is not a master server packet, including short list
+    /// requests.
+    pub fn decode(packet: &[u8]) -> Option<Self> {
+        let body = packet.strip_prefix(&MAGIC)?;
+
+        let (message, _) = bincode::decode_from_slice::<Self, _>(
+            body,
+            bincode::config::standard().with_limit::<MASTER_PACKET_BYTES>(),
+        )
+        .ok()?;
+
+        if let MasterMessage::ListRequest { .. } = message
+            && packet.len() < MASTER_PACKET_BYTES
+        {
+            return None;
+        }
+
+        Some(message)
+    }
+}
@@ -1,2 +1,11 @@
+pub mod account;
+pub mod chat;
+pub mod discovery;
 pub mod encryption;
+pub mod master;
+pub mod netsim;
 pub mod network;
+pub mod signals;
+pub mod tether;
+pub mod voice;
+pub mod world;
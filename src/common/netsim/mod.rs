@@ -0,0 +1,345 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    env, fmt, io,
+    net::{SocketAddr, UdpSocket},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use rand::Rng;
+
+/// Extra hold applied to packets picked for reordering, so later packets overtake them.
+const REORDER_DELAY: Duration = Duration::from_millis(30);
+/// Peers that sent nothing for this long lose their upstream socket.
+const PEER_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_PACKET_BYTES: usize = 1500;
+
+/// Impairments applied to each direction of a conditioned link.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LinkConditions {
+    pub latency: Duration,
+    /// Random extra delay, up to this much, added to every packet.
+    pub jitter: Duration,
+    pub loss_percent: f32,
+    pub duplicate_percent: f32,
+    pub reorder_percent: f32,
+}
+
+impl LinkConditions {
+    /// Reads `ABSENT_CHROMA_NETSIM_*` variables. Returns `None` when none of them are set.
+    pub fn from_env() -> Option<Self> {
+        fn read<T: std::str::FromStr>(name: &str) -> Option<T> {
+            env::var(format!("ABSENT_CHROMA_NETSIM_{}", name))
+                .ok()?
+                .parse()
+                .ok()
+        }
+
+        let latency = read::<u64>("LATENCY_MS");
+        let jitter = read::<u64>("JITTER_MS");
+        let loss = read::<f32>("LOSS_PERCENT");
+        let duplicate = read::<f32>("DUPLICATE_PERCENT");
+        let reorder = read::<f32>("REORDER_PERCENT");
+
+        if latency.is_none()
+            && jitter.is_none()
+            && loss.is_none()
+            && duplicate.is_none()
+            && reorder.is_none()
+        {
+            return None;
+        }
+
+        Some(Self {
+            latency: Duration::from_millis(latency.unwrap_or_default()),
+            jitter: Duration::from_millis(jitter.unwrap_or_default()),
+            loss_percent: loss.unwrap_or_default().clamp(0.0, 100.0),
+            duplicate_percent: duplicate.unwrap_or_default().clamp(0.0, 100.0),
+            reorder_percent: reorder.unwrap_or_default().clamp(0.0, 100.0),
+        })
+    }
+
+    /// Changes one setting by name, as typed in the console: `latency 120`, `loss 5`.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let millis = || {
+            value
+                .parse()
+                .map(Duration::from_millis)
+                .map_err(|_| format!("Expected milliseconds, got {:?}", value))
+        };
+        let percent = || {
+            value
+                .parse::<f32>()
+                .map(|percent| percent.clamp(0.0, 100.0))
+                .map_err(|_| format!("Expected a percentage, got {:?}", value))
+        };
+
+        match name {
+            "latency" => self.latency = millis()?,
+            "jitter" => self.jitter = millis()?,
+            "loss" => self.loss_percent = percent()?,
+            "duplicate" => self.duplicate_percent = percent()?,
+            "reorder" => self.reorder_percent = percent()?,
+            _ => {
+                return Err(format!(
+                    "Unknown setting {:?}, expected latency, jitter, loss, duplicate or reorder",
+                    name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn delay(&self, rng: &mut impl Rng) -> Duration {
+        let mut delay = self.latency;
+
+        if !self.jitter.is_zero() {
+            delay += self.jitter.mul_f32(rng.random());
+        }
+
+        if rng.random::<f32>() * 100.0 < self.reorder_percent {
+            delay += REORDER_DELAY;
+        }
+
+        delay
+    }
+}
+
+impl fmt::Display for LinkConditions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "latency: {}ms jitter: {}ms loss: {}% duplicate: {}% reorder: {}%",
+            self.latency.as_millis(),
+            self.jitter.as_millis(),
+            self.loss_percent,
+            self.duplicate_percent,
+            self.reorder_percent
+        )
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Route {
+    /// From the peer towards the target.
+    Upstream(SocketAddr),
+    /// From the target back to the peer.
+    Downstream(SocketAddr),
+}
+
+struct Delayed {
+    route: Route,
+    data: Vec<u8>,
+}
+
+struct Peer {
+    socket: UdpSocket,
+    last_seen: Instant,
+}
+
+/// UDP relay that forwards packets between its peers and a target address, impairing both
+/// directions. Clients connect through it by putting its address in their connect token.
+///
+/// The relay thread stops when this is dropped.
+#[derive(Resource)]
+pub struct NetworkConditioner {
+    local_addr: SocketAddr,
+    conditions: Arc<Mutex<LinkConditions>>,
+    running: Arc<AtomicBool>,
+}
+
+impl NetworkConditioner {
+    pub fn spawn(
+        bind: SocketAddr,
+        target: SocketAddr,
+        conditions: LinkConditions,
+    ) -> io::Result<Self> {
+        let front = UdpSocket::bind(bind)?;
+        front.set_nonblocking(true)?;
+
+        let local_addr = front.local_addr()?;
+        let shared = Arc::new(Mutex::new(conditions));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let relay = Relay {
+            front,
+            target,
+            peers: HashMap::new(),
+            queue: BinaryHeap::new(),
+            delayed: HashMap::new(),
+            next_id: 0,
+            conditions: shared.clone(),
+            running: running.clone(),
+        };
+
+        thread::spawn(move || relay.run());
+
+        info!(
+            "Network conditioner on {} -> {} ({})",
+            local_addr, target, conditions
+        );
+
+        Ok(Self {
+            local_addr,
+            conditions: shared,
+            running,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn conditions(&self) -> LinkConditions {
+        *self.conditions.lock().expect("Conditioner lock poisoned.")
+    }
+
+    pub fn set_conditions(&self, conditions: LinkConditions) {
+        *self.conditions.lock().expect("Conditioner lock poisoned.") = conditions;
+    }
+}
+
+impl Drop for NetworkConditioner {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+struct Relay {
+    front: UdpSocket,
+    target: SocketAddr,
+    peers: HashMap<SocketAddr, Peer>,
+    queue: BinaryHeap<Reverse<(Instant, u64)>>,
+    delayed: HashMap<u64, Delayed>,
+    next_id: u64,
+    conditions: Arc<Mutex<LinkConditions>>,
+    running: Arc<AtomicBool>,
+}
+
+impl Relay {
+    fn run(mut self) {
+        let mut rng = rand::rng();
+        let mut buffer = [0u8; MAX_PACKET_BYTES];
+
+        while self.running.load(Ordering::Relaxed) {
+            let conditions = *self.conditions.lock().expect("Conditioner lock poisoned.");
+            let now = Instant::now();
+
+            while let Ok((len, peer_addr)) = self.front.recv_from(&mut buffer) {
+                if !self.peers.contains_key(&peer_addr) {
+                    let Ok(socket) = UdpSocket::bind("0.0.0.0:0") else {
+                        continue;
+                    };
+                    if socket.set_nonblocking(true).is_err() {
+                        continue;
+                    }
+
+                    self.peers.insert(
+                        peer_addr,
+                        Peer {
+                            socket,
+                            last_seen: now,
+                        },
+                    );
+                }
+
+                if let Some(peer) = self.peers.get_mut(&peer_addr) {
+                    peer.last_seen = now;
+                }
+
+                self.schedule(
+                    Route::Upstream(peer_addr),
+                    &buffer[..len],
+                    &conditions,
+                    now,
+                    &mut rng,
+                );
+            }
+
+            let mut downstream = vec![];
+            for (peer_addr, peer) in &self.peers {
+                while let Ok((len, from)) = peer.socket.recv_from(&mut buffer) {
+                    if from == self.target {
+                        downstream.push((*peer_addr, buffer[..len].to_vec()));
+                    }
+                }
+            }
+            for (peer_addr, data) in downstream {
+                self.schedule(
+                    Route::Downstream(peer_addr),
+                    &data,
+                    &conditions,
+                    now,
+                    &mut rng,
+                );
+            }
+
+            while let Some(Reverse((at, id))) = self.queue.peek().copied() {
+                if at > now {
+                    break;
+                }
+                self.queue.pop();
+
+                let Some(packet) = self.delayed.remove(&id) else {
+                    continue;
+                };
+
+                match packet.route {
+                    Route::Upstream(peer_addr) => {
+                        if let Some(peer) = self.peers.get(&peer_addr) {
+                            let _ = peer.socket.send_to(&packet.data, self.target);
+                        }
+                    }
+                    Route::Downstream(peer_addr) => {
+                        let _ = self.front.send_to(&packet.data, peer_addr);
+                    }
+                }
+            }
+
+            self.peers
+                .retain(|_, peer| now.duration_since(peer.last_seen) < PEER_TIMEOUT);
+
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn schedule(
+        &mut self,
+        route: Route,
+        data: &[u8],
+        conditions: &LinkConditions,
+        now: Instant,
+        rng: &mut impl Rng,
+    ) {
+        if rng.random::<f32>() * 100.0 < conditions.loss_percent {
+            return;
+        }
+
+        let copies = if rng.random::<f32>() * 100.0 < conditions.duplicate_percent {
+            2
+        } else {
+            1
+        };
+
+        for _ in 0..copies {
+            let id = self.next_id;
+            self.next_id += 1;
+
+            self.queue.push(Reverse((now + conditions.delay(rng), id)));
+            self.delayed.insert(
+                id,
+                Delayed {
+                    route,
+                    data: data.to_vec(),
+                },
+            );
+        }
+    }
+}
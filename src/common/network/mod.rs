@@ -1,9 +1,11 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 use bevy::ecs::resource::Resource;
-use bevy_renet::renet::DefaultChannel;
+use bevy_renet::renet::{ChannelConfig, ConnectionConfig, DefaultChannel, SendType};
 use bincode::{Decode, Encode};
 
+use crate::common::{tether::CommState, voice::VoiceFrame};
+
 pub fn get_private_key_env() -> [u8; 32] {
     let private_key_string = env!("PRIVATE_KEY");
     // parse the key from raw string
@@ -22,7 +24,9 @@ pub struct UserData(pub [u8; 256]);
 
 impl UserData {
     pub fn to_username(&self) -> &str {
-        str::from_utf8(&self.0).unwrap_or("Null")
+        str::from_utf8(&self.0)
+            .unwrap_or("Null")
+            .trim_end_matches('\0')
     }
 
     pub fn from_str(str: &str) -> UserData {
@@ -41,23 +45,236 @@ impl UserData {
 #[derive(Resource, Default)]
 pub struct ConnectedUsers(pub HashMap<u64, UserData>);
 
-#[derive(Encode, Debug, Clone, Copy, Decode, Default)]
+/// UDP port of the game transport.
+pub const SERVER_PORT: u16 = 42069;
+
+/// Unreliable channel carrying voice frames, encrypted like every channel but the KEM one.
+pub const VOICE_CHANNEL: u8 = 4;
+
+/// Upper bound for a single decoded message, enforced while decoding untrusted input.
+pub const MAX_DECODED_MESSAGE_BYTES: usize = 16 * 1024;
+
+/// Token handed out with [`ServerMessage::Welcome`] that lets a dropped player reclaim their slot.
+pub type ResumeToken = [u8; 32];
+
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+    /// The human. Sees but cannot hear.
+    Gray,
+    /// The drone. Hears but cannot see.
+    Note,
+}
+
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PartnerStatus {
+    #[default]
+    Absent,
+    Connected,
+    /// The partner dropped and has this many seconds left to reconnect.
+    Reconnecting(u32),
+}
+
+/// Lifetime stats the server keeps for an account.
+#[derive(Encode, Decode, Debug, Clone, PartialEq, Eq, Default)]
+pub struct PlayerProfile {
+    pub username: String,
+    pub matches_played: u32,
+    /// Role of each match, oldest first, capped to the most recent ones.
+    pub role_history: Vec<Role>,
+}
+
+#[derive(Encode, Debug, Clone, PartialEq, Decode, Default)]
 pub enum ServerMessage {
     #[default]
     Pong,
     KEMEncapsKey([u8; 800]),
+    HandshakeComplete,
+    Welcome {
+        role: Role,
+        resume_token: ResumeToken,
+        resumed: bool,
+        /// Where the player stands: kept from before a drop, restored from a save, or the origin.
+        translation: [f32; 3],
+        /// World the match is played in, see [`WorldGenerator`](crate::common::world::WorldGenerator).
+        seed: u64,
+    },
+    PartnerStatus(PartnerStatus),
+    Shutdown {
+        reason: String,
+    },
+    Kicked {
+        reason: String,
+    },
+    /// Free-form announcement from the server operator.
+    Notice(String),
+    LoginAccepted {
+        username: String,
+    },
+    /// The server disconnects the client right after.
+    LoginRejected {
+        reason: String,
+    },
+    Profile(PlayerProfile),
+    /// Chat from the partner, degraded by the distance between the players.
+    Chat {
+        text: String,
+        /// Share of the message that made it through, see [`crate::common::tether::clarity`].
+        clarity: f32,
+        /// Where the partner was when they said it.
+        translation: [f32; 3],
+    },
+    /// The link to the partner changed. `clarity` is rounded to tenths.
+    Comm {
+        state: CommState,
+        clarity: f32,
+    },
+    /// A quick signal from either player, placed where they stood.
+    Signal {
+        from: Role,
+        id: String,
+        translation: [f32; 3],
+    },
+    /// Partner voice, sent on [`VOICE_CHANNEL`].
+    Voice {
+        frame: VoiceFrame,
+        /// Loudness left after the distance between the players, 0 to 1.
+        gain: f32,
+        /// Where the partner was when they said it.
+        translation: [f32; 3],
+    },
 }
 
-#[derive(Encode, Debug, Clone, Copy, Decode, Default)]
+#[derive(Encode, Debug, Clone, Decode, Default)]
 pub enum ClientMessage {
     #[default]
     Ping,
     KEMCipherText([u8; 768]),
+    Hello {
+        resume_token: Option<ResumeToken>,
+    },
+    PlayerState {
+        translation: [f32; 3],
+    },
+    Login {
+        username: String,
+        password: String,
+    },
+    /// Creates the account and logs in. Registering an existing account with its own password
+    /// just logs in.
+    Register {
+        username: String,
+        password: String,
+    },
+    /// Asks for the logged in account's [`PlayerProfile`].
+    ProfileRequest,
+    /// Said to the partner, who only gets it if they are close enough.
+    Chat {
+        text: String,
+    },
+    /// Microphone audio, sent on [`VOICE_CHANNEL`].
+    Voice(VoiceFrame),
+    /// Quick signal from the radial menu, by id from the signal definitions.
+    Signal {
+        id: String,
+    },
+}
+
+impl ServerMessage {
+    /// Welcomes carrying a resume token and relayed voice, which never go into captured payloads.
+    /// Recordings hash the token and keep voice without the audio.
+    pub fn is_private(&self) -> bool {
+        matches!(
+            self,
+            ServerMessage::Welcome { .. } | ServerMessage::Voice { .. }
+        )
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ServerMessage::Pong => "Pong",
+            ServerMessage::KEMEncapsKey(_) => "KEMEncapsKey",
+            ServerMessage::HandshakeComplete => "HandshakeComplete",
+            ServerMessage::Welcome { .. } => "Welcome",
+            ServerMessage::PartnerStatus(_) => "PartnerStatus",
+            ServerMessage::Shutdown { .. } => "Shutdown",
+            ServerMessage::Kicked { .. } => "Kicked",
+            ServerMessage::Notice(_) => "Notice",
+            ServerMessage::LoginAccepted { .. } => "LoginAccepted",
+            ServerMessage::LoginRejected { .. } => "LoginRejected",
+            ServerMessage::Profile(_) => "Profile",
+            ServerMessage::Chat { .. } => "Chat",
+            ServerMessage::Voice { .. } => "Voice",
+            ServerMessage::Signal { .. } => "Signal",
+            ServerMessage::Comm { .. } => "Comm",
+        }
+    }
+}
+
+impl ClientMessage {
+    /// Messages carrying a password, a resume token or a voice, which never go into captured
+    /// payloads. Recordings leave out passwords, hash tokens and keep voice without the audio.
+    pub fn is_private(&self) -> bool {
+        matches!(
+            self,
+            ClientMessage::Login { .. }
+                | ClientMessage::Register { .. }
+                | ClientMessage::Hello { .. }
+                | ClientMessage::Voice(_)
+        )
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ClientMessage::Ping => "Ping",
+            ClientMessage::KEMCipherText(_) => "KEMCipherText",
+            ClientMessage::Hello { .. } => "Hello",
+            ClientMessage::PlayerState { .. } => "PlayerState",
+            ClientMessage::Login { .. } => "Login",
+            ClientMessage::Register { .. } => "Register",
+            ClientMessage::ProfileRequest => "ProfileRequest",
+            ClientMessage::Chat { .. } => "Chat",
+            ClientMessage::Voice(_) => "Voice",
+            ClientMessage::Signal { .. } => "Signal",
+        }
+    }
+}
+
+/// Renet's default channels plus channel 3 for the unencrypted KEM handshake and
+/// [`VOICE_CHANNEL`], on both sides.
+pub fn connection_config() -> ConnectionConfig {
+    let mut connection_config = ConnectionConfig::default();
+
+    let kem_channel = ChannelConfig {
+        channel_id: 3,
+        send_type: SendType::ReliableOrdered {
+            resend_time: Duration::from_millis(300),
+        },
+        max_memory_usage_bytes: 5 * 1024 * 1024,
+    };
+
+    connection_config
+        .client_channels_config
+        .push(kem_channel.clone());
+    connection_config.server_channels_config.push(kem_channel);
+
+    let voice_channel = ChannelConfig {
+        channel_id: VOICE_CHANNEL,
+        send_type: SendType::Unreliable,
+        max_memory_usage_bytes: 1024 * 1024,
+    };
+
+    connection_config
+        .client_channels_config
+        .push(voice_channel.clone());
+    connection_config.server_channels_config.push(voice_channel);
+
+    connection_config
 }
 
-pub const NETWORK_CHANNELS: [u8; 4] = [
+pub const NETWORK_CHANNELS: [u8; 5] = [
     DefaultChannel::ReliableOrdered as u8,
     DefaultChannel::ReliableUnordered as u8,
     DefaultChannel::Unreliable as u8,
     3, // For unencrypted KEM handshake
+    VOICE_CHANNEL,
 ];
@@ -0,0 +1,117 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+
+pub const MAX_SIGNAL_ID_CHARS: usize = 32;
+
+/// Quick signal ids are short and plain, since the server relays them without knowing them.
+pub fn valid_signal_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_SIGNAL_ID_CHARS
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// How a quick signal looks to Gray and sounds to Note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalDef {
+    pub id: String,
+    /// Earcon frequency, in Hz.
+    pub pitch: f32,
+    /// Times the earcon repeats.
+    pub pulses: u32,
+    /// Marker color as sRGB bytes.
+    pub color: [u8; 3],
+    pub label: String,
+}
+
+/// `rrggbb` hex, without a leading `#`.
+fn parse_color(hex: &str) -> Option<[u8; 3]> {
+    if hex.len() != 6 {
+        return None;
+    }
+
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    let [_, r, g, b] = value.to_be_bytes();
+
+    Some([r, g, b])
+}
+
+fn parse_line(line: &str) -> Option<SignalDef> {
+    let mut words = line.split_whitespace();
+
+    let id = words.next()?.to_string();
+    let pitch = words.next()?.parse().ok()?;
+    let pulses = words.next()?.parse().ok()?;
+    let color = parse_color(words.next()?)?;
+    let label = words.collect::<Vec<_>>().join(" ");
+
+    if !valid_signal_id(&id) || label.is_empty() || !(20.0..=20_000.0).contains(&pitch) {
+        return None;
+    }
+
+    Some(SignalDef {
+        id,
+        pitch,
+        pulses: pulses.clamp(1, 8),
+        color,
+        label,
+    })
+}
+
+/// Quick signals stored one per line as `id earcon_hz pulses color label`, in file order.
+///
+/// The file is `assets/signals.txt`, or `ABSENT_CHROMA_SIGNALS`.
+#[derive(Resource, Debug, Default)]
+pub struct SignalDefs(pub Vec<SignalDef>);
+
+impl SignalDefs {
+    pub fn load_default() -> Self {
+        let path = env::var("ABSENT_CHROMA_SIGNALS")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from("assets").join("signals.txt"));
+
+        Self::load(&path)
+    }
+
+    /// Reads the file at `path`. A missing file means no signals, `#` comments and bad lines are
+    /// skipped, and a repeated id keeps its first definition.
+    pub fn load(path: &Path) -> Self {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                if error.kind() != io::ErrorKind::NotFound {
+                    warn!("Could not read signals {}: {}", path.display(), error);
+                }
+                return Self::default();
+            }
+        };
+
+        Self::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> Self {
+        let mut defs: Vec<SignalDef> = vec![];
+
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match parse_line(line) {
+                Some(def) if defs.iter().all(|other| other.id != def.id) => defs.push(def),
+                _ => warn!("Skipped signal line {:?}", line),
+            }
+        }
+
+        Self(defs)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&SignalDef> {
+        self.0.iter().find(|def| def.id == id)
+    }
+}
@@ -0,0 +1,51 @@
+use bincode::{Decode, Encode};
+
+/// Players closer than this talk without loss.
+pub const CLEAR_RANGE: f32 = 20.0;
+
+/// Players this far apart cannot communicate at all.
+pub const LOST_RANGE: f32 = 60.0;
+
+/// How far back inside a range a worse link has to come before it counts as better again, so
+/// players standing on a boundary do not flicker between states.
+pub const HYSTERESIS: f32 = 2.0;
+
+/// How well the two players can reach each other, decided by the server from their distance.
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommState {
+    #[default]
+    Clear,
+    /// Chat loses characters and voice gets quieter.
+    Degraded,
+    /// Chat, voice and signals no longer reach the partner.
+    Lost,
+}
+
+impl CommState {
+    /// State for `distance` given the current one. Worse states start at the range boundaries,
+    /// better ones only [`HYSTERESIS`] inside them.
+    pub fn next(self, distance: f32) -> Self {
+        let clear_below = match self {
+            CommState::Clear => CLEAR_RANGE,
+            _ => CLEAR_RANGE - HYSTERESIS,
+        };
+        let lost_from = match self {
+            CommState::Lost => LOST_RANGE - HYSTERESIS,
+            _ => LOST_RANGE,
+        };
+
+        if distance >= lost_from {
+            CommState::Lost
+        } else if distance <= clear_below {
+            CommState::Clear
+        } else {
+            CommState::Degraded
+        }
+    }
+}
+
+/// Share of a message that survives `distance`: 1 within [`CLEAR_RANGE`], falling to 0 at
+/// [`LOST_RANGE`].
+pub fn clarity(distance: f32) -> f32 {
+    ((LOST_RANGE - distance) / (LOST_RANGE - CLEAR_RANGE)).clamp(0.0, 1.0)
+}
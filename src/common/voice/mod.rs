@@ -0,0 +1,172 @@
+use std::f32::consts::TAU;
+
+use bincode::{Decode, Encode};
+
+/// Voice is mono at this rate, in Hz.
+pub const SAMPLE_RATE: u32 = 16_000;
+
+/// Samples in one frame, 20 ms.
+pub const FRAME_SAMPLES: usize = 320;
+
+/// Encoded size of a full frame. Anything bigger is not relayed.
+pub const MAX_FRAME_BYTES: usize = FRAME_SAMPLES / 2;
+
+const STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+const INDEX_TABLE: [i32; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+
+/// One frame of IMA ADPCM, four bits per sample.
+///
+/// Every frame carries the coder state it starts from, so a lost frame is a gap and not a
+/// garbled stream.
+#[derive(Encode, Decode, Debug, Clone, PartialEq, Eq, Default)]
+pub struct VoiceFrame {
+    /// Counts up per frame, so the receiver can drop late and duplicated frames.
+    pub sequence: u32,
+    pub predictor: i16,
+    pub step_index: u8,
+    /// Two samples per byte, low nibble first.
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Coder {
+    predictor: i32,
+    step_index: i32,
+}
+
+impl Coder {
+    fn decode(&mut self, nibble: u8) -> i16 {
+        let step = STEP_TABLE[self.step_index as usize];
+
+        let mut diff = step >> 3;
+        if nibble & 4 != 0 {
+            diff += step;
+        }
+        if nibble & 2 != 0 {
+            diff += step >> 1;
+        }
+        if nibble & 1 != 0 {
+            diff += step >> 2;
+        }
+        if nibble & 8 != 0 {
+            diff = -diff;
+        }
+
+        self.predictor = (self.predictor + diff).clamp(i16::MIN as i32, i16::MAX as i32);
+        self.step_index = (self.step_index + INDEX_TABLE[nibble as usize]).clamp(0, 88);
+
+        self.predictor as i16
+    }
+
+    fn encode(&mut self, sample: i16) -> u8 {
+        let step = STEP_TABLE[self.step_index as usize];
+        let mut diff = sample as i32 - self.predictor;
+
+        let mut nibble = 0;
+        if diff < 0 {
+            nibble = 8;
+            diff = -diff;
+        }
+        if diff >= step {
+            nibble |= 4;
+            diff -= step;
+        }
+        if diff >= step >> 1 {
+            nibble |= 2;
+            diff -= step >> 1;
+        }
+        if diff >= step >> 2 {
+            nibble |= 1;
+        }
+
+        // Track what the decoder will reconstruct, not the input.
+        self.decode(nibble);
+
+        nibble
+    }
+}
+
+/// Compresses a stream of samples into frames, keeping the coder state between them.
+#[derive(Debug, Default)]
+pub struct VoiceEncoder {
+    coder: Coder,
+    sequence: u32,
+}
+
+impl VoiceEncoder {
+    pub fn encode(&mut self, samples: &[i16]) -> VoiceFrame {
+        let mut frame = VoiceFrame {
+            sequence: self.sequence,
+            predictor: self.coder.predictor as i16,
+            step_index: self.coder.step_index as u8,
+            data: Vec::with_capacity(samples.len().div_ceil(2)),
+        };
+
+        for pair in samples.chunks(2) {
+            let low = self.coder.encode(pair[0]);
+            let high = pair.get(1).map_or(0, |sample| self.coder.encode(*sample));
+
+            frame.data.push(low | (high << 4));
+        }
+
+        self.sequence = self.sequence.wrapping_add(1);
+
+        frame
+    }
+}
+
+/// Decodes a frame on its own. Odd sample counts come back with one trailing sample extra.
+pub fn decode(frame: &VoiceFrame) -> Vec<i16> {
+    let mut coder = Coder {
+        predictor: frame.predictor as i32,
+        step_index: (frame.step_index as i32).clamp(0, 88),
+    };
+
+    frame
+        .data
+        .iter()
+        .flat_map(|byte| [byte & 0x0f, byte >> 4])
+        .map(|nibble| coder.decode(nibble))
+        .collect()
+}
+
+/// Sine wave standing in for a microphone, so voice can be tested without one.
+#[derive(Debug, Clone)]
+pub struct TestTone {
+    pub frequency: f32,
+    pub amplitude: f32,
+    phase: f32,
+}
+
+impl TestTone {
+    pub fn new(frequency: f32) -> Self {
+        Self {
+            frequency,
+            amplitude: 0.25,
+            phase: 0.0,
+        }
+    }
+
+    pub fn frame(&mut self) -> Vec<i16> {
+        (0..FRAME_SAMPLES)
+            .map(|_| {
+                let sample = self.phase.sin() * self.amplitude * i16::MAX as f32;
+                self.phase = (self.phase + TAU * self.frequency / SAMPLE_RATE as f32) % TAU;
+                sample as i16
+            })
+            .collect()
+    }
+}
+
+impl Default for TestTone {
+    fn default() -> Self {
+        Self::new(440.0)
+    }
+}
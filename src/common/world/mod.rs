@@ -0,0 +1,130 @@
+use bevy::{ecs::resource::Resource, math::IVec2};
+
+/// Quads along each side of a terrain chunk, one world unit each.
+pub const CHUNK_SIZE: usize = 32;
+
+/// Heights in a chunk's heightfield, which shares its edge rows with the neighbouring chunks.
+pub const CHUNK_SIDE: usize = CHUNK_SIZE + 1;
+
+/// Noise cells per world unit of the lowest octave.
+const NOISE_SCALE: f32 = 0.1;
+
+/// Height of the tallest hills and depth of the deepest dips, in world units.
+pub const HEIGHT_SCALE: f32 = 2.0;
+
+/// World height the terrain's zero level sits at.
+pub const TERRAIN_Y: f32 = -2.0;
+
+/// Frequency and amplitude of each octave, broad hills first.
+const OCTAVES: [(f32, f32); 3] = [(1.0, 1.0), (2.0, 0.5), (4.0, 0.25)];
+
+/// Seed the server picked for the current world.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldSeed(pub u64);
+
+/// Terrain heights for a [`WorldSeed`].
+///
+/// Seeded gradient noise built from integer hashing, additions, multiplications and floors only,
+/// so client and server get the same bits on every platform. It does not use `rand`, whose
+/// generators are free to change between versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldGenerator {
+    seed: u64,
+}
+
+/// splitmix64 finalizer.
+fn mix(mut value: u64) -> u64 {
+    value = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+    value ^ (value >> 31)
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// One of eight gradients picked by `hash`, dotted with the offset from its corner.
+fn gradient(hash: u64, dx: f32, dz: f32) -> f32 {
+    match hash & 7 {
+        0 => dx + dz,
+        1 => dz - dx,
+        2 => dx - dz,
+        3 => -dx - dz,
+        4 => dx,
+        5 => -dx,
+        6 => dz,
+        _ => -dz,
+    }
+}
+
+impl WorldGenerator {
+    pub fn new(seed: WorldSeed) -> Self {
+        Self { seed: seed.0 }
+    }
+
+    pub fn seed(&self) -> WorldSeed {
+        WorldSeed(self.seed)
+    }
+
+    fn corner(&self, octave: usize, x: i32, z: i32) -> u64 {
+        let cell = ((x as u32 as u64) << 32) | z as u32 as u64;
+
+        mix(mix(self.seed ^ octave as u64) ^ cell)
+    }
+
+    /// Perlin noise in about `-1.0..=1.0`, zero on every integer point.
+    fn perlin(&self, octave: usize, x: f32, z: f32) -> f32 {
+        let (x0, z0) = (x.floor(), z.floor());
+        let (dx, dz) = (x - x0, z - z0);
+        let (xi, zi) = (x0 as i32, z0 as i32);
+
+        let n00 = gradient(self.corner(octave, xi, zi), dx, dz);
+        let n10 = gradient(self.corner(octave, xi + 1, zi), dx - 1.0, dz);
+        let n01 = gradient(self.corner(octave, xi, zi + 1), dx, dz - 1.0);
+        let n11 = gradient(self.corner(octave, xi + 1, zi + 1), dx - 1.0, dz - 1.0);
+
+        let (u, v) = (fade(dx), fade(dz));
+
+        lerp(lerp(n00, n10, u), lerp(n01, n11, u), v)
+    }
+
+    /// Terrain height at world position `x`, `z`, within [`HEIGHT_SCALE`] of zero.
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        let mut height = 0.0;
+        let mut total = 0.0;
+
+        for (octave, (frequency, amplitude)) in OCTAVES.into_iter().enumerate() {
+            let scale = NOISE_SCALE * frequency;
+            height += self.perlin(octave, x * scale, z * scale) * amplitude;
+            total += amplitude;
+        }
+
+        (height / total).clamp(-1.0, 1.0) * HEIGHT_SCALE
+    }
+
+    /// World height of the ground at `x`, `z`, the terrain height placed at [`TERRAIN_Y`].
+    pub fn ground_at(&self, x: f32, z: f32) -> f32 {
+        self.height_at(x, z) + TERRAIN_Y
+    }
+
+    /// Heights of the chunk at `coord` on the chunk grid, [`CHUNK_SIDE`] by [`CHUNK_SIDE`] with
+    /// x major, starting at the chunk's corner closest to negative infinity.
+    pub fn chunk_heights(&self, coord: IVec2) -> Vec<f32> {
+        let origin = coord * CHUNK_SIZE as i32;
+        let mut heights = Vec::with_capacity(CHUNK_SIDE * CHUNK_SIDE);
+
+        for x in 0..CHUNK_SIDE as i32 {
+            for z in 0..CHUNK_SIDE as i32 {
+                heights.push(self.height_at((origin.x + x) as f32, (origin.y + z) as f32));
+            }
+        }
+
+        heights
+    }
+}
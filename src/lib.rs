@@ -0,0 +1,7 @@
+#[cfg(feature = "client")]
+pub mod client;
+pub mod common;
+#[cfg(feature = "master")]
+pub mod master;
+#[cfg(feature = "server")]
+pub mod server;
@@ -0,0 +1,182 @@
+use std::{
+    collections::HashMap,
+    net::{SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+
+use crate::common::master::{
+    ListedServer, MASTER_PACKET_BYTES, MASTER_PORT, MasterMessage, SERVERS_PER_PAGE,
+};
+
+/// Servers that miss heartbeats for this long are dropped from the list.
+pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Most servers a single IP address may register, so one host cannot flood the list.
+const MAX_SERVERS_PER_IP: usize = 16;
+
+/// Keeps the list of dedicated servers that heartbeat with it and hands it out to clients.
+pub struct MasterPlugin;
+
+#[derive(Resource, Debug, Clone)]
+pub struct MasterSettings {
+    pub bind: SocketAddr,
+}
+
+impl Default for MasterSettings {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::from(([0, 0, 0, 0], MASTER_PORT)),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct MasterSocket(pub UdpSocket);
+
+struct Registration {
+    listing: ListedServer,
+    last_heartbeat: Duration,
+}
+
+/// Registered servers, keyed by game address.
+#[derive(Resource, Default)]
+pub struct Registry(HashMap<SocketAddr, Registration>);
+
+impl Registry {
+    pub fn servers(&self) -> impl Iterator<Item = &ListedServer> {
+        self.0.values().map(|registration| &registration.listing)
+    }
+}
+
+impl MasterPlugin {
+    fn bind(mut commands: Commands, settings: Res<MasterSettings>) {
+        let socket = UdpSocket::bind(settings.bind)
+            .and_then(|socket| {
+                socket.set_nonblocking(true)?;
+                Ok(socket)
+            })
+            .expect("Could not bind master server socket.");
+
+        info!(
+            "Master server listening on {}",
+            socket.local_addr().unwrap_or(settings.bind)
+        );
+
+        commands.insert_resource(MasterSocket(socket));
+    }
+
+    fn handle_packets(socket: Res<MasterSocket>, mut registry: ResMut<Registry>, time: Res<Time>) {
+        let mut buffer = [0u8; MASTER_PACKET_BYTES];
+        let now = time.elapsed();
+
+        while let Ok((len, from)) = socket.0.recv_from(&mut buffer) {
+            let Some(message) = MasterMessage::decode(&buffer[..len]) else {
+                continue;
+            };
+
+            match message {
+                MasterMessage::Heartbeat {
+                    port,
+                    name,
+                    version,
+                    players,
+                    max_players,
+                } => {
+                    let addr = SocketAddr::new(from.ip(), port);
+
+                    let listing = ListedServer {
+                        addr,
+                        name,
+                        version,
+                        players,
+                        max_players,
+                    };
+
+                    match registry.0.get_mut(&addr) {
+                        Some(registration) => {
+                            registration.listing = listing;
+                            registration.last_heartbeat = now;
+                        }
+                        None => {
+                            let from_ip = registry
+                                .0
+                                .keys()
+                                .filter(|registered| registered.ip() == addr.ip())
+                                .count();
+
+                            if from_ip >= MAX_SERVERS_PER_IP {
+                                warn!("Ignoring heartbeat from {}: too many servers", addr);
+                                continue;
+                            }
+
+                            info!("Registered server {:?} at {}", listing.name, addr);
+
+                            registry.0.insert(
+                                addr,
+                                Registration {
+                                    listing,
+                                    last_heartbeat: now,
+                                },
+                            );
+                        }
+                    }
+                }
+
+                MasterMessage::Unregister { port } => {
+                    let addr = SocketAddr::new(from.ip(), port);
+
+                    if registry.0.remove(&addr).is_some() {
+                        info!("Unregistered server at {}", addr);
+                    }
+                }
+
+                MasterMessage::ListRequest { page } => {
+                    let mut servers: Vec<&ListedServer> = registry.servers().collect();
+                    servers.sort_by_key(|server| server.addr);
+
+                    let start = page as usize * SERVERS_PER_PAGE;
+                    let end = (start + SERVERS_PER_PAGE).min(servers.len());
+
+                    let reply = MasterMessage::ServerList {
+                        page,
+                        more: end < servers.len(),
+                        servers: servers
+                            .get(start..end)
+                            .unwrap_or_default()
+                            .iter()
+                            .map(|server| (*server).clone())
+                            .collect(),
+                    };
+
+                    let _ = socket.0.send_to(&reply.encode(), from);
+                }
+
+                MasterMessage::ServerList { .. } => {}
+            }
+        }
+    }
+
+    fn expire(mut registry: ResMut<Registry>, time: Res<Time>) {
+        let now = time.elapsed();
+
+        registry.0.retain(|addr, registration| {
+            let alive = now - registration.last_heartbeat < HEARTBEAT_TIMEOUT;
+
+            if !alive {
+                info!("Server at {} stopped sending heartbeats", addr);
+            }
+
+            alive
+        });
+    }
+}
+
+impl Plugin for MasterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MasterSettings>();
+        app.insert_resource(Registry::default());
+        app.add_systems(Startup, Self::bind);
+        app.add_systems(Update, (Self::handle_packets, Self::expire).chain());
+    }
+}
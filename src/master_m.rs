@@ -0,0 +1,43 @@
+use std::{env, net::SocketAddr, process, time::Duration};
+
+use bevy::{app::ScheduleRunnerPlugin, log::LogPlugin, prelude::*};
+
+use absent_chroma::master::{MasterPlugin, MasterSettings};
+
+fn main() {
+    let mut settings = MasterSettings::default();
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--bind", Some(addr)) => match addr.parse::<SocketAddr>() {
+                Ok(addr) => settings.bind = addr,
+                Err(_) => {
+                    eprintln!("Invalid --bind address: {}", addr);
+                    process::exit(2);
+                }
+            },
+            _ => {
+                eprintln!("Usage: master [--bind <ip:port>]");
+                process::exit(2);
+            }
+        }
+    }
+
+    let mut app = App::new();
+
+    app.add_plugins(
+        MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+            1.0 / 60.0,
+        ))),
+    );
+
+    app.add_plugins(LogPlugin::default());
+
+    app.insert_resource(settings);
+
+    app.add_plugins(MasterPlugin);
+
+    app.run();
+}
@@ -0,0 +1,200 @@
+use std::{
+    collections::HashSet,
+    fmt, fs, io,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+
+/// A ban or allowlist rule, stored one per line as `user <name>`, `id <client id>` or `ip <addr>`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AccessEntry {
+    /// Lowercase, like the account names it is matched against.
+    Username(String),
+    /// The id a client picks for its connection, normally at random. It only holds until the
+    /// client reconnects, and a client can claim any id it likes, so it is for getting rid of
+    /// someone now. It is no reason to let anyone in.
+    ClientId(u64),
+    Ip(IpAddr),
+}
+
+impl AccessEntry {
+    pub fn parse(kind: &str, value: &str) -> Result<Self, String> {
+        let value = value.trim();
+
+        match kind {
+            "user" if !value.is_empty() => Ok(AccessEntry::Username(value.to_lowercase())),
+            "id" => value
+                .parse()
+                .map(AccessEntry::ClientId)
+                .map_err(|_| format!("Invalid client id: {:?}", value)),
+            "ip" => value
+                .parse()
+                .map(AccessEntry::Ip)
+                .map_err(|_| format!("Invalid ip address: {:?}", value)),
+            _ => Err(format!(
+                "Expected `user <name>`, `id <client id>` or `ip <addr>`, got {:?}",
+                kind
+            )),
+        }
+    }
+
+    fn parse_line(line: &str) -> Result<Self, String> {
+        let (kind, value) = line.split_once(' ').unwrap_or((line, ""));
+
+        Self::parse(kind, value)
+    }
+}
+
+impl fmt::Display for AccessEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessEntry::Username(username) => write!(f, "user {}", username),
+            AccessEntry::ClientId(client_id) => write!(f, "id {}", client_id),
+            AccessEntry::Ip(ip) => write!(f, "ip {}", ip),
+        }
+    }
+}
+
+/// File-backed ban list and optional allowlist, checked when a client connects.
+#[derive(Resource, Default, Debug)]
+pub struct AccessList {
+    pub bans: HashSet<AccessEntry>,
+    /// Only clients matching an entry may join. Disabled when `None`.
+    pub allow: Option<HashSet<AccessEntry>>,
+    ban_path: Option<PathBuf>,
+    allow_path: Option<PathBuf>,
+}
+
+impl AccessList {
+    pub fn load(ban_path: Option<PathBuf>, allow_path: Option<PathBuf>) -> Self {
+        let bans = ban_path.as_deref().map(read_entries).unwrap_or_default();
+        let allow = allow_path.as_deref().map(read_entries);
+
+        info!(
+            "Loaded {} ban(s), allowlist: {}",
+            bans.len(),
+            allow
+                .as_ref()
+                .map(|allow| format!("{} entries", allow.len()))
+                .unwrap_or("disabled".to_string())
+        );
+
+        Self {
+            bans,
+            allow,
+            ban_path,
+            allow_path,
+        }
+    }
+
+    /// Returns the reason a client with these identifiers may not join.
+    pub fn check(&self, username: &str, client_id: u64, ip: Option<IpAddr>) -> Result<(), String> {
+        let mut identifiers = vec![
+            AccessEntry::Username(username.to_lowercase()),
+            AccessEntry::ClientId(client_id),
+        ];
+        identifiers.extend(ip.map(AccessEntry::Ip));
+
+        if let Some(ban) = identifiers.iter().find(|entry| self.bans.contains(*entry)) {
+            return Err(format!("You are banned from this server ({}).", ban));
+        }
+
+        if let Some(allow) = &self.allow
+            && !identifiers.iter().any(|entry| allow.contains(entry))
+        {
+            return Err("You are not on this server's allowlist.".to_string());
+        }
+
+        Ok(())
+    }
+
+    pub fn ban(&mut self, entry: AccessEntry) -> bool {
+        let added = self.bans.insert(entry);
+
+        if added && let Some(path) = &self.ban_path {
+            write_entries(path, &self.bans);
+        }
+
+        added
+    }
+
+    pub fn unban(&mut self, entry: &AccessEntry) -> bool {
+        let removed = self.bans.remove(entry);
+
+        if removed && let Some(path) = &self.ban_path {
+            write_entries(path, &self.bans);
+        }
+
+        removed
+    }
+
+    pub fn allow(&mut self, entry: AccessEntry) -> Result<bool, String> {
+        let allow = self
+            .allow
+            .as_mut()
+            .ok_or("No allowlist configured, set ABSENT_CHROMA_ALLOWLIST.")?;
+
+        let added = allow.insert(entry);
+
+        if added && let Some(path) = &self.allow_path {
+            write_entries(path, allow);
+        }
+
+        Ok(added)
+    }
+
+    pub fn disallow(&mut self, entry: &AccessEntry) -> Result<bool, String> {
+        let allow = self
+            .allow
+            .as_mut()
+            .ok_or("No allowlist configured, set ABSENT_CHROMA_ALLOWLIST.")?;
+
+        let removed = allow.remove(entry);
+
+        if removed && let Some(path) = &self.allow_path {
+            write_entries(path, allow);
+        }
+
+        Ok(removed)
+    }
+}
+
+fn read_entries(path: &Path) -> HashSet<AccessEntry> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return HashSet::new(),
+        Err(error) => {
+            warn!("Could not read {}: {}", path.display(), error);
+            return HashSet::new();
+        }
+    };
+
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| match AccessEntry::parse_line(line) {
+            Ok(entry) => Some(entry),
+            Err(error) => {
+                warn!("Skipping line in {}: {}", path.display(), error);
+                None
+            }
+        })
+        .collect()
+}
+
+fn write_entries(path: &Path, entries: &HashSet<AccessEntry>) {
+    let mut entries: Vec<&AccessEntry> = entries.iter().collect();
+    entries.sort();
+
+    let contents: String = entries
+        .into_iter()
+        .map(|entry| format!("{}\n", entry))
+        .collect();
+
+    if let Err(error) = fs::write(path, contents) {
+        warn!("Could not write {}: {}", path.display(), error);
+    }
+}
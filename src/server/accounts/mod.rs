@@ -0,0 +1,467 @@
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    mem,
+    path::{Path, PathBuf},
+};
+
+use argon2::{
+    Argon2,
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+};
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task, block_on, futures_lite::future},
+};
+use bevy_renet::renet::ServerEvent;
+use rand::TryRngCore;
+use zeroize::Zeroizing;
+
+use crate::{
+    common::{
+        account::{validate_password, validate_username},
+        network::{ConnectedUsers, ServerMessage, UserData},
+    },
+    server::{
+        access::AccessList, config::ServerSettings, encryption::SecureChannel, network::KickClient,
+        replay::RecordedEvent,
+    },
+};
+
+const BAD_CREDENTIALS: &str = "Wrong username or password.";
+
+/// Registers and logs in accounts, which clients must do before they can join a session.
+pub struct AccountsPlugin;
+
+/// A Login or Register message from a client that finished the KEM handshake.
+#[derive(Message, Clone)]
+pub struct ClientLogin {
+    pub client_id: u64,
+    pub username: String,
+    pub password: Zeroizing<String>,
+    pub register: bool,
+}
+
+/// Account name of every client logged in on its current connection.
+#[derive(Resource, Default, Debug)]
+pub struct LoggedInUsers(pub HashMap<u64, String>);
+
+impl LoggedInUsers {
+    pub fn contains(&self, client_id: u64) -> bool {
+        self.0.contains_key(&client_id)
+    }
+}
+
+struct Account {
+    username: String,
+    /// Argon2 hash in PHC string format, salt and parameters included.
+    hash: String,
+}
+
+/// Accounts stored one per line as `username hash`, keyed by lowercase username.
+#[derive(Resource)]
+pub struct AccountStore {
+    path: PathBuf,
+    accounts: HashMap<String, Account>,
+    /// Checked against for unknown usernames, so they take as long to refuse as a wrong password.
+    dummy_hash: String,
+}
+
+/// A password check taken out of the store, so the hashing can run off the tick thread.
+enum LoginCheck {
+    Verify {
+        /// Registered name, `None` when the check runs against the dummy hash.
+        account: Option<String>,
+        hash: String,
+        password: Zeroizing<String>,
+    },
+    /// Registering an existing name logs in, but only with its own password.
+    Existing {
+        account: String,
+        hash: String,
+        password: Zeroizing<String>,
+    },
+    Create {
+        username: String,
+        password: Zeroizing<String>,
+    },
+}
+
+/// What is left of a login once the password has been checked.
+enum Checked {
+    Verified(String),
+    /// A new account, saved when the check comes back.
+    Hashed {
+        username: String,
+        hash: String,
+    },
+}
+
+/// Argon2 hash of `password` with a fresh random salt.
+fn new_hash(password: &str) -> Result<String, String> {
+    let mut salt = [0u8; 16];
+    rand::rngs::OsRng
+        .try_fill_bytes(&mut salt)
+        .map_err(|_| "Could not create the account.".to_string())?;
+
+    let salt = SaltString::encode_b64(&salt).expect("Salt has a valid length.");
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|_| "Could not create the account.".to_string())
+}
+
+fn password_matches(hash: &str, password: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+impl LoginCheck {
+    /// Does the expensive part. Tens of milliseconds, so the server runs it on the async compute
+    /// pool.
+    fn run(self) -> Result<Checked, String> {
+        match self {
+            LoginCheck::Verify {
+                account,
+                hash,
+                password,
+            } => {
+                let matches = password_matches(&hash, &password);
+
+                match account {
+                    Some(account) if matches => Ok(Checked::Verified(account)),
+                    _ => Err(BAD_CREDENTIALS.to_string()),
+                }
+            }
+
+            LoginCheck::Existing {
+                account,
+                hash,
+                password,
+            } => {
+                if password_matches(&hash, &password) {
+                    Ok(Checked::Verified(account))
+                } else {
+                    Err("That username is taken.".to_string())
+                }
+            }
+
+            LoginCheck::Create { username, password } => Ok(Checked::Hashed {
+                hash: new_hash(&password)?,
+                username,
+            }),
+        }
+    }
+}
+
+impl AccountStore {
+    /// Reads the file at `path`. A missing file is an empty store and bad lines are skipped.
+    pub fn load(path: &Path) -> Self {
+        let accounts = match fs::read_to_string(path) {
+            Ok(contents) => contents
+                .lines()
+                .filter_map(|line| {
+                    let (username, hash) = line.split_once(' ')?;
+                    PasswordHash::new(hash).ok()?;
+
+                    Some((
+                        username.to_lowercase(),
+                        Account {
+                            username: username.to_string(),
+                            hash: hash.to_string(),
+                        },
+                    ))
+                })
+                .collect(),
+            Err(error) => {
+                if error.kind() != io::ErrorKind::NotFound {
+                    warn!("Could not read accounts {}: {}", path.display(), error);
+                }
+                HashMap::new()
+            }
+        };
+
+        let salt = SaltString::encode_b64(&[0u8; 16]).expect("Salt has a valid length.");
+        let dummy_hash = Argon2::default()
+            .hash_password(b"no such account", &salt)
+            .expect("Could not hash the dummy password.")
+            .to_string();
+
+        Self {
+            path: path.to_path_buf(),
+            accounts,
+            dummy_hash,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// Takes what checking `password` for `username` needs out of the store.
+    fn prepare(
+        &self,
+        username: &str,
+        password: &str,
+        register: bool,
+    ) -> Result<LoginCheck, String> {
+        let password = Zeroizing::new(password.to_string());
+        let account = self.accounts.get(&username.to_lowercase());
+
+        match (account, register) {
+            (Some(account), true) => Ok(LoginCheck::Existing {
+                account: account.username.clone(),
+                hash: account.hash.clone(),
+                password,
+            }),
+
+            (None, true) => {
+                validate_username(username)?;
+                validate_password(&password)?;
+
+                Ok(LoginCheck::Create {
+                    username: username.to_string(),
+                    password,
+                })
+            }
+
+            (account, false) => Ok(LoginCheck::Verify {
+                account: account.map(|account| account.username.clone()),
+                hash: account
+                    .map_or_else(|| self.dummy_hash.clone(), |account| account.hash.clone()),
+                password,
+            }),
+        }
+    }
+
+    /// Saves a new account, unless someone registered the name while its password was hashed.
+    fn complete(&mut self, checked: Checked) -> Result<String, String> {
+        let (username, hash) = match checked {
+            Checked::Verified(username) => return Ok(username),
+            Checked::Hashed { username, hash } => (username, hash),
+        };
+
+        if self.accounts.contains_key(&username.to_lowercase()) {
+            return Err("That username is taken.".to_string());
+        }
+
+        let line = format!("{} {}\n", username, hash);
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()));
+
+        if let Err(error) = written {
+            warn!("Could not save accounts {}: {}", self.path.display(), error);
+            return Err("Could not create the account.".to_string());
+        }
+
+        info!("Registered account {}", username);
+
+        self.accounts.insert(
+            username.to_lowercase(),
+            Account {
+                username: username.clone(),
+                hash,
+            },
+        );
+
+        Ok(username)
+    }
+
+    /// Checks the password and returns the account name as it was registered.
+    pub fn verify(&self, username: &str, password: &str) -> Result<String, String> {
+        match self.prepare(username, password, false)?.run()? {
+            Checked::Verified(username) => Ok(username),
+            Checked::Hashed { .. } => Err(BAD_CREDENTIALS.to_string()),
+        }
+    }
+
+    /// Creates an account and appends it to the file. An existing account only logs in, and only
+    /// with its own password.
+    pub fn register(&mut self, username: &str, password: &str) -> Result<String, String> {
+        let checked = self.prepare(username, password, true)?.run()?;
+
+        self.complete(checked)
+    }
+}
+
+struct PendingLogin {
+    client_id: u64,
+    /// Name as the client typed it, for the logs.
+    username: String,
+    task: Task<Result<Checked, String>>,
+}
+
+/// Password checks running on the async compute pool, at most one per client.
+#[derive(Resource, Default)]
+pub struct PendingLogins(Vec<PendingLogin>);
+
+impl PendingLogins {
+    pub fn contains(&self, client_id: u64) -> bool {
+        self.0.iter().any(|login| login.client_id == client_id)
+    }
+
+    /// True once every check is done and only waits for the next tick to pick it up.
+    pub fn all_finished(&self) -> bool {
+        self.0.iter().all(|login| login.task.is_finished())
+    }
+}
+
+fn reject(
+    channel: &mut SecureChannel,
+    kicks: &mut MessageWriter<KickClient>,
+    client_id: u64,
+    username: &str,
+    reason: String,
+) {
+    warn!(
+        "Login failed => username: {} id: {} reason: {}",
+        username, client_id, reason
+    );
+    channel.metrics.login_failures += 1;
+
+    channel.send(
+        client_id,
+        &ServerMessage::LoginRejected {
+            reason: reason.clone(),
+        },
+    );
+    kicks.write(KickClient { client_id, reason });
+}
+
+impl AccountsPlugin {
+    fn load_accounts(mut commands: Commands, settings: Res<ServerSettings>) {
+        let store = AccountStore::load(&settings.accounts);
+
+        info!(
+            "Loaded {} accounts from {}",
+            store.len(),
+            settings.accounts.display()
+        );
+
+        commands.insert_resource(store);
+    }
+
+    /// Hands password checks to the async compute pool, Argon2 is too slow for the tick.
+    fn start_logins(
+        mut logins: MessageReader<ClientLogin>,
+        store: Res<AccountStore>,
+        logged_in: Res<LoggedInUsers>,
+        mut pending: ResMut<PendingLogins>,
+        mut channel: SecureChannel,
+        mut kicks: MessageWriter<KickClient>,
+    ) {
+        for login in logins.read() {
+            let client_id = login.client_id;
+
+            let check = if logged_in.contains(client_id) || pending.contains(client_id) {
+                Err("Already logged in.".to_string())
+            } else {
+                store.prepare(&login.username, &login.password, login.register)
+            };
+
+            match check {
+                Ok(check) => pending.0.push(PendingLogin {
+                    client_id,
+                    username: login.username.clone(),
+                    task: AsyncComputeTaskPool::get().spawn(async move { check.run() }),
+                }),
+                Err(reason) => reject(&mut channel, &mut kicks, client_id, &login.username, reason),
+            }
+        }
+    }
+
+    fn finish_logins(
+        mut pending: ResMut<PendingLogins>,
+        mut store: ResMut<AccountStore>,
+        mut logged_in: ResMut<LoggedInUsers>,
+        mut users: ResMut<ConnectedUsers>,
+        access: Res<AccessList>,
+        mut channel: SecureChannel,
+        mut kicks: MessageWriter<KickClient>,
+    ) {
+        let mut finished = Vec::new();
+
+        pending.0.retain_mut(|login| {
+            let Some(result) = block_on(future::poll_once(&mut login.task)) else {
+                return true;
+            };
+
+            finished.push((login.client_id, mem::take(&mut login.username), result));
+            false
+        });
+
+        for (client_id, typed, result) in finished {
+            let result = result
+                .and_then(|checked| store.complete(checked))
+                .and_then(|username| {
+                    if logged_in
+                        .0
+                        .values()
+                        .any(|other| other.to_lowercase() == username.to_lowercase())
+                    {
+                        return Err(format!("{} is already playing.", username));
+                    }
+
+                    access.check(&username, client_id, None)?;
+
+                    Ok(username)
+                });
+
+            match result {
+                Ok(username) => {
+                    info!("Logged in => username: {} id: {}", username, client_id);
+                    channel.metrics.logins += 1;
+                    channel.recorder.record(RecordedEvent::LoggedIn {
+                        client_id,
+                        username: username.clone(),
+                    });
+
+                    users.0.insert(client_id, UserData::from_str(&username));
+                    logged_in.0.insert(client_id, username.clone());
+
+                    channel.send(client_id, &ServerMessage::LoginAccepted { username });
+                }
+
+                Err(reason) => reject(&mut channel, &mut kicks, client_id, &typed, reason),
+            }
+        }
+    }
+
+    fn forget_logins(
+        mut event_reader: MessageReader<ServerEvent>,
+        mut logged_in: ResMut<LoggedInUsers>,
+        mut pending: ResMut<PendingLogins>,
+    ) {
+        for event in event_reader.read() {
+            if let ServerEvent::ClientDisconnected { client_id, .. } = event {
+                logged_in.0.remove(client_id);
+                pending.0.retain(|login| login.client_id != *client_id);
+            }
+        }
+    }
+}
+
+impl Plugin for AccountsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LoggedInUsers::default());
+        app.insert_resource(PendingLogins::default());
+        app.add_message::<ClientLogin>();
+        app.add_systems(Startup, Self::load_accounts);
+        app.add_systems(
+            Update,
+            (Self::start_logins, Self::finish_logins, Self::forget_logins).chain(),
+        );
+    }
+}
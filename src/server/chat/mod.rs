@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+use rand::{SeedableRng, rngs::StdRng};
+
+use crate::{
+    common::{
+        chat::{degrade, sanitize},
+        network::ServerMessage,
+        tether::CommState,
+        world::WorldSeed,
+    },
+    server::{
+        encryption::SecureChannel,
+        session::{ServerPlayer, Sessions},
+        tether::Tether,
+    },
+};
+
+/// Relays chat between the two players, degraded or dropped by the [`Tether`] between them.
+pub struct ChatPlugin;
+
+#[derive(Message, Debug, Clone)]
+pub struct ClientChat {
+    pub client_id: u64,
+    pub text: String,
+}
+
+/// Picks the characters lost to a degraded link. Seeded from the [`WorldSeed`], so a replayed
+/// match degrades its chat the same way.
+#[derive(Resource)]
+pub struct ChatRng(pub StdRng);
+
+impl ChatPlugin {
+    fn seed_rng(mut commands: Commands, seed: Res<WorldSeed>) {
+        commands.insert_resource(ChatRng(StdRng::seed_from_u64(seed.0)));
+    }
+
+    fn relay_chat(
+        mut chats: MessageReader<ClientChat>,
+        sessions: Res<Sessions>,
+        tether: Res<Tether>,
+        players: Query<&Transform, With<ServerPlayer>>,
+        mut rng: ResMut<ChatRng>,
+        mut channel: SecureChannel,
+    ) {
+        for chat in chats.read() {
+            let Some(session) = sessions.by_client(chat.client_id) else {
+                continue;
+            };
+
+            let Some(text) = sanitize(&chat.text) else {
+                debug!("Dropped empty or oversized chat from {}", session.username);
+                continue;
+            };
+
+            let Some(partner_id) = sessions
+                .partner_of(session.role)
+                .and_then(|partner| partner.client_id)
+            else {
+                continue;
+            };
+
+            let Ok(from) = players.get(session.entity) else {
+                continue;
+            };
+
+            let text = match tether.state {
+                CommState::Clear => text,
+                CommState::Degraded => degrade(&text, tether.clarity, &mut rng.0),
+                CommState::Lost => {
+                    channel.metrics.chat_out_of_range += 1;
+                    continue;
+                }
+            };
+
+            channel.metrics.chat_relayed += 1;
+
+            channel.send(
+                partner_id,
+                &ServerMessage::Chat {
+                    text,
+                    clarity: tether.clarity,
+                    translation: from.translation.to_array(),
+                },
+            );
+        }
+    }
+}
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<ClientChat>();
+        app.add_systems(Startup, Self::seed_rng);
+        app.add_systems(Update, Self::relay_chat);
+    }
+}
@@ -0,0 +1,156 @@
+use std::{env, net::SocketAddr, path::PathBuf, str::FromStr, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::common::netsim::LinkConditions;
+
+/// Runtime settings for the dedicated server, read from `ABSENT_CHROMA_*` environment variables.
+#[derive(Resource, Debug, Clone)]
+pub struct ServerSettings {
+    /// Where to write a snapshot of the match on shutdown. Nothing is saved when unset.
+    pub save_path: Option<PathBuf>,
+    /// How long reliable channels get to deliver the shutdown notice before the server exits.
+    pub shutdown_drain: Duration,
+    /// Fixed world seed. A random one is picked when unset.
+    pub seed: Option<u64>,
+    /// Unix socket the admin console also listens on.
+    pub console_socket: Option<PathBuf>,
+    pub ban_list: PathBuf,
+    /// Account store, one `username hash` line per account.
+    pub accounts: PathBuf,
+    /// Player profile store, one line of stats per account.
+    pub profiles: PathBuf,
+    /// Only clients listed in this file may join. Everyone may join when unset.
+    pub allowlist: Option<PathBuf>,
+    pub rate_limits: RateLimits,
+    /// Address of the Prometheus metrics endpoint. Disabled when unset.
+    pub metrics_addr: Option<SocketAddr>,
+    /// File the JSON lines network log is appended to. Disabled when unset.
+    pub net_log: Option<PathBuf>,
+    /// Also write decrypted message payloads to the network log.
+    pub capture_payloads: bool,
+    /// File the match is recorded to for later replay. Nothing is recorded when unset.
+    pub record_path: Option<PathBuf>,
+    /// Impairments for the conditioned port. It is only opened when some are configured.
+    pub netsim: Option<LinkConditions>,
+    pub netsim_port: u16,
+    /// Name shown to LAN clients.
+    pub server_name: String,
+    /// Answer LAN discovery probes.
+    pub discovery: bool,
+    /// Master server to register with. The server stays unlisted when unset.
+    pub master_addr: Option<SocketAddr>,
+}
+
+/// Budgets applied to every client, separately for each channel.
+#[derive(Debug, Clone)]
+pub struct RateLimits {
+    pub messages_per_second: f32,
+    pub bytes_per_second: f32,
+    /// How many seconds of budget a client may spend at once.
+    pub burst_seconds: f32,
+    /// Largest message accepted from the wire, before decryption.
+    pub max_message_bytes: usize,
+    /// Dropped messages tolerated, leaking one per second, before the client is disconnected.
+    pub max_strikes: f32,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            messages_per_second: 120.0,
+            bytes_per_second: 64.0 * 1024.0,
+            burst_seconds: 2.0,
+            max_message_bytes: 8 * 1024,
+            max_strikes: 100.0,
+        }
+    }
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            save_path: None,
+            shutdown_drain: Duration::from_millis(500),
+            seed: None,
+            console_socket: None,
+            ban_list: PathBuf::from("bans.txt"),
+            accounts: PathBuf::from("accounts.txt"),
+            profiles: PathBuf::from("profiles.txt"),
+            allowlist: None,
+            rate_limits: RateLimits::default(),
+            metrics_addr: None,
+            net_log: None,
+            capture_payloads: false,
+            record_path: None,
+            netsim: None,
+            netsim_port: 42072,
+            server_name: "Absent Chroma".to_string(),
+            discovery: true,
+            master_addr: None,
+        }
+    }
+}
+
+impl ServerSettings {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            save_path: var("SAVE_PATH").map(PathBuf::from),
+            shutdown_drain: parse("SHUTDOWN_DRAIN_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.shutdown_drain),
+            seed: parse("SEED"),
+            console_socket: var("CONSOLE_SOCKET").map(PathBuf::from),
+            ban_list: var("BAN_LIST")
+                .map(PathBuf::from)
+                .unwrap_or(defaults.ban_list),
+            accounts: var("ACCOUNTS")
+                .map(PathBuf::from)
+                .unwrap_or(defaults.accounts),
+            profiles: var("PROFILES")
+                .map(PathBuf::from)
+                .unwrap_or(defaults.profiles),
+            allowlist: var("ALLOWLIST").map(PathBuf::from),
+            rate_limits: RateLimits {
+                messages_per_second: parse("RATE_MESSAGES_PER_SECOND")
+                    .unwrap_or(defaults.rate_limits.messages_per_second),
+                bytes_per_second: parse("RATE_BYTES_PER_SECOND")
+                    .unwrap_or(defaults.rate_limits.bytes_per_second),
+                burst_seconds: parse("RATE_BURST_SECONDS")
+                    .unwrap_or(defaults.rate_limits.burst_seconds),
+                max_message_bytes: parse("MAX_MESSAGE_BYTES")
+                    .unwrap_or(defaults.rate_limits.max_message_bytes),
+                max_strikes: parse("MAX_STRIKES").unwrap_or(defaults.rate_limits.max_strikes),
+            },
+            metrics_addr: parse("METRICS_ADDR"),
+            net_log: var("NET_LOG").map(PathBuf::from),
+            capture_payloads: parse("CAPTURE_PAYLOADS").unwrap_or(defaults.capture_payloads),
+            record_path: var("RECORD").map(PathBuf::from),
+            netsim: LinkConditions::from_env(),
+            netsim_port: parse("NETSIM_PORT").unwrap_or(defaults.netsim_port),
+            server_name: var("SERVER_NAME").unwrap_or(defaults.server_name),
+            discovery: parse("DISCOVERY").unwrap_or(defaults.discovery),
+            master_addr: parse("MASTER_ADDR"),
+        }
+    }
+}
+
+fn var(name: &str) -> Option<String> {
+    env::var(format!("ABSENT_CHROMA_{}", name))
+        .ok()
+        .filter(|value| !value.is_empty())
+}
+
+fn parse<T: FromStr>(name: &str) -> Option<T> {
+    let value = var(name)?;
+
+    match value.parse() {
+        Ok(parsed) => Some(parsed),
+        Err(_) => {
+            warn!("Ignoring invalid ABSENT_CHROMA_{}: {}", name, value);
+            None
+        }
+    }
+}
@@ -0,0 +1,484 @@
+use std::{
+    io::{self, BufRead},
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+use bevy::prelude::*;
+use bevy_renet::netcode::NetcodeServerTransport;
+
+use crate::{
+    common::{
+        netsim::NetworkConditioner,
+        network::{ConnectedUsers, ServerMessage},
+        world::WorldSeed,
+    },
+    server::{
+        access::{AccessEntry, AccessList},
+        config::ServerSettings,
+        encryption::{DKeyStore, SecureChannel},
+        network::KickClient,
+        session::{MatchState, Sessions},
+        shutdown::RequestShutdown,
+    },
+};
+
+const HELP: &str = "Commands: help, list, kick <id> [reason], say <text>, seed, state, stats, shutdown [reason], \
+ban|unban|allow|disallow <user|id|ip> <value>, access, \
+netsim [off | latency|jitter|loss|duplicate|reorder <value>]";
+
+pub struct ConsolePlugin;
+
+/// A command read from stdin or the admin socket.
+///
+/// Socket connections wait on `reply` for the output; stdin output goes to the log.
+struct ConsoleLine {
+    text: String,
+    reply: Option<Sender<String>>,
+}
+
+#[derive(Resource)]
+struct ConsoleInput(Mutex<Receiver<ConsoleLine>>);
+
+enum Command {
+    Help,
+    List,
+    Kick(u64, String),
+    Say(String),
+    Seed,
+    State,
+    Stats,
+    Shutdown(String),
+    Ban(AccessEntry),
+    Unban(AccessEntry),
+    Allow(AccessEntry),
+    Disallow(AccessEntry),
+    Access,
+    Netsim(NetsimChange),
+}
+
+enum NetsimChange {
+    Show,
+    Off,
+    Set(String, String),
+}
+
+impl Command {
+    fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (name, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        match name {
+            "help" => Ok(Command::Help),
+            "list" => Ok(Command::List),
+            "kick" => {
+                let (id, reason) = rest.split_once(' ').unwrap_or((rest, ""));
+                let id = id
+                    .parse()
+                    .map_err(|_| format!("Invalid client id: {:?}", id))?;
+                let reason = match reason.trim() {
+                    "" => "Kicked by the server operator.".to_string(),
+                    reason => reason.to_string(),
+                };
+
+                Ok(Command::Kick(id, reason))
+            }
+            "say" if !rest.is_empty() => Ok(Command::Say(rest.to_string())),
+            "say" => Err("Usage: say <text>".to_string()),
+            "seed" => Ok(Command::Seed),
+            "state" => Ok(Command::State),
+            "stats" => Ok(Command::Stats),
+            "shutdown" => Ok(Command::Shutdown(match rest {
+                "" => "Server shut down by the operator.".to_string(),
+                reason => reason.to_string(),
+            })),
+            "ban" | "unban" | "allow" | "disallow" => {
+                let (kind, value) = rest.split_once(' ').unwrap_or((rest, ""));
+                let entry = AccessEntry::parse(kind, value)?;
+
+                Ok(match name {
+                    "ban" => Command::Ban(entry),
+                    "unban" => Command::Unban(entry),
+                    "allow" => Command::Allow(entry),
+                    _ => Command::Disallow(entry),
+                })
+            }
+            "access" => Ok(Command::Access),
+            "netsim" => match rest.split_once(' ') {
+                _ if rest.is_empty() => Ok(Command::Netsim(NetsimChange::Show)),
+                _ if rest == "off" => Ok(Command::Netsim(NetsimChange::Off)),
+                Some((setting, value)) => Ok(Command::Netsim(NetsimChange::Set(
+                    setting.to_string(),
+                    value.trim().to_string(),
+                ))),
+                None => Err("Usage: netsim [off | <setting> <value>]".to_string()),
+            },
+            _ => Err(format!("Unknown command: {:?}. {}", name, HELP)),
+        }
+    }
+}
+
+impl ConsolePlugin {
+    fn spawn_readers(mut commands: Commands, settings: Res<ServerSettings>) {
+        let (sender, receiver) = mpsc::channel();
+
+        let stdin_sender = sender.clone();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines().map_while(Result::ok) {
+                let line = ConsoleLine {
+                    text: line,
+                    reply: None,
+                };
+
+                if stdin_sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        if let Some(path) = settings.console_socket.clone() {
+            #[cfg(unix)]
+            thread::spawn(move || socket::listen(path, sender));
+
+            #[cfg(not(unix))]
+            warn!(
+                "Admin console sockets are only supported on Unix, ignoring {}",
+                path.display()
+            );
+        }
+
+        commands.insert_resource(ConsoleInput(Mutex::new(receiver)));
+    }
+
+    fn run_commands(
+        input: Res<ConsoleInput>,
+        mut channel: SecureChannel,
+        sessions: Res<Sessions>,
+        users: Res<ConnectedUsers>,
+        match_state: Res<MatchState>,
+        seed: Res<WorldSeed>,
+        dks: Res<DKeyStore>,
+        mut kicks: MessageWriter<KickClient>,
+        mut shutdown: MessageWriter<RequestShutdown>,
+        mut access: ResMut<AccessList>,
+        transport: Option<Res<NetcodeServerTransport>>,
+        conditioner: Option<Res<NetworkConditioner>>,
+    ) {
+        let receiver = input.0.lock().expect("Console input lock poisoned.");
+
+        while let Ok(line) = receiver.try_recv() {
+            if line.text.trim().is_empty() {
+                continue;
+            }
+
+            let output = match Command::parse(&line.text) {
+                Err(error) => error,
+
+                Ok(Command::Help) => HELP.to_string(),
+
+                Ok(Command::List) => {
+                    let lines: Vec<String> = channel
+                        .server
+                        .clients_id()
+                        .into_iter()
+                        .map(|client_id| {
+                            let username = users
+                                .0
+                                .get(&client_id)
+                                .map(|user| user.to_username())
+                                .unwrap_or_default();
+                            let role = sessions.by_client(client_id).map(|session| session.role);
+
+                            format!("id: {} username: {} role: {:?}", client_id, username, role)
+                        })
+                        .collect();
+
+                    if lines.is_empty() {
+                        "No clients connected.".to_string()
+                    } else {
+                        lines.join("\n")
+                    }
+                }
+
+                Ok(Command::Kick(client_id, reason)) => {
+                    if channel.server.is_connected(client_id) {
+                        kicks.write(KickClient { client_id, reason });
+                        format!("Kicking {}.", client_id)
+                    } else {
+                        format!("No client with id {}.", client_id)
+                    }
+                }
+
+                Ok(Command::Say(text)) => {
+                    let message = ServerMessage::Notice(text);
+                    let sent = channel
+                        .server
+                        .clients_id()
+                        .into_iter()
+                        .filter(|client_id| channel.send(*client_id, &message))
+                        .count();
+
+                    format!("Sent to {} client(s).", sent)
+                }
+
+                Ok(Command::Seed) => format!("World seed: {}", seed.0),
+
+                Ok(Command::State) => {
+                    let mut lines = vec![format!("Match state: {:?}", *match_state)];
+
+                    for session in &sessions.0 {
+                        let status = match (session.client_id, session.disconnected_at) {
+                            (Some(client_id), _) => format!("connected as {}", client_id),
+                            (None, Some(at)) => format!("reconnecting since {:.0?}", at),
+                            (None, None) => "not connected".to_string(),
+                        };
+
+                        lines.push(format!(
+                            "{:?}: {} ({})",
+                            session.role, session.username, status
+                        ));
+                    }
+
+                    lines.join("\n")
+                }
+
+                Ok(Command::Stats) => {
+                    let mut lines = vec![format!(
+                        "clients: {} sessions: {} dkeys: {} ssks: {} nonces: {}",
+                        channel.server.connected_clients(),
+                        sessions.0.len(),
+                        dks.0.len(),
+                        channel.ssks.0.len(),
+                        channel.nonce.0.len(),
+                    )];
+
+                    for client_id in channel.server.clients_id() {
+                        if let Ok(info) = channel.server.network_info(client_id) {
+                            lines.push(format!(
+                                "id: {} rtt: {:.1}ms loss: {:.1}% sent: {:.0}B/s received: {:.0}B/s",
+                                client_id,
+                                info.rtt * 1000.0,
+                                info.packet_loss * 100.0,
+                                info.bytes_sent_per_second,
+                                info.bytes_received_per_second,
+                            ));
+                        }
+                    }
+
+                    lines.join("\n")
+                }
+
+                Ok(Command::Shutdown(reason)) => {
+                    shutdown.write(RequestShutdown { reason });
+                    "Shutting down.".to_string()
+                }
+
+                Ok(Command::Ban(entry)) => {
+                    let output = if access.ban(entry.clone()) {
+                        format!("Banned {}.", entry)
+                    } else {
+                        format!("{} was already banned.", entry)
+                    };
+
+                    // Apply the ban to anyone already connected.
+                    for client_id in channel.server.clients_id() {
+                        let username = users
+                            .0
+                            .get(&client_id)
+                            .map(|user| user.to_username())
+                            .unwrap_or_default();
+                        // Without a netcode transport there are no addresses to match.
+                        let ip = transport
+                            .as_ref()
+                            .and_then(|transport| transport.client_addr(client_id))
+                            .map(|addr| addr.ip());
+
+                        if let Err(reason) = access.check(username, client_id, ip) {
+                            kicks.write(KickClient { client_id, reason });
+                        }
+                    }
+
+                    output
+                }
+
+                Ok(Command::Unban(entry)) => {
+                    if access.unban(&entry) {
+                        format!("Unbanned {}.", entry)
+                    } else {
+                        format!("{} was not banned.", entry)
+                    }
+                }
+
+                Ok(Command::Allow(entry)) => match access.allow(entry.clone()) {
+                    Ok(true) => format!("Allowed {}.", entry),
+                    Ok(false) => format!("{} was already allowed.", entry),
+                    Err(error) => error,
+                },
+
+                Ok(Command::Disallow(entry)) => match access.disallow(&entry) {
+                    Ok(true) => format!("Removed {} from the allowlist.", entry),
+                    Ok(false) => format!("{} was not on the allowlist.", entry),
+                    Err(error) => error,
+                },
+
+                Ok(Command::Access) => {
+                    let mut bans: Vec<String> =
+                        access.bans.iter().map(|entry| entry.to_string()).collect();
+                    bans.sort();
+
+                    let allow = match &access.allow {
+                        Some(allow) => {
+                            let mut allow: Vec<String> =
+                                allow.iter().map(|entry| entry.to_string()).collect();
+                            allow.sort();
+                            format!("[{}]", allow.join(", "))
+                        }
+                        None => "disabled".to_string(),
+                    };
+
+                    format!("bans: [{}]\nallowlist: {}", bans.join(", "), allow)
+                }
+
+                Ok(Command::Netsim(change)) => match &conditioner {
+                    None => "Network conditioner is not running, set ABSENT_CHROMA_NETSIM_* to \
+                        start it."
+                        .to_string(),
+
+                    Some(conditioner) => {
+                        let mut conditions = conditioner.conditions();
+
+                        let result = match change {
+                            NetsimChange::Show => Ok(()),
+                            NetsimChange::Off => {
+                                conditions = Default::default();
+                                Ok(())
+                            }
+                            NetsimChange::Set(setting, value) => conditions.set(&setting, &value),
+                        };
+
+                        match result {
+                            Ok(()) => {
+                                conditioner.set_conditions(conditions);
+                                format!("{} => {}", conditioner.local_addr(), conditions)
+                            }
+                            Err(error) => error,
+                        }
+                    }
+                },
+            };
+
+            match line.reply {
+                Some(reply) => {
+                    let _ = reply.send(output);
+                }
+                None => {
+                    for output_line in output.lines() {
+                        info!("{}", output_line);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+mod socket {
+    use std::{
+        fs::{self, Permissions},
+        io::{BufRead, BufReader, Write},
+        os::unix::{
+            fs::{FileTypeExt, PermissionsExt},
+            net::UnixListener,
+        },
+        path::PathBuf,
+        sync::mpsc::{self, Sender},
+        thread,
+        time::Duration,
+    };
+
+    use bevy::log::{info, warn};
+
+    use super::ConsoleLine;
+
+    pub fn listen(path: PathBuf, sender: Sender<ConsoleLine>) {
+        // A socket left behind by a previous run would make bind fail. Anything else at the path
+        // is left alone, it is more likely a typo in the config than ours to delete.
+        if let Ok(metadata) = fs::symlink_metadata(&path) {
+            if !metadata.file_type().is_socket() {
+                warn!(
+                    "Not binding admin socket, {} exists and is not a socket.",
+                    path.display()
+                );
+                return;
+            }
+
+            let _ = fs::remove_file(&path);
+        }
+
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(error) => {
+                warn!("Could not bind admin socket {}: {}", path.display(), error);
+                return;
+            }
+        };
+
+        // The console has full control of the server, so only its owner may connect.
+        if let Err(error) = fs::set_permissions(&path, Permissions::from_mode(0o600)) {
+            warn!(
+                "Could not restrict admin socket {}: {}",
+                path.display(),
+                error
+            );
+            return;
+        }
+
+        info!("Admin console listening on {}", path.display());
+
+        for stream in listener.incoming().map_while(Result::ok) {
+            let sender = sender.clone();
+
+            thread::spawn(move || {
+                let Ok(reader) = stream.try_clone() else {
+                    return;
+                };
+                let mut writer = stream;
+
+                for text in BufReader::new(reader).lines().map_while(Result::ok) {
+                    let (reply, response) = mpsc::channel();
+
+                    let line = ConsoleLine {
+                        text,
+                        reply: Some(reply),
+                    };
+
+                    if sender.send(line).is_err() {
+                        return;
+                    }
+
+                    let output = response
+                        .recv_timeout(Duration::from_secs(5))
+                        .unwrap_or_else(|_| "No response from server.".to_string());
+
+                    if writeln!(writer, "{}", output).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    }
+}
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, Self::spawn_readers);
+        app.add_systems(
+            Update,
+            Self::run_commands.run_if(resource_exists::<ConsoleInput>),
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use std::net::{SocketAddr, UdpSocket};
+
+use bevy::prelude::*;
+
+use crate::{
+    common::{
+        discovery::{DISCOVERY_PORT, DiscoveryMessage, PROBE_BYTES, ServerAnnouncement},
+        network::SERVER_PORT,
+    },
+    server::{config::ServerSettings, network::MAX_CLIENTS, session::Sessions},
+};
+
+pub struct DiscoveryPlugin;
+
+/// Socket LAN clients send their discovery probes to.
+#[derive(Resource)]
+struct DiscoverySocket(UdpSocket);
+
+impl DiscoveryPlugin {
+    fn bind(mut commands: Commands, settings: Res<ServerSettings>) {
+        if !settings.discovery {
+            return;
+        }
+
+        let bind = SocketAddr::from(([0, 0, 0, 0], DISCOVERY_PORT));
+
+        let socket = match UdpSocket::bind(bind).and_then(|socket| {
+            socket.set_nonblocking(true)?;
+            Ok(socket)
+        }) {
+            Ok(socket) => socket,
+            Err(error) => {
+                warn!("Could not bind LAN discovery on {}: {}", bind, error);
+                return;
+            }
+        };
+
+        info!("Answering LAN discovery on {}", bind);
+
+        commands.insert_resource(DiscoverySocket(socket));
+    }
+
+    fn answer_probes(
+        socket: Res<DiscoverySocket>,
+        settings: Res<ServerSettings>,
+        sessions: Res<Sessions>,
+    ) {
+        let mut buffer = [0u8; PROBE_BYTES];
+
+        while let Ok((len, from)) = socket.0.recv_from(&mut buffer) {
+            let Some(DiscoveryMessage::Probe) = DiscoveryMessage::decode(&buffer[..len]) else {
+                continue;
+            };
+
+            // Sessions inside their grace period still hold a slot.
+            let announcement = DiscoveryMessage::Announcement(ServerAnnouncement {
+                name: settings.server_name.clone(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                port: SERVER_PORT,
+                rooms: 1,
+                free_slots: MAX_CLIENTS.saturating_sub(sessions.0.len()) as u16,
+            });
+
+            let _ = socket.0.send_to(&announcement.encode(), from);
+        }
+    }
+}
+
+impl Plugin for DiscoveryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, Self::bind);
+        app.add_systems(
+            Update,
+            Self::answer_probes.run_if(resource_exists::<DiscoverySocket>),
+        );
+    }
+}
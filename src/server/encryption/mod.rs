@@ -1,15 +1,24 @@
 use std::collections::HashMap;
 
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 use bevy_renet::renet::{DefaultChannel, RenetServer};
-use cryptoxide::chacha20poly1305::ChaCha20Poly1305;
 use fips203::{
     SharedSecretKey, ml_kem_512,
     traits::{Decaps, KeyGen, SerDes},
 };
 use zeroize::{Zeroize, Zeroizing};
 
-use crate::common::network::ServerMessage;
+use crate::{
+    common::{
+        encryption::{Direction, ReceivedNonces, encrypt},
+        network::ServerMessage,
+    },
+    server::{
+        metrics::ServerMetrics,
+        netlog::{HandshakeStep, NetEvent, NetLog},
+        replay::MatchRecorder,
+    },
+};
 
 fn generate_key() -> ([u8; 800], [u8; 1632]) {
     let (encaps_key, decaps_key) = ml_kem_512::KG::try_keygen().expect("Failed encryption keygen.");
@@ -20,12 +29,50 @@ fn generate_key() -> ([u8; 800], [u8; 1632]) {
     (encaps_key_bytes, decaps_key_bytes)
 }
 
-pub fn try_encryption(server: &mut RenetServer, client_id: u64, d_key_res: &mut DKeyStore) {
+/// The network log and metrics every sent message is accounted in.
+pub struct SendLog<'a> {
+    pub net_log: &'a mut NetLog,
+    pub metrics: &'a mut ServerMetrics,
+}
+
+impl SendLog<'_> {
+    /// Accounts for `message` going out as `wire_bytes`, with `payload` as its logged plaintext.
+    pub fn sent(
+        &mut self,
+        client_id: u64,
+        channel_id: u8,
+        message: &ServerMessage,
+        wire_bytes: usize,
+        payload: &[u8],
+    ) {
+        self.net_log.record(NetEvent::Sent {
+            client_id,
+            channel_id,
+            kind: message.kind(),
+            wire_bytes,
+            payload,
+        });
+        self.metrics.record_sent(channel_id, wire_bytes);
+    }
+}
+
+pub fn try_encryption(
+    server: &mut RenetServer,
+    client_id: u64,
+    d_key_res: &mut DKeyStore,
+    log: &mut SendLog,
+) {
     let (mut e_key, d_key) = generate_key();
 
     let server_message = ServerMessage::KEMEncapsKey(e_key);
 
-    let message = bincode::encode_to_vec(server_message, bincode::config::standard()).unwrap();
+    let message = bincode::encode_to_vec(&server_message, bincode::config::standard()).unwrap();
+
+    log.sent(client_id, 3, &server_message, message.len(), &message);
+    log.net_log.record(NetEvent::Handshake {
+        client_id,
+        step: HandshakeStep::EncapsKeySent,
+    });
 
     server.send_message(client_id, 3, message);
 
@@ -34,16 +81,11 @@ pub fn try_encryption(server: &mut RenetServer, client_id: u64, d_key_res: &mut
     e_key.zeroize();
 }
 
-pub fn try_decaps(ct: [u8; 768], dk: &[u8; 1632]) -> SharedSecretKey {
-    let ciphertext =
-        ml_kem_512::CipherText::try_from_bytes(ct).expect("Error converting ciphertext.");
-    let decaps_key =
-        ml_kem_512::DecapsKey::try_from_bytes(*dk).expect("Error converting decaps key");
-    let ssk = decaps_key
-        .try_decaps(&ciphertext)
-        .expect("Could not get ssk.");
+pub fn try_decaps(ct: [u8; 768], dk: &[u8; 1632]) -> Option<SharedSecretKey> {
+    let ciphertext = ml_kem_512::CipherText::try_from_bytes(ct).ok()?;
+    let decaps_key = ml_kem_512::DecapsKey::try_from_bytes(*dk).ok()?;
 
-    ssk
+    decaps_key.try_decaps(&ciphertext).ok()
 }
 
 #[derive(Resource, Clone)]
@@ -55,6 +97,10 @@ pub struct SSKStore(pub HashMap<u64, Zeroizing<[u8; 32]>>);
 #[derive(Resource)]
 pub struct Nonce(pub HashMap<u64, [u8; 12]>);
 
+/// Nonces of the last messages accepted from each client.
+#[derive(Resource, Default)]
+pub struct ReceivedNonce(pub HashMap<u64, ReceivedNonces>);
+
 impl ServerMessage {
     pub fn send_encrypted(
         server: &mut RenetServer,
@@ -62,48 +108,94 @@ impl ServerMessage {
         message: &Self,
         client_id: u64,
         nonce_res: &mut Nonce,
+        log: &mut SendLog,
     ) {
-        let key = ssk;
+        Self::send_encrypted_on(
+            server,
+            ssk,
+            message,
+            client_id,
+            nonce_res,
+            log,
+            DefaultChannel::ReliableOrdered.into(),
+        );
+    }
 
-        let default_nonce = [0u8; 12];
+    pub fn send_encrypted_on(
+        server: &mut RenetServer,
+        ssk: &[u8; 32],
+        message: &Self,
+        client_id: u64,
+        nonce_res: &mut Nonce,
+        log: &mut SendLog,
+        channel_id: u8,
+    ) {
+        let nonce = nonce_res.0.entry(client_id).or_insert([0u8; 12]);
 
-        let nonce = nonce_res.0.entry(client_id).or_insert(default_nonce);
+        let input = bincode::encode_to_vec(message, bincode::config::standard())
+            .expect("Error sending encmsg.");
 
-        let aad = [0u8; 0];
+        let output = encrypt(ssk, nonce, Direction::ServerToClient, &input);
 
-        let mut cipher = ChaCha20Poly1305::new(key, &nonce, &aad);
+        debug!("Sent {} (Encrypted).", message.kind());
 
-        let mut input = vec![];
+        log.sent(
+            client_id,
+            channel_id,
+            message,
+            output.len(),
+            if message.is_private() { &[] } else { &input },
+        );
 
-        let mut channel_id = DefaultChannel::ReliableOrdered;
+        server.send_message(client_id, channel_id, output);
+    }
+}
 
-        match *message {
-            ServerMessage::Pong => {
-                input = bincode::encode_to_vec(ServerMessage::Pong, bincode::config::standard())
-                    .expect("Error sending encmsg.");
-                channel_id = DefaultChannel::ReliableOrdered;
-                info!("Sent Pong (Encrypted).");
-            }
+/// Encrypted send access for systems that only talk to clients which finished the KEM handshake.
+#[derive(SystemParam)]
+pub struct SecureChannel<'w> {
+    pub server: ResMut<'w, RenetServer>,
+    pub ssks: Res<'w, SSKStore>,
+    pub nonce: ResMut<'w, Nonce>,
+    pub net_log: ResMut<'w, NetLog>,
+    pub recorder: ResMut<'w, MatchRecorder>,
+    pub metrics: ResMut<'w, ServerMetrics>,
+}
 
-            _ => {}
+impl SecureChannel<'_> {
+    /// Accounting for messages sent around the channel, such as the clear kick notice.
+    pub fn log(&mut self) -> SendLog<'_> {
+        SendLog {
+            net_log: &mut self.net_log,
+            metrics: &mut self.metrics,
         }
+    }
 
-        let mut output = vec![0u8; input.len() + 16];
-        let mut out_tag = [0u8; 16];
-
-        cipher.encrypt(&input, &mut output[..input.len()], &mut out_tag);
-
-        output[input.len()..].copy_from_slice(&out_tag);
-
-        for i in (0..12).rev() {
-            if nonce[i] == 255 {
-                nonce[i] = 0;
-            } else {
-                nonce[i] += 1;
-                break;
-            }
-        }
+    /// Sends `message` to `client_id`, returning `false` if the client has no session key yet.
+    pub fn send(&mut self, client_id: u64, message: &ServerMessage) -> bool {
+        self.send_on(client_id, DefaultChannel::ReliableOrdered.into(), message)
+    }
 
-        server.send_message(client_id, channel_id, output);
+    pub fn send_on(&mut self, client_id: u64, channel_id: u8, message: &ServerMessage) -> bool {
+        let Some(ssk) = self.ssks.0.get(&client_id) else {
+            return false;
+        };
+
+        ServerMessage::send_encrypted_on(
+            &mut self.server,
+            ssk,
+            message,
+            client_id,
+            &mut self.nonce,
+            &mut SendLog {
+                net_log: &mut self.net_log,
+                metrics: &mut self.metrics,
+            },
+            channel_id,
+        );
+
+        self.recorder.record_output(client_id, message);
+
+        true
     }
 }
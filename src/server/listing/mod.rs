@@ -0,0 +1,88 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_renet::renet::RenetServer;
+use local_ip_address::local_ip;
+
+use crate::{
+    common::{master::MasterMessage, network::SERVER_PORT},
+    server::{config::ServerSettings, network::MAX_CLIENTS},
+};
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Registers the server with a master server and keeps the registration alive.
+pub struct ListingPlugin;
+
+#[derive(Resource)]
+struct MasterLink {
+    socket: UdpSocket,
+    master: SocketAddr,
+}
+
+impl ListingPlugin {
+    fn connect(mut commands: Commands, settings: Res<ServerSettings>) {
+        let Some(master) = settings.master_addr else {
+            return;
+        };
+
+        // The master lists the heartbeat's source address, so it has to be the game's.
+        let bind = SocketAddr::new(local_ip().expect("Could not find local ip address."), 0);
+
+        match UdpSocket::bind(bind) {
+            Ok(socket) => {
+                info!("Registering with master server {}", master);
+                commands.insert_resource(MasterLink { socket, master });
+            }
+            Err(error) => warn!("Could not open master server socket: {}", error),
+        }
+    }
+
+    fn heartbeat(
+        link: Res<MasterLink>,
+        settings: Res<ServerSettings>,
+        server: Res<RenetServer>,
+        time: Res<Time>,
+        mut last_sent: Local<Option<Duration>>,
+    ) {
+        if last_sent.is_some_and(|last| time.elapsed() - last < HEARTBEAT_INTERVAL) {
+            return;
+        }
+        *last_sent = Some(time.elapsed());
+
+        let heartbeat = MasterMessage::Heartbeat {
+            port: SERVER_PORT,
+            name: settings.server_name.clone(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            players: server.connected_clients() as u16,
+            max_players: MAX_CLIENTS as u16,
+        };
+
+        if let Err(error) = link.socket.send_to(&heartbeat.encode(), link.master) {
+            warn!("Could not reach master server {}: {}", link.master, error);
+        }
+    }
+
+    fn unregister(mut exits: MessageReader<AppExit>, link: Res<MasterLink>) {
+        if exits.read().next().is_none() {
+            return;
+        }
+
+        let message = MasterMessage::Unregister { port: SERVER_PORT };
+        let _ = link.socket.send_to(&message.encode(), link.master);
+    }
+}
+
+impl Plugin for ListingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, Self::connect);
+        app.add_systems(
+            Update,
+            Self::heartbeat.run_if(resource_exists::<MasterLink>),
+        );
+        app.add_systems(Last, Self::unregister.run_if(resource_exists::<MasterLink>));
+    }
+}
@@ -0,0 +1,358 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use bevy_renet::renet::RenetServer;
+
+use crate::{
+    common::network::ConnectedUsers,
+    server::{
+        accounts::LoggedInUsers,
+        config::ServerSettings,
+        encryption::{DKeyStore, Nonce, SSKStore},
+        session::Sessions,
+    },
+};
+
+const RENDER_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct MetricsPlugin;
+
+/// Counters fed by the connection and message handling systems.
+#[derive(Resource, Default, Debug)]
+pub struct ServerMetrics {
+    pub connections: u64,
+    pub disconnections: u64,
+    pub rejections: u64,
+    pub handshakes_completed: u64,
+    pub handshakes_failed: u64,
+    pub decrypt_failures: u64,
+    pub decode_failures: u64,
+    pub throttled_messages: u64,
+    pub logins: u64,
+    pub login_failures: u64,
+    pub chat_relayed: u64,
+    /// Chat dropped because the players were too far apart.
+    pub chat_out_of_range: u64,
+    pub voice_relayed: u64,
+    /// Voice frames dropped because the players were too far apart.
+    pub voice_out_of_range: u64,
+    /// Messages and bytes received, keyed by channel id.
+    pub received: BTreeMap<u8, (u64, u64)>,
+    /// Messages and bytes sent, keyed by channel id.
+    pub sent: BTreeMap<u8, (u64, u64)>,
+    pub tick_seconds: f64,
+}
+
+impl ServerMetrics {
+    pub fn record_received(&mut self, channel_id: u8, len: usize) {
+        let (messages, bytes) = self.received.entry(channel_id).or_default();
+
+        *messages += 1;
+        *bytes += len as u64;
+    }
+
+    pub fn record_sent(&mut self, channel_id: u8, len: usize) {
+        let (messages, bytes) = self.sent.entry(channel_id).or_default();
+
+        *messages += 1;
+        *bytes += len as u64;
+    }
+}
+
+/// Latest rendered exposition, served by the HTTP thread.
+#[derive(Resource, Clone)]
+struct MetricsExport(Arc<Mutex<String>>);
+
+#[derive(Resource)]
+struct TickStart(Instant);
+
+impl MetricsPlugin {
+    fn serve(mut commands: Commands, settings: Res<ServerSettings>) {
+        let Some(addr) = settings.metrics_addr else {
+            return;
+        };
+
+        let listener = match TcpListener::bind(addr) {
+            Ok(listener) => listener,
+            Err(error) => {
+                warn!("Could not bind metrics endpoint {}: {}", addr, error);
+                return;
+            }
+        };
+
+        info!("Serving metrics on http://{}/metrics", addr);
+
+        let export = MetricsExport(Arc::new(Mutex::new(String::new())));
+        let body = export.0.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().map_while(Result::ok) {
+                respond(stream, &body);
+            }
+        });
+
+        commands.insert_resource(export);
+    }
+
+    fn start_tick(mut start: ResMut<TickStart>) {
+        start.0 = Instant::now();
+    }
+
+    fn end_tick(start: Res<TickStart>, mut metrics: ResMut<ServerMetrics>) {
+        metrics.tick_seconds = start.0.elapsed().as_secs_f64();
+    }
+
+    fn render(
+        export: Res<MetricsExport>,
+        metrics: Res<ServerMetrics>,
+        server: Res<RenetServer>,
+        sessions: Res<Sessions>,
+        dks: Res<DKeyStore>,
+        ssks: Res<SSKStore>,
+        nonces: Res<Nonce>,
+        users: Res<ConnectedUsers>,
+        logged_in: Res<LoggedInUsers>,
+        time: Res<Time>,
+        mut last_render: Local<Duration>,
+    ) {
+        if time.elapsed() - *last_render < RENDER_INTERVAL {
+            return;
+        }
+        *last_render = time.elapsed();
+
+        let mut out = String::new();
+
+        let counters = [
+            (
+                "connections_total",
+                "Clients that connected.",
+                metrics.connections,
+            ),
+            (
+                "disconnections_total",
+                "Clients that disconnected.",
+                metrics.disconnections,
+            ),
+            (
+                "rejections_total",
+                "Clients rejected by the ban list or allowlist.",
+                metrics.rejections,
+            ),
+            (
+                "handshakes_completed_total",
+                "KEM handshakes completed.",
+                metrics.handshakes_completed,
+            ),
+            (
+                "handshakes_failed_total",
+                "KEM handshakes that failed or were abandoned.",
+                metrics.handshakes_failed,
+            ),
+            (
+                "decrypt_failures_total",
+                "Client messages that failed authentication.",
+                metrics.decrypt_failures,
+            ),
+            (
+                "decode_failures_total",
+                "Client messages that could not be decoded.",
+                metrics.decode_failures,
+            ),
+            (
+                "throttled_messages_total",
+                "Client messages dropped by rate limits.",
+                metrics.throttled_messages,
+            ),
+            ("logins_total", "Successful logins.", metrics.logins),
+            (
+                "login_failures_total",
+                "Logins and registrations that were refused.",
+                metrics.login_failures,
+            ),
+            (
+                "chat_relayed_total",
+                "Chat messages delivered to the partner.",
+                metrics.chat_relayed,
+            ),
+            (
+                "chat_out_of_range_total",
+                "Chat messages dropped because the players were too far apart.",
+                metrics.chat_out_of_range,
+            ),
+            (
+                "voice_relayed_total",
+                "Voice frames delivered to the partner.",
+                metrics.voice_relayed,
+            ),
+            (
+                "voice_out_of_range_total",
+                "Voice frames dropped because the players were too far apart.",
+                metrics.voice_out_of_range,
+            ),
+        ];
+
+        for (name, help, value) in counters {
+            write_metric(
+                &mut out,
+                name,
+                "counter",
+                help,
+                &[(String::new(), value as f64)],
+            );
+        }
+
+        let by_channel = |counts: &BTreeMap<u8, (u64, u64)>,
+                          select: fn(&(u64, u64)) -> u64|
+         -> Vec<(String, f64)> {
+            counts
+                .iter()
+                .map(|(channel_id, counts)| {
+                    (format!("channel=\"{}\"", channel_id), select(counts) as f64)
+                })
+                .collect()
+        };
+
+        write_metric(
+            &mut out,
+            "messages_received_total",
+            "counter",
+            "Client messages received per channel.",
+            &by_channel(&metrics.received, |(messages, _)| *messages),
+        );
+        write_metric(
+            &mut out,
+            "bytes_received_total",
+            "counter",
+            "Client message bytes received per channel.",
+            &by_channel(&metrics.received, |(_, bytes)| *bytes),
+        );
+        write_metric(
+            &mut out,
+            "messages_sent_total",
+            "counter",
+            "Server messages sent per channel.",
+            &by_channel(&metrics.sent, |(messages, _)| *messages),
+        );
+        write_metric(
+            &mut out,
+            "bytes_sent_total",
+            "counter",
+            "Server message bytes sent per channel.",
+            &by_channel(&metrics.sent, |(_, bytes)| *bytes),
+        );
+
+        write_metric(
+            &mut out,
+            "connected_clients",
+            "gauge",
+            "Clients currently connected.",
+            &[(String::new(), server.connected_clients() as f64)],
+        );
+        write_metric(
+            &mut out,
+            "sessions",
+            "gauge",
+            "Player sessions, including ones inside their reconnect grace period.",
+            &[(String::new(), sessions.0.len() as f64)],
+        );
+        write_metric(
+            &mut out,
+            "store_entries",
+            "gauge",
+            "Per-client entries held by the server, should follow connected_clients.",
+            &[
+                ("store=\"decaps_keys\"".to_string(), dks.0.len() as f64),
+                ("store=\"session_keys\"".to_string(), ssks.0.len() as f64),
+                ("store=\"nonces\"".to_string(), nonces.0.len() as f64),
+                ("store=\"users\"".to_string(), users.0.len() as f64),
+                ("store=\"logins\"".to_string(), logged_in.0.len() as f64),
+            ],
+        );
+        write_metric(
+            &mut out,
+            "tick_seconds",
+            "gauge",
+            "Time spent running the last server tick.",
+            &[(String::new(), metrics.tick_seconds)],
+        );
+
+        let rtts: Vec<(String, f64)> = server
+            .clients_id()
+            .into_iter()
+            .filter_map(|client_id| {
+                let info = server.network_info(client_id).ok()?;
+                Some((format!("client_id=\"{}\"", client_id), info.rtt))
+            })
+            .collect();
+
+        write_metric(
+            &mut out,
+            "client_rtt_seconds",
+            "gauge",
+            "Round trip time per client.",
+            &rtts,
+        );
+
+        *export.0.lock().expect("Metrics lock poisoned.") = out;
+    }
+}
+
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str, samples: &[(String, f64)]) {
+    out.push_str(&format!("# HELP absent_chroma_{} {}\n", name, help));
+    out.push_str(&format!("# TYPE absent_chroma_{} {}\n", name, kind));
+
+    for (labels, value) in samples {
+        if labels.is_empty() {
+            out.push_str(&format!("absent_chroma_{} {}\n", name, value));
+        } else {
+            out.push_str(&format!("absent_chroma_{}{{{}}} {}\n", name, labels, value));
+        }
+    }
+}
+
+fn respond(mut stream: TcpStream, body: &Mutex<String>) {
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+
+    let mut request_line = String::new();
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    if BufReader::new(reader).read_line(&mut request_line).is_err() {
+        return;
+    }
+
+    let response = if request_line.starts_with("GET /metrics ") {
+        let body = body.lock().expect("Metrics lock poisoned.").clone();
+
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+
+    let _ = stream.write_all(response.as_bytes());
+}
+
+impl Plugin for MetricsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ServerMetrics::default());
+        app.insert_resource(TickStart(Instant::now()));
+        app.add_systems(Startup, Self::serve);
+        app.add_systems(First, Self::start_tick);
+        app.add_systems(Last, Self::end_tick);
+        app.add_systems(
+            Update,
+            Self::render.run_if(resource_exists::<MetricsExport>),
+        );
+    }
+}
@@ -1,2 +1,18 @@
+pub mod access;
+pub mod accounts;
+pub mod chat;
+pub mod config;
+pub mod console;
+pub mod discovery;
 pub mod encryption;
+pub mod listing;
+pub mod metrics;
+pub mod netlog;
 pub mod network;
+pub mod profiles;
+pub mod replay;
+pub mod session;
+pub mod shutdown;
+pub mod signals;
+pub mod tether;
+pub mod voice;
@@ -0,0 +1,262 @@
+use std::{
+    fmt::Write as _,
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    net::IpAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+
+use crate::server::config::ServerSettings;
+
+pub struct NetLogPlugin;
+
+#[derive(Debug, Clone, Copy)]
+pub enum HandshakeStep {
+    EncapsKeySent,
+    Completed,
+    Failed,
+    /// The client disconnected before sending its ciphertext.
+    Abandoned,
+}
+
+/// One line of the network log.
+///
+/// `payload` is the plaintext bincode message, written as hex only when payload capture is on.
+pub enum NetEvent<'a> {
+    Connected {
+        client_id: u64,
+        username: &'a str,
+        ip: Option<IpAddr>,
+    },
+    Rejected {
+        client_id: u64,
+        reason: &'a str,
+    },
+    Disconnected {
+        client_id: u64,
+        reason: String,
+    },
+    Handshake {
+        client_id: u64,
+        step: HandshakeStep,
+    },
+    Received {
+        client_id: u64,
+        channel_id: u8,
+        kind: &'static str,
+        wire_bytes: usize,
+        payload: &'a [u8],
+    },
+    Sent {
+        client_id: u64,
+        channel_id: u8,
+        kind: &'static str,
+        wire_bytes: usize,
+        payload: &'a [u8],
+    },
+    Dropped {
+        client_id: u64,
+        channel_id: u8,
+        reason: &'static str,
+        wire_bytes: usize,
+    },
+}
+
+/// JSON lines log of connection events, handshake steps and messages. Does nothing when no
+/// `NET_LOG` path is configured.
+#[derive(Resource, Default)]
+pub struct NetLog {
+    writer: Option<BufWriter<File>>,
+    capture_payloads: bool,
+}
+
+impl NetLog {
+    pub fn record(&mut self, event: NetEvent<'_>) {
+        let Some(writer) = &mut self.writer else {
+            return;
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        let mut line = format!("{{\"ts_ms\":{}", timestamp);
+
+        match event {
+            NetEvent::Connected {
+                client_id,
+                username,
+                ip,
+            } => {
+                let _ = write!(
+                    line,
+                    ",\"event\":\"connected\",\"client_id\":{},\"username\":{}",
+                    client_id,
+                    json_string(username)
+                );
+                if let Some(ip) = ip {
+                    let _ = write!(line, ",\"ip\":\"{}\"", ip);
+                }
+            }
+
+            NetEvent::Rejected { client_id, reason } => {
+                let _ = write!(
+                    line,
+                    ",\"event\":\"rejected\",\"client_id\":{},\"reason\":{}",
+                    client_id,
+                    json_string(reason)
+                );
+            }
+
+            NetEvent::Disconnected { client_id, reason } => {
+                let _ = write!(
+                    line,
+                    ",\"event\":\"disconnected\",\"client_id\":{},\"reason\":{}",
+                    client_id,
+                    json_string(&reason)
+                );
+            }
+
+            NetEvent::Handshake { client_id, step } => {
+                let step = match step {
+                    HandshakeStep::EncapsKeySent => "encaps_key_sent",
+                    HandshakeStep::Completed => "completed",
+                    HandshakeStep::Failed => "failed",
+                    HandshakeStep::Abandoned => "abandoned",
+                };
+
+                let _ = write!(
+                    line,
+                    ",\"event\":\"handshake\",\"client_id\":{},\"step\":\"{}\"",
+                    client_id, step
+                );
+            }
+
+            NetEvent::Received {
+                client_id,
+                channel_id,
+                kind,
+                wire_bytes,
+                payload,
+            } => {
+                let _ = write!(
+                    line,
+                    ",\"event\":\"received\",\"client_id\":{},\"channel\":{},\"kind\":\"{}\",\"wire_bytes\":{}",
+                    client_id, channel_id, kind, wire_bytes
+                );
+                write_payload(&mut line, payload, self.capture_payloads);
+            }
+
+            NetEvent::Sent {
+                client_id,
+                channel_id,
+                kind,
+                wire_bytes,
+                payload,
+            } => {
+                let _ = write!(
+                    line,
+                    ",\"event\":\"sent\",\"client_id\":{},\"channel\":{},\"kind\":\"{}\",\"wire_bytes\":{}",
+                    client_id, channel_id, kind, wire_bytes
+                );
+                write_payload(&mut line, payload, self.capture_payloads);
+            }
+
+            NetEvent::Dropped {
+                client_id,
+                channel_id,
+                reason,
+                wire_bytes,
+            } => {
+                let _ = write!(
+                    line,
+                    ",\"event\":\"dropped\",\"client_id\":{},\"channel\":{},\"reason\":\"{}\",\"wire_bytes\":{}",
+                    client_id, channel_id, reason, wire_bytes
+                );
+            }
+        }
+
+        line.push_str("}\n");
+
+        if let Err(error) = writer.write_all(line.as_bytes()) {
+            warn!("Could not write network log, disabling it: {}", error);
+            self.writer = None;
+        }
+    }
+}
+
+fn write_payload(line: &mut String, payload: &[u8], capture: bool) {
+    let _ = write!(line, ",\"bytes\":{}", payload.len());
+
+    if capture {
+        line.push_str(",\"payload\":\"");
+        for byte in payload {
+            let _ = write!(line, "{:02x}", byte);
+        }
+        line.push('"');
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+impl NetLogPlugin {
+    fn open_log(mut net_log: ResMut<NetLog>, settings: Res<ServerSettings>) {
+        let Some(path) = &settings.net_log else {
+            return;
+        };
+
+        let file = match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => file,
+            Err(error) => {
+                warn!("Could not open network log {}: {}", path.display(), error);
+                return;
+            }
+        };
+
+        info!(
+            "Writing network log to {} (payload capture: {})",
+            path.display(),
+            settings.capture_payloads
+        );
+
+        net_log.writer = Some(BufWriter::new(file));
+        net_log.capture_payloads = settings.capture_payloads;
+    }
+
+    fn flush_log(mut net_log: ResMut<NetLog>) {
+        if let Some(writer) = &mut net_log.writer {
+            let _ = writer.flush();
+        }
+    }
+}
+
+impl Plugin for NetLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(NetLog::default());
+        app.add_systems(Startup, Self::open_log);
+        app.add_systems(Last, Self::flush_log);
+    }
+}
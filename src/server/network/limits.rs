@@ -0,0 +1,90 @@
+use std::{collections::HashMap, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::server::config::RateLimits;
+
+/// Per-client, per-channel token buckets for message count and bytes.
+#[derive(Resource, Default)]
+pub struct RateLimiter {
+    buckets: HashMap<(u64, u8), Bucket>,
+    /// Dropped-message count per client, leaking one per second.
+    strikes: HashMap<u64, (f32, Duration)>,
+}
+
+struct Bucket {
+    messages: f32,
+    bytes: f32,
+    refilled_at: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Accept,
+    /// Over budget, drop the message.
+    Throttle,
+    /// Over budget for too long, the client should be removed.
+    Disconnect,
+}
+
+impl RateLimiter {
+    pub fn check(
+        &mut self,
+        limits: &RateLimits,
+        client_id: u64,
+        channel_id: u8,
+        len: usize,
+        now: Duration,
+    ) -> Verdict {
+        if len > limits.max_message_bytes {
+            return self.strike(limits, client_id, now);
+        }
+
+        let max_messages = limits.messages_per_second * limits.burst_seconds;
+        let max_bytes = limits.bytes_per_second * limits.burst_seconds;
+
+        let bucket = self
+            .buckets
+            .entry((client_id, channel_id))
+            .or_insert(Bucket {
+                messages: max_messages,
+                bytes: max_bytes,
+                refilled_at: now,
+            });
+
+        let elapsed = now.saturating_sub(bucket.refilled_at).as_secs_f32();
+        bucket.refilled_at = now;
+        bucket.messages =
+            (bucket.messages + elapsed * limits.messages_per_second).min(max_messages);
+        bucket.bytes = (bucket.bytes + elapsed * limits.bytes_per_second).min(max_bytes);
+
+        if bucket.messages < 1.0 || bucket.bytes < len as f32 {
+            return self.strike(limits, client_id, now);
+        }
+
+        bucket.messages -= 1.0;
+        bucket.bytes -= len as f32;
+
+        Verdict::Accept
+    }
+
+    /// Counts a rejected message against the client.
+    pub fn strike(&mut self, limits: &RateLimits, client_id: u64, now: Duration) -> Verdict {
+        let (strikes, struck_at) = self.strikes.entry(client_id).or_insert((0.0, now));
+
+        let leaked = now.saturating_sub(*struck_at).as_secs_f32();
+        *strikes = (*strikes - leaked).max(0.0) + 1.0;
+        *struck_at = now;
+
+        if *strikes > limits.max_strikes {
+            Verdict::Disconnect
+        } else {
+            Verdict::Throttle
+        }
+    }
+
+    pub fn forget(&mut self, client_id: u64) {
+        self.buckets.retain(|(id, _), _| *id != client_id);
+        self.strikes.remove(&client_id);
+    }
+}
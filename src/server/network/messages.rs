@@ -1,86 +1,377 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 use bevy_renet::renet::RenetServer;
-use cryptoxide::chacha20poly1305::ChaCha20Poly1305;
 use fips203::traits::SerDes;
+use zeroize::Zeroizing;
 
 use crate::{
-    common::network::{ClientMessage, ConnectedUsers, NETWORK_CHANNELS, ServerMessage, UserData},
-    server::encryption::{DKeyStore, Nonce, SSKStore, try_decaps},
+    common::{
+        encryption::{Direction, decrypt},
+        network::{
+            ClientMessage, ConnectedUsers, MAX_DECODED_MESSAGE_BYTES, NETWORK_CHANNELS,
+            ServerMessage, UserData,
+        },
+    },
+    server::{
+        accounts::{ClientLogin, LoggedInUsers},
+        chat::ClientChat,
+        config::ServerSettings,
+        encryption::{DKeyStore, Nonce, ReceivedNonce, SSKStore, SendLog, try_decaps},
+        metrics::ServerMetrics,
+        netlog::{HandshakeStep, NetEvent, NetLog},
+        network::{
+            KickClient,
+            limits::{RateLimiter, Verdict},
+        },
+        profiles::ProfileRequest,
+        replay::{MatchRecorder, RecordedEvent},
+        session::{ClientHello, ClientPlayerState},
+        signals::ClientSignal,
+        voice::ClientVoice,
+    },
 };
 
+/// Where decoded client messages are handed off to the systems that act on them.
+#[derive(SystemParam)]
+pub struct ClientInputs<'w> {
+    chats: MessageWriter<'w, ClientChat>,
+    hellos: MessageWriter<'w, ClientHello>,
+    logins: MessageWriter<'w, ClientLogin>,
+    player_states: MessageWriter<'w, ClientPlayerState>,
+    profile_requests: MessageWriter<'w, ProfileRequest>,
+    signals: MessageWriter<'w, ClientSignal>,
+    voices: MessageWriter<'w, ClientVoice>,
+}
+
 pub fn receive_client_messages(
     mut server: ResMut<RenetServer>,
     users: Res<ConnectedUsers>,
+    logged_in: Res<LoggedInUsers>,
     mut dks: ResMut<DKeyStore>,
     mut ssks: ResMut<SSKStore>,
     mut nonce_res: ResMut<Nonce>,
+    mut received_res: ResMut<ReceivedNonce>,
+    mut inputs: ClientInputs,
+    mut limiter: ResMut<RateLimiter>,
+    mut kicks: MessageWriter<KickClient>,
+    settings: Res<ServerSettings>,
+    time: Res<Time>,
+    mut metrics: ResMut<ServerMetrics>,
+    mut net_log: ResMut<NetLog>,
+    mut recorder: ResMut<MatchRecorder>,
 ) {
+    let limits = &settings.rate_limits;
+    let decode_config = bincode::config::standard().with_limit::<MAX_DECODED_MESSAGE_BYTES>();
+    let mut kicked = vec![];
+
     for channel_id in NETWORK_CHANNELS {
         for client_id in server.clients_id() {
-            let default_data = UserData::from_str("Unknown");
-            let user_data = users.0.get(&client_id).unwrap_or(&default_data);
-            let username = user_data.to_username();
+            if kicked.contains(&client_id) {
+                continue;
+            }
+
+            let username = users
+                .0
+                .get(&client_id)
+                .map(UserData::to_username)
+                .unwrap_or_default();
 
             while let Some(mut message) = server.receive_message(client_id, channel_id) {
+                let wire_bytes = message.len();
+                metrics.record_received(channel_id, wire_bytes);
+
+                match limiter.check(limits, client_id, channel_id, message.len(), time.elapsed()) {
+                    Verdict::Accept => {}
+
+                    Verdict::Throttle => {
+                        debug!(
+                            "Throttled message from client: {} id: {} channel: {}",
+                            username, client_id, channel_id
+                        );
+                        metrics.throttled_messages += 1;
+                        net_log.record(NetEvent::Dropped {
+                            client_id,
+                            channel_id,
+                            reason: "throttled",
+                            wire_bytes,
+                        });
+                        continue;
+                    }
+
+                    Verdict::Disconnect => {
+                        warn!(
+                            "Client exceeded rate limits => username: {} id: {}",
+                            username, client_id
+                        );
+
+                        kicks.write(KickClient {
+                            client_id,
+                            reason: "Sending too many messages.".to_string(),
+                        });
+                        net_log.record(NetEvent::Dropped {
+                            client_id,
+                            channel_id,
+                            reason: "rate_limited",
+                            wire_bytes,
+                        });
+                        kicked.push(client_id);
+
+                        break;
+                    }
+                }
+
                 if channel_id != 3 {
-                    let key = &**ssks.0.get(&client_id).expect("Could not find Client Key.");
+                    let Some(key) = ssks.0.get(&client_id) else {
+                        warn!(
+                            "Dropped encrypted message before KEM handshake from client: {} id: {}",
+                            username, client_id
+                        );
+                        net_log.record(NetEvent::Dropped {
+                            client_id,
+                            channel_id,
+                            reason: "no_session_key",
+                            wire_bytes,
+                        });
+                        continue;
+                    };
+
+                    let Some(output) = decrypt(key, Direction::ClientToServer, &message) else {
+                        warn!(
+                            "Could not decrypt message from client: {} id: {}",
+                            username, client_id
+                        );
+                        metrics.decrypt_failures += 1;
+                        net_log.record(NetEvent::Dropped {
+                            client_id,
+                            channel_id,
+                            reason: "decrypt_failed",
+                            wire_bytes,
+                        });
+                        continue;
+                    };
+
+                    let received = received_res.0.entry(client_id).or_default();
+
+                    if !received.advance(channel_id, &message) {
+                        warn!(
+                            "Dropped replayed message from client: {} id: {}",
+                            username, client_id
+                        );
+                        net_log.record(NetEvent::Dropped {
+                            client_id,
+                            channel_id,
+                            reason: "replayed",
+                            wire_bytes,
+                        });
+                        continue;
+                    }
+
+                    message = output.into();
+                }
 
-                    let mut output = vec![0u8; message.len() - 16];
+                let client_message =
+                    match bincode::decode_from_slice::<ClientMessage, _>(&message, decode_config) {
+                        Ok((client_message, _)) => client_message,
+                        Err(error) => {
+                            warn!(
+                                "Could not decode message from client: {} id: {} error: {}",
+                                username, client_id, error
+                            );
+                            metrics.decode_failures += 1;
+                            net_log.record(NetEvent::Dropped {
+                                client_id,
+                                channel_id,
+                                reason: "decode_failed",
+                                wire_bytes,
+                            });
 
-                    let nonce = nonce_res.0.entry(client_id).or_insert([0u8; 12]);
+                            if limiter.strike(limits, client_id, time.elapsed())
+                                == Verdict::Disconnect
+                            {
+                                kicks.write(KickClient {
+                                    client_id,
+                                    reason: "Sending malformed messages.".to_string(),
+                                });
+                                kicked.push(client_id);
 
-                    let aad = [0u8; 0];
+                                break;
+                            }
 
-                    let mut cipher = ChaCha20Poly1305::new(key, nonce, &aad);
+                            continue;
+                        }
+                    };
 
-                    cipher.decrypt(
-                        &message[..message.len() - 16],
-                        &mut output,
-                        &message[message.len() - 16..],
+                // The handshake channel is not encrypted, so it only carries the handshake.
+                if channel_id == 3 && !matches!(client_message, ClientMessage::KEMCipherText(_)) {
+                    warn!(
+                        "Dropped unencrypted {} from client: {} id: {}",
+                        client_message.kind(),
+                        username,
+                        client_id
                     );
+                    net_log.record(NetEvent::Dropped {
+                        client_id,
+                        channel_id,
+                        reason: "unencrypted",
+                        wire_bytes,
+                    });
 
-                    message = output.into();
+                    if limiter.strike(limits, client_id, time.elapsed()) == Verdict::Disconnect {
+                        kicks.write(KickClient {
+                            client_id,
+                            reason: "Sending unencrypted messages.".to_string(),
+                        });
+                        kicked.push(client_id);
+
+                        break;
+                    }
+
+                    continue;
                 }
 
-                let (client_message, _) = bincode::decode_from_slice::<ClientMessage, _>(
-                    &message,
-                    bincode::config::standard(),
-                )
-                .expect("Error decoding client message.");
+                net_log.record(NetEvent::Received {
+                    client_id,
+                    channel_id,
+                    kind: client_message.kind(),
+                    wire_bytes,
+                    payload: if client_message.is_private() {
+                        &[]
+                    } else {
+                        &message
+                    },
+                });
+
+                recorder.record_input(client_id, &client_message);
 
                 match client_message {
                     ClientMessage::Ping => {
-                        let ssk = &**ssks.0.get(&client_id).expect("No SSK for the client");
+                        let Some(ssk) = ssks.0.get(&client_id) else {
+                            continue;
+                        };
 
                         info!("Received Ping from client: {} id: {}", username, client_id);
 
                         ServerMessage::send_encrypted(
                             &mut server,
-                            ssk,
+                            &**ssk,
                             &ServerMessage::Pong,
                             client_id,
                             &mut nonce_res,
+                            &mut SendLog {
+                                net_log: &mut net_log,
+                                metrics: &mut metrics,
+                            },
                         );
                     }
 
                     ClientMessage::KEMCipherText(ct) => {
-                        let dk = dks.0.get(&client_id).expect("Cannot find decaps key.");
-                        let ssk = try_decaps(ct, dk);
+                        let Some(dk) = dks.0.remove(&client_id) else {
+                            warn!("Unexpected KEM ciphertext from client id: {}", client_id);
+                            continue;
+                        };
 
-                        dks.0.remove(&client_id);
+                        let Some(ssk) = try_decaps(ct, &dk) else {
+                            warn!(
+                                "KEM decapsulation failed for client: {} id: {}",
+                                username, client_id
+                            );
+                            metrics.handshakes_failed += 1;
+                            net_log.record(NetEvent::Handshake {
+                                client_id,
+                                step: HandshakeStep::Failed,
+                            });
+                            continue;
+                        };
+
+                        nonce_res.0.insert(client_id, [0u8; 12]);
+                        received_res.0.remove(&client_id);
 
                         ServerMessage::send_encrypted(
                             &mut server,
                             &ssk.clone().into_bytes(),
-                            &ServerMessage::Pong,
+                            &ServerMessage::HandshakeComplete,
                             client_id,
                             &mut nonce_res,
+                            &mut SendLog {
+                                net_log: &mut net_log,
+                                metrics: &mut metrics,
+                            },
                         );
 
                         ssks.0.insert(client_id, ssk.into_bytes().into());
 
+                        metrics.handshakes_completed += 1;
+                        recorder.record(RecordedEvent::HandshakeComplete { client_id });
+                        net_log.record(NetEvent::Handshake {
+                            client_id,
+                            step: HandshakeStep::Completed,
+                        });
+
                         info!("KEM encryption success.");
                     }
+
+                    ClientMessage::Hello { resume_token } => {
+                        if !logged_in.contains(client_id) {
+                            warn!(
+                                "Dropped Hello before login from client: {} id: {}",
+                                username, client_id
+                            );
+                            net_log.record(NetEvent::Dropped {
+                                client_id,
+                                channel_id,
+                                reason: "not_logged_in",
+                                wire_bytes,
+                            });
+                            continue;
+                        }
+
+                        inputs.hellos.write(ClientHello {
+                            client_id,
+                            resume_token,
+                        });
+                    }
+
+                    ClientMessage::PlayerState { translation } => {
+                        inputs.player_states.write(ClientPlayerState {
+                            client_id,
+                            translation: Vec3::from_array(translation),
+                        });
+                    }
+
+                    ClientMessage::Login { username, password } => {
+                        inputs.logins.write(ClientLogin {
+                            client_id,
+                            username,
+                            password: Zeroizing::new(password),
+                            register: false,
+                        });
+                    }
+
+                    ClientMessage::Register { username, password } => {
+                        inputs.logins.write(ClientLogin {
+                            client_id,
+                            username,
+                            password: Zeroizing::new(password),
+                            register: true,
+                        });
+                    }
+
+                    ClientMessage::ProfileRequest => {
+                        if logged_in.contains(client_id) {
+                            inputs.profile_requests.write(ProfileRequest { client_id });
+                        }
+                    }
+
+                    ClientMessage::Chat { text } => {
+                        inputs.chats.write(ClientChat { client_id, text });
+                    }
+
+                    ClientMessage::Voice(frame) => {
+                        inputs.voices.write(ClientVoice { client_id, frame });
+                    }
+
+                    ClientMessage::Signal { id } => {
+                        inputs.signals.write(ClientSignal { client_id, id });
+                    }
                 }
             }
         }
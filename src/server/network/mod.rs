@@ -7,126 +7,308 @@ use std::{
 use bevy::prelude::*;
 use bevy_renet::{
     netcode::{NetcodeServerTransport, ServerAuthentication, ServerConfig},
-    renet::{ChannelConfig, ConnectionConfig, RenetServer, ServerEvent},
+    renet::{RenetServer, ServerEvent},
 };
 use local_ip_address::local_ip;
 use zeroize::Zeroize;
 
 use crate::{
-    common::network::{ConnectedUsers, UserData, get_private_key_env},
+    common::{
+        netsim::NetworkConditioner,
+        network::{
+            ConnectedUsers, SERVER_PORT, ServerMessage, UserData, connection_config,
+            get_private_key_env,
+        },
+    },
     server::{
-        encryption::{self, DKeyStore, Nonce, SSKStore},
-        network::messages::receive_client_messages,
+        access::AccessList,
+        config::ServerSettings,
+        encryption::{self, DKeyStore, Nonce, ReceivedNonce, SSKStore, SecureChannel, SendLog},
+        metrics::ServerMetrics,
+        netlog::{HandshakeStep, NetEvent, NetLog},
+        network::{limits::RateLimiter, messages::receive_client_messages},
+        replay::{MatchRecorder, RecordedEvent},
     },
 };
+pub mod limits;
 mod messages;
 
-pub struct NetworkPlugin;
+/// Players in the server's one match.
+pub const MAX_CLIENTS: usize = 2;
 
-impl NetworkPlugin {
-    fn create_renet_server(mut commands: Commands) {
-        let mut connection_config = ConnectionConfig::default();
-
-        connection_config
-            .client_channels_config
-            .push(ChannelConfig {
-                channel_id: 3,
-                send_type: bevy_renet::renet::SendType::ReliableOrdered {
-                    resend_time: Duration::from_millis(300),
-                },
-                max_memory_usage_bytes: 5 * 1024 * 1024,
-            });
-
-        connection_config
-            .server_channels_config
-            .push(ChannelConfig {
-                channel_id: 3,
-                send_type: bevy_renet::renet::SendType::ReliableOrdered {
-                    resend_time: Duration::from_millis(300),
-                },
-                max_memory_usage_bytes: 5 * 1024 * 1024,
-            });
-
-        let server = RenetServer::new(connection_config);
-
-        commands.insert_resource(server);
-
-        let current_time = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap_or_default();
-
-        let server_addr =
-            SocketAddr::new(local_ip().expect("Could not find local ip address."), 42069);
-
-        info!("Creating Server!: {:?}", server_addr);
-
-        let mut private_key = get_private_key_env();
+/// How long a kicked client gets to receive [`ServerMessage::Kicked`] before it is disconnected.
+const KICK_DELAY: Duration = Duration::from_millis(250);
 
-        let authentication = ServerAuthentication::Secure { private_key };
+pub struct NetworkPlugin;
 
-        let server_config = ServerConfig {
-            max_clients: 2,
-            protocol_id: 69,
-            public_addresses: vec![server_addr],
-            authentication,
-            current_time,
-        };
+/// Binds the netcode transport. Left out when something else moves the packets, like the
+/// in-memory transport used by the integration tests.
+pub struct TransportPlugin;
 
-        private_key.zeroize();
+/// Tells a client why it is being removed, then disconnects it.
+#[derive(Message, Debug, Clone)]
+pub struct KickClient {
+    pub client_id: u64,
+    pub reason: String,
+}
 
-        let socket = UdpSocket::bind(server_addr)
-            .expect("UdpSocket bind failure. Consider restarting the server.");
+#[derive(Resource, Default)]
+struct PendingKicks(Vec<(u64, Duration)>);
 
-        let transport = NetcodeServerTransport::new(server_config, socket).expect(
-            "NetcodeServerTransport could not be established. Consider restarting the server.",
-        );
+impl NetworkPlugin {
+    fn create_renet_server(mut commands: Commands) {
+        commands.insert_resource(RenetServer::new(connection_config()));
+    }
 
-        commands.insert_resource(transport);
+    fn load_access_list(mut commands: Commands, settings: Res<ServerSettings>) {
+        commands.insert_resource(AccessList::load(
+            Some(settings.ban_list.clone()),
+            settings.allowlist.clone(),
+        ));
     }
 
     fn server_events(
         mut event_reader: MessageReader<ServerEvent>,
-        transport: Res<NetcodeServerTransport>,
+        transport: Option<Res<NetcodeServerTransport>>,
         mut users: ResMut<ConnectedUsers>,
         mut server: ResMut<RenetServer>,
         mut d_key_res: ResMut<DKeyStore>,
         mut ssk_res: ResMut<SSKStore>,
+        mut nonce_res: ResMut<Nonce>,
+        mut received_res: ResMut<ReceivedNonce>,
+        access: Res<AccessList>,
+        mut kicks: MessageWriter<KickClient>,
+        mut limiter: ResMut<RateLimiter>,
+        mut metrics: ResMut<ServerMetrics>,
+        mut net_log: ResMut<NetLog>,
+        mut recorder: ResMut<MatchRecorder>,
     ) {
         for event in event_reader.read() {
             match event {
                 ServerEvent::ClientConnected { client_id } => {
-                    let username = transport
-                        .user_data(*client_id)
-                        .unwrap_or_else(|| UserData::from_str("Anon").0);
+                    metrics.connections += 1;
 
-                    users.0.insert(*client_id, UserData(username));
+                    // Without a netcode transport, whoever added the connection registers the user.
+                    let Some(user_data) = transport
+                        .as_ref()
+                        .and_then(|transport| transport.user_data(*client_id))
+                        .map(UserData)
+                        .or_else(|| users.0.get(client_id).copied())
+                    else {
+                        warn!("Rejected client without a username => id: {}", client_id);
+                        kicks.write(KickClient {
+                            client_id: *client_id,
+                            reason: "Missing username.".to_string(),
+                        });
+                        continue;
+                    };
 
-                    let username_str = str::from_utf8(&username).unwrap_or("Anon");
+                    // The name from the connect token is only a claim until the client logs in.
+                    users.0.insert(*client_id, user_data);
+
+                    let username_str = user_data.to_username();
 
                     info!(
                         "Client Connected => username: {} id: {}",
                         username_str, client_id
                     );
 
-                    encryption::try_encryption(&mut server, *client_id, &mut d_key_res);
+                    let ip = transport
+                        .as_ref()
+                        .and_then(|transport| transport.client_addr(*client_id))
+                        .map(|addr| addr.ip());
+
+                    net_log.record(NetEvent::Connected {
+                        client_id: *client_id,
+                        username: username_str,
+                        ip,
+                    });
+
+                    if let Err(reason) = access.check(username_str, *client_id, ip) {
+                        warn!(
+                            "Rejected client => username: {} id: {} ip: {:?} reason: {}",
+                            username_str, client_id, ip, reason
+                        );
+                        metrics.rejections += 1;
+                        net_log.record(NetEvent::Rejected {
+                            client_id: *client_id,
+                            reason: &reason,
+                        });
+
+                        kicks.write(KickClient {
+                            client_id: *client_id,
+                            reason,
+                        });
+
+                        continue;
+                    }
+
+                    recorder.record(RecordedEvent::Connected {
+                        client_id: *client_id,
+                        username: username_str.to_string(),
+                    });
+
+                    encryption::try_encryption(
+                        &mut server,
+                        *client_id,
+                        &mut d_key_res,
+                        &mut SendLog {
+                            net_log: &mut net_log,
+                            metrics: &mut metrics,
+                        },
+                    );
                 }
 
                 ServerEvent::ClientDisconnected { client_id, reason } => {
                     let username = users
                         .0
                         .get(client_id)
-                        .unwrap_or(&UserData([0u8; 256]))
-                        .to_username();
+                        .map(UserData::to_username)
+                        .unwrap_or_default();
 
                     info!(
                         "Client Disconnected => username: {} id: {} reason: {:?}",
                         username, client_id, reason
                     );
 
+                    metrics.disconnections += 1;
+                    recorder.record(RecordedEvent::Disconnected {
+                        client_id: *client_id,
+                    });
+                    net_log.record(NetEvent::Disconnected {
+                        client_id: *client_id,
+                        reason: format!("{:?}", reason),
+                    });
+
+                    // A decapsulation key still pending means the handshake never finished.
+                    if d_key_res.0.remove(client_id).is_some() {
+                        metrics.handshakes_failed += 1;
+                        net_log.record(NetEvent::Handshake {
+                            client_id: *client_id,
+                            step: HandshakeStep::Abandoned,
+                        });
+                    }
+
                     ssk_res.0.remove(client_id);
+                    nonce_res.0.remove(client_id);
+                    received_res.0.remove(client_id);
+                    users.0.remove(client_id);
+                    limiter.forget(*client_id);
+                }
+            }
+        }
+    }
+
+    fn kick_clients(
+        mut kicks: MessageReader<KickClient>,
+        mut pending: ResMut<PendingKicks>,
+        mut channel: SecureChannel,
+        time: Res<Time>,
+    ) {
+        let now = time.elapsed();
+
+        for kick in kicks.read() {
+            if pending
+                .0
+                .iter()
+                .any(|(client_id, _)| *client_id == kick.client_id)
+            {
+                continue;
+            }
+
+            info!(
+                "Kicking client id: {} reason: {}",
+                kick.client_id, kick.reason
+            );
+
+            let message = ServerMessage::Kicked {
+                reason: kick.reason.clone(),
+            };
+
+            // Clients rejected on connect have no session key yet, so they are told in the clear
+            // on the handshake channel.
+            if !channel.send(kick.client_id, &message) {
+                let payload = bincode::encode_to_vec(&message, bincode::config::standard())
+                    .expect("Error encoding kick message.");
+
+                channel
+                    .log()
+                    .sent(kick.client_id, 3, &message, payload.len(), &payload);
+                channel.server.send_message(kick.client_id, 3, payload);
+            }
+
+            pending.0.push((kick.client_id, now + KICK_DELAY));
+        }
+
+        pending.0.retain(|(client_id, at)| {
+            if now < *at {
+                return true;
+            }
+
+            channel.server.disconnect(*client_id);
+
+            false
+        });
+    }
+}
+
+impl TransportPlugin {
+    fn create_transport(mut commands: Commands, settings: Res<ServerSettings>) {
+        let current_time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let server_addr = SocketAddr::new(
+            local_ip().expect("Could not find local ip address."),
+            SERVER_PORT,
+        );
+
+        info!("Creating Server!: {:?}", server_addr);
+
+        let mut private_key = get_private_key_env();
+
+        let authentication = ServerAuthentication::Secure { private_key };
+
+        let mut public_addresses = vec![server_addr];
+
+        // Clients reach the conditioned port through the same connect tokens, so it has to be
+        // one of the server's public addresses.
+        if let Some(conditions) = settings.netsim {
+            let bind = SocketAddr::new(server_addr.ip(), settings.netsim_port);
+
+            match NetworkConditioner::spawn(bind, server_addr, conditions) {
+                Ok(conditioner) => {
+                    public_addresses.push(conditioner.local_addr());
+                    commands.insert_resource(conditioner);
                 }
+                Err(error) => warn!("Could not start network conditioner on {}: {}", bind, error),
             }
         }
+
+        let server_config = ServerConfig {
+            max_clients: MAX_CLIENTS,
+            protocol_id: 69,
+            public_addresses,
+            authentication,
+            current_time,
+        };
+
+        private_key.zeroize();
+
+        let socket = UdpSocket::bind(server_addr)
+            .expect("UdpSocket bind failure. Consider restarting the server.");
+
+        let transport = NetcodeServerTransport::new(server_config, socket).expect(
+            "NetcodeServerTransport could not be established. Consider restarting the server.",
+        );
+
+        commands.insert_resource(transport);
+    }
+}
+
+impl Plugin for TransportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, Self::create_transport);
     }
 }
 
@@ -136,8 +318,13 @@ impl Plugin for NetworkPlugin {
         app.insert_resource(DKeyStore(HashMap::new()));
         app.insert_resource(SSKStore(HashMap::new()));
         app.insert_resource(Nonce(HashMap::new()));
-        app.add_systems(Startup, Self::create_renet_server);
+        app.insert_resource(ReceivedNonce::default());
+        app.insert_resource(PendingKicks::default());
+        app.insert_resource(RateLimiter::default());
+        app.add_message::<KickClient>();
+        app.add_systems(Startup, (Self::create_renet_server, Self::load_access_list));
         app.add_systems(Update, Self::server_events);
         app.add_systems(Update, receive_client_messages);
+        app.add_systems(Update, Self::kick_clients);
     }
 }
@@ -0,0 +1,247 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    common::network::{PlayerProfile, Role, ServerMessage},
+    server::{
+        accounts::LoggedInUsers,
+        config::ServerSettings,
+        encryption::SecureChannel,
+        session::{MatchState, Sessions},
+    },
+};
+
+/// Matches kept in a profile's role history.
+pub const MAX_ROLE_HISTORY: usize = 20;
+
+/// Keeps lifetime stats for every account and sends players their own on request.
+pub struct ProfilesPlugin;
+
+/// A logged in client asked for its profile.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ProfileRequest {
+    pub client_id: u64,
+}
+
+/// Profiles stored one per line as `username matches history`, keyed by lowercase username.
+///
+/// The history is one letter per match, `G` for Gray and `N` for Note, or `-` when empty.
+#[derive(Resource)]
+pub struct ProfileStore {
+    /// Not written anywhere when `None`.
+    path: Option<PathBuf>,
+    profiles: HashMap<String, PlayerProfile>,
+}
+
+fn parse_line(line: &str) -> Option<PlayerProfile> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    let (username, matches_played, history) = match words[..] {
+        [username, matches_played, history] => (username, matches_played, history),
+        // Older stores also have night, beacon and enemy counts, which nothing ever counted.
+        [username, matches_played, _, _, _, history] => (username, matches_played, history),
+        _ => return None,
+    };
+
+    let username = username.to_string();
+    let matches_played = matches_played.parse().ok()?;
+    let role_history = history
+        .chars()
+        .filter(|c| *c != '-')
+        .map(|c| match c {
+            'G' => Some(Role::Gray),
+            'N' => Some(Role::Note),
+            _ => None,
+        })
+        .collect::<Option<Vec<Role>>>()?;
+
+    Some(PlayerProfile {
+        username,
+        matches_played,
+        role_history,
+    })
+}
+
+fn format_line(profile: &PlayerProfile) -> String {
+    let mut history: String = profile
+        .role_history
+        .iter()
+        .map(|role| match role {
+            Role::Gray => 'G',
+            Role::Note => 'N',
+        })
+        .collect();
+
+    if history.is_empty() {
+        history.push('-');
+    }
+
+    format!(
+        "{} {} {}\n",
+        profile.username, profile.matches_played, history
+    )
+}
+
+impl ProfileStore {
+    /// Reads the file at `path`. A missing file is an empty store and bad lines are skipped.
+    pub fn load(path: &Path) -> Self {
+        let profiles = match fs::read_to_string(path) {
+            Ok(contents) => contents
+                .lines()
+                .filter_map(parse_line)
+                .map(|profile| (profile.username.to_lowercase(), profile))
+                .collect(),
+            Err(error) => {
+                if error.kind() != io::ErrorKind::NotFound {
+                    warn!("Could not read profiles {}: {}", path.display(), error);
+                }
+                HashMap::new()
+            }
+        };
+
+        Self {
+            path: Some(path.to_path_buf()),
+            profiles,
+        }
+    }
+
+    /// An empty store that is never saved, for replays.
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            profiles: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.profiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.profiles.is_empty()
+    }
+
+    /// The stored profile, or an empty one for accounts that never played.
+    pub fn get(&self, username: &str) -> PlayerProfile {
+        self.profiles
+            .get(&username.to_lowercase())
+            .cloned()
+            .unwrap_or_else(|| PlayerProfile {
+                username: username.to_string(),
+                ..default()
+            })
+    }
+
+    /// Changes a profile and writes the store.
+    pub fn update(&mut self, username: &str, change: impl FnOnce(&mut PlayerProfile)) {
+        let profile = self
+            .profiles
+            .entry(username.to_lowercase())
+            .or_insert_with(|| PlayerProfile {
+                username: username.to_string(),
+                ..default()
+            });
+
+        change(profile);
+
+        self.save();
+    }
+
+    pub fn record_match(&mut self, username: &str, role: Role) {
+        self.update(username, |profile| {
+            profile.matches_played += 1;
+            profile.role_history.push(role);
+
+            let excess = profile.role_history.len().saturating_sub(MAX_ROLE_HISTORY);
+            profile.role_history.drain(..excess);
+        });
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let mut profiles: Vec<&PlayerProfile> = self.profiles.values().collect();
+        profiles.sort_by(|a, b| a.username.cmp(&b.username));
+
+        let contents: String = profiles.into_iter().map(format_line).collect();
+
+        if let Err(error) = fs::write(path, contents) {
+            warn!("Could not save profiles {}: {}", path.display(), error);
+        }
+    }
+}
+
+impl ProfilesPlugin {
+    fn load_profiles(
+        mut commands: Commands,
+        settings: Res<ServerSettings>,
+        existing: Option<Res<ProfileStore>>,
+    ) {
+        if existing.is_some() {
+            return;
+        }
+
+        let store = ProfileStore::load(&settings.profiles);
+
+        info!(
+            "Loaded {} profiles from {}",
+            store.len(),
+            settings.profiles.display()
+        );
+
+        commands.insert_resource(store);
+    }
+
+    /// Counts a match for both players when it starts. Resuming after a drop is the same match.
+    fn count_matches(
+        match_state: Res<MatchState>,
+        sessions: Res<Sessions>,
+        mut store: ResMut<ProfileStore>,
+        mut previous: Local<MatchState>,
+    ) {
+        if *match_state == *previous {
+            return;
+        }
+
+        if *previous == MatchState::WaitingForPlayers && *match_state == MatchState::InProgress {
+            for session in sessions.0.iter() {
+                store.record_match(&session.username, session.role);
+            }
+        }
+
+        *previous = *match_state;
+    }
+
+    fn answer_requests(
+        mut requests: MessageReader<ProfileRequest>,
+        store: Res<ProfileStore>,
+        logged_in: Res<LoggedInUsers>,
+        mut channel: SecureChannel,
+    ) {
+        for request in requests.read() {
+            let Some(username) = logged_in.0.get(&request.client_id) else {
+                continue;
+            };
+
+            channel.send(
+                request.client_id,
+                &ServerMessage::Profile(store.get(username)),
+            );
+        }
+    }
+}
+
+impl Plugin for ProfilesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<ProfileRequest>();
+        app.add_systems(Startup, Self::load_profiles);
+        app.add_systems(Update, (Self::count_matches, Self::answer_requests).chain());
+    }
+}
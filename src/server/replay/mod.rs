@@ -0,0 +1,554 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use bevy::{log::LogPlugin, prelude::*, time::TimeUpdateStrategy};
+use bevy_renet::{RenetServerPlugin, renet::RenetServer};
+use bincode::{Decode, Encode, error::DecodeError};
+use cryptoxide::hashing::sha256;
+use rand::TryRngCore;
+use zeroize::Zeroizing;
+
+use crate::{
+    common::{
+        network::{
+            ClientMessage, ConnectedUsers, ResumeToken, ServerMessage, UserData, connection_config,
+        },
+        voice::VoiceFrame,
+        world::WorldSeed,
+    },
+    server::{
+        accounts::LoggedInUsers,
+        chat::{ChatPlugin, ClientChat},
+        config::ServerSettings,
+        encryption::{Nonce, SSKStore},
+        metrics::ServerMetrics,
+        netlog::NetLog,
+        profiles::{ProfileRequest, ProfileStore, ProfilesPlugin},
+        session::{ClientHello, ClientPlayerState, SessionPlugin},
+        signals::{ClientSignal, SignalsPlugin},
+        tether::TetherPlugin,
+        voice::{ClientVoice, VoicePlugin},
+    },
+};
+
+const RECORDING_VERSION: u32 = 2;
+
+pub struct RecordingPlugin;
+
+#[derive(Encode, Decode, Debug)]
+struct RecordingHeader {
+    version: u32,
+    seed: u64,
+}
+
+/// Something that happened during a server tick.
+///
+/// Handshake traffic and passwords are left out. Resume tokens are only stored hashed and voice
+/// only by its length, see [`MatchRecorder::record_input`].
+#[derive(Encode, Decode, Debug, Clone)]
+pub enum RecordedEvent {
+    Connected {
+        client_id: u64,
+        username: String,
+    },
+    Disconnected {
+        client_id: u64,
+    },
+    HandshakeComplete {
+        client_id: u64,
+    },
+    /// The account check passed, standing in for the Login or Register that is not recorded.
+    LoggedIn {
+        client_id: u64,
+        username: String,
+    },
+    Input {
+        client_id: u64,
+        message: ClientMessage,
+    },
+    /// Hash of a resume token the server drew. Replayed sessions hand out the hashes as their
+    /// tokens, which is what the recorded Hellos and Welcomes carry.
+    ResumeToken(ResumeToken),
+    Output {
+        client_id: u64,
+        message: ServerMessage,
+    },
+}
+
+/// One server tick. Every tick is written, empty or not, so replay runs on the same clock.
+#[derive(Encode, Decode, Debug)]
+struct Frame {
+    delta_micros: u64,
+    events: Vec<RecordedEvent>,
+}
+
+/// Collects the events of the current tick and writes them out at the end of it.
+///
+/// During replay it collects outputs for comparison and serves the recorded resume tokens.
+#[derive(Resource, Default)]
+pub struct MatchRecorder {
+    writer: Option<BufWriter<File>>,
+    capturing: bool,
+    events: Vec<RecordedEvent>,
+    replay_tokens: Option<VecDeque<ResumeToken>>,
+}
+
+impl MatchRecorder {
+    pub fn record(&mut self, event: RecordedEvent) {
+        if self.capturing {
+            self.events.push(event);
+        }
+    }
+
+    /// Records a client message, without the passwords, tokens and audio it carries.
+    pub fn record_input(&mut self, client_id: u64, message: &ClientMessage) {
+        if !self.capturing {
+            return;
+        }
+
+        let message = match message {
+            ClientMessage::KEMCipherText(_)
+            | ClientMessage::Login { .. }
+            | ClientMessage::Register { .. } => return,
+            ClientMessage::Hello { resume_token } => ClientMessage::Hello {
+                resume_token: resume_token.map(|token| self.hide_token(&token)),
+            },
+            ClientMessage::Voice(frame) => ClientMessage::Voice(silent(frame)),
+            message => message.clone(),
+        };
+
+        self.events
+            .push(RecordedEvent::Input { client_id, message });
+    }
+
+    pub fn record_output(&mut self, client_id: u64, message: &ServerMessage) {
+        if !self.capturing {
+            return;
+        }
+
+        let message = match message {
+            ServerMessage::Welcome {
+                role,
+                resume_token,
+                resumed,
+                translation,
+                seed,
+            } => ServerMessage::Welcome {
+                role: *role,
+                resume_token: self.hide_token(resume_token),
+                resumed: *resumed,
+                translation: *translation,
+                seed: *seed,
+            },
+            ServerMessage::Voice {
+                frame,
+                gain,
+                translation,
+            } => ServerMessage::Voice {
+                frame: silent(frame),
+                gain: *gain,
+                translation: *translation,
+            },
+            message => message.clone(),
+        };
+
+        self.events
+            .push(RecordedEvent::Output { client_id, message });
+    }
+
+    /// Replaced by its hash in recordings. Replay already hands out the hashes.
+    fn hide_token(&self, token: &ResumeToken) -> ResumeToken {
+        if self.replay_tokens.is_some() {
+            *token
+        } else {
+            sha256(token)
+        }
+    }
+
+    /// Draws a fresh resume token, or the next recorded one while replaying.
+    pub fn resume_token(&mut self) -> Zeroizing<ResumeToken> {
+        if let Some(tokens) = &mut self.replay_tokens {
+            return Zeroizing::new(tokens.pop_front().unwrap_or_default());
+        }
+
+        let mut token = Zeroizing::new([0u8; 32]);
+        rand::rngs::OsRng
+            .try_fill_bytes(&mut *token)
+            .expect("Could not generate resume token.");
+
+        self.record(RecordedEvent::ResumeToken(sha256(&*token)));
+
+        token
+    }
+}
+
+/// A frame of the same length without the audio.
+fn silent(frame: &VoiceFrame) -> VoiceFrame {
+    VoiceFrame {
+        data: vec![0; frame.data.len()],
+        ..frame.clone()
+    }
+}
+
+impl RecordingPlugin {
+    fn start_recording(
+        mut recorder: ResMut<MatchRecorder>,
+        settings: Res<ServerSettings>,
+        seed: Res<WorldSeed>,
+    ) {
+        let Some(path) = &settings.record_path else {
+            return;
+        };
+
+        let mut writer = match File::create(path) {
+            Ok(file) => BufWriter::new(file),
+            Err(error) => {
+                warn!("Could not create recording {}: {}", path.display(), error);
+                return;
+            }
+        };
+
+        let header = RecordingHeader {
+            version: RECORDING_VERSION,
+            seed: seed.0,
+        };
+
+        if let Err(error) =
+            bincode::encode_into_std_write(&header, &mut writer, bincode::config::standard())
+        {
+            warn!("Could not write recording {}: {}", path.display(), error);
+            return;
+        }
+
+        info!("Recording match to {}", path.display());
+
+        recorder.writer = Some(writer);
+        recorder.capturing = true;
+    }
+
+    /// Writes the virtual time delta, the one the simulation ran on.
+    fn write_frame(mut recorder: ResMut<MatchRecorder>, time: Res<Time>) {
+        let recorder = &mut *recorder;
+
+        let Some(writer) = &mut recorder.writer else {
+            return;
+        };
+
+        let frame = Frame {
+            delta_micros: time.delta().as_micros() as u64,
+            events: std::mem::take(&mut recorder.events),
+        };
+
+        let result =
+            bincode::encode_into_std_write(&frame, &mut *writer, bincode::config::standard())
+                .map_err(|error| error.to_string())
+                .and_then(|_| writer.flush().map_err(|error| error.to_string()));
+
+        if let Err(error) = result {
+            warn!("Could not write recording, stopping it: {}", error);
+            recorder.writer = None;
+            recorder.capturing = false;
+        }
+    }
+}
+
+impl Plugin for RecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MatchRecorder::default());
+        app.add_systems(Startup, Self::start_recording);
+        app.add_systems(Last, Self::write_frame);
+    }
+}
+
+pub struct ReplayReport {
+    pub frames: usize,
+    pub inputs: usize,
+    pub matched: usize,
+    /// Description of the first output that differed from the recording.
+    pub divergence: Option<String>,
+}
+
+/// Outputs produced by the simulation. Notices, kicks and shutdowns come from the operator,
+/// logins and profiles from the stores on disk, and are not reproduced.
+fn simulated(message: &ServerMessage) -> bool {
+    matches!(
+        message,
+        ServerMessage::Welcome { .. }
+            | ServerMessage::PartnerStatus(_)
+            | ServerMessage::Comm { .. }
+            | ServerMessage::Chat { .. }
+            | ServerMessage::Signal { .. }
+            | ServerMessage::Voice { .. }
+    )
+}
+
+fn read_recording(path: &Path) -> Result<(RecordingHeader, Vec<Frame>), String> {
+    let file = File::open(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    let mut reader = BufReader::new(file);
+    let config = bincode::config::standard();
+
+    let header: RecordingHeader = bincode::decode_from_std_read(&mut reader, config)
+        .map_err(|error| format!("Invalid recording header: {}", error))?;
+
+    if header.version != RECORDING_VERSION {
+        return Err(format!(
+            "Unsupported recording version {}, expected {}",
+            header.version, RECORDING_VERSION
+        ));
+    }
+
+    let mut frames = vec![];
+
+    loop {
+        match bincode::decode_from_std_read::<Frame, _, _>(&mut reader, config) {
+            Ok(frame) => frames.push(frame),
+            // A server that died mid-write leaves a truncated last frame.
+            Err(DecodeError::Io { inner, .. }) if inner.kind() == io::ErrorKind::UnexpectedEof => {
+                break;
+            }
+            Err(DecodeError::UnexpectedEnd { .. }) => break,
+            Err(error) => {
+                return Err(format!("Invalid frame {}: {}", frames.len(), error));
+            }
+        }
+    }
+
+    Ok((header, frames))
+}
+
+fn apply(world: &mut World, event: &RecordedEvent) {
+    match event {
+        RecordedEvent::Connected {
+            client_id,
+            username,
+        } => {
+            world
+                .resource_mut::<ConnectedUsers>()
+                .0
+                .insert(*client_id, UserData::from_str(username));
+            world
+                .resource_mut::<RenetServer>()
+                .add_connection(*client_id);
+        }
+
+        RecordedEvent::Disconnected { client_id } => {
+            let mut server = world.resource_mut::<RenetServer>();
+
+            if server.is_connected(*client_id) {
+                server.disconnect(*client_id);
+            }
+
+            world.resource_mut::<SSKStore>().0.remove(client_id);
+            world.resource_mut::<LoggedInUsers>().0.remove(client_id);
+        }
+
+        // Replayed messages are never put on the wire, so any key will do.
+        RecordedEvent::HandshakeComplete { client_id } => {
+            world
+                .resource_mut::<SSKStore>()
+                .0
+                .insert(*client_id, Zeroizing::new([0u8; 32]));
+        }
+
+        RecordedEvent::LoggedIn {
+            client_id,
+            username,
+        } => {
+            world
+                .resource_mut::<LoggedInUsers>()
+                .0
+                .insert(*client_id, username.clone());
+        }
+
+        RecordedEvent::Input { client_id, message } => {
+            let client_id = *client_id;
+            let logged_in = world.resource::<LoggedInUsers>().contains(client_id);
+
+            // Filtered the same way the server does before handing messages on.
+            match message.clone() {
+                ClientMessage::Hello { resume_token } if logged_in => {
+                    world.write_message(ClientHello {
+                        client_id,
+                        resume_token,
+                    });
+                }
+                ClientMessage::PlayerState { translation } => {
+                    world.write_message(ClientPlayerState {
+                        client_id,
+                        translation: Vec3::from_array(translation),
+                    });
+                }
+                ClientMessage::ProfileRequest if logged_in => {
+                    world.write_message(ProfileRequest { client_id });
+                }
+                ClientMessage::Chat { text } => {
+                    world.write_message(ClientChat { client_id, text });
+                }
+                ClientMessage::Voice(frame) => {
+                    world.write_message(ClientVoice { client_id, frame });
+                }
+                ClientMessage::Signal { id } => {
+                    world.write_message(ClientSignal { client_id, id });
+                }
+                _ => {}
+            }
+        }
+
+        RecordedEvent::ResumeToken(_) | RecordedEvent::Output { .. } => {}
+    }
+}
+
+/// Feeds a recording through the simulation and checks that it sends the same messages it did
+/// during the match.
+///
+/// Messages are compared in order per client and kind. Systems that send to the same client in
+/// one tick may run in any order, on the server as well as here.
+pub fn replay(path: &Path) -> Result<ReplayReport, String> {
+    let (header, frames) = read_recording(path)?;
+
+    let tokens = frames
+        .iter()
+        .flat_map(|frame| &frame.events)
+        .filter_map(|event| match event {
+            RecordedEvent::ResumeToken(token) => Some(*token),
+            _ => None,
+        })
+        .collect();
+
+    let mut expected: BTreeMap<(u64, &'static str), VecDeque<(usize, ServerMessage)>> =
+        BTreeMap::new();
+
+    for (tick, frame) in frames.iter().enumerate() {
+        for event in &frame.events {
+            if let RecordedEvent::Output { client_id, message } = event
+                && simulated(message)
+            {
+                expected
+                    .entry((*client_id, message.kind()))
+                    .or_default()
+                    .push_back((tick, message.clone()));
+            }
+        }
+    }
+
+    let mut app = App::new();
+
+    app.add_plugins(MinimalPlugins);
+    app.add_plugins(LogPlugin::default());
+    app.add_plugins(RenetServerPlugin);
+
+    app.insert_resource(RenetServer::new(connection_config()));
+    app.insert_resource(ConnectedUsers::default());
+    app.insert_resource(LoggedInUsers::default());
+    app.insert_resource(SSKStore(HashMap::new()));
+    app.insert_resource(Nonce(HashMap::new()));
+    app.insert_resource(NetLog::default());
+    app.insert_resource(ServerMetrics::default());
+    app.insert_resource(ServerSettings::default());
+    app.insert_resource(WorldSeed(header.seed));
+    app.insert_resource(MatchRecorder {
+        capturing: true,
+        replay_tokens: Some(tokens),
+        ..default()
+    });
+    app.insert_resource(ProfileStore::in_memory());
+
+    app.add_plugins(SessionPlugin);
+    app.add_plugins(TetherPlugin);
+    app.add_plugins(ChatPlugin);
+    app.add_plugins(SignalsPlugin);
+    app.add_plugins(VoicePlugin);
+    app.add_plugins(ProfilesPlugin);
+
+    app.finish();
+    app.cleanup();
+
+    info!(
+        "Replaying {} ({} frames, seed {})",
+        path.display(),
+        frames.len(),
+        header.seed
+    );
+
+    let mut report = ReplayReport {
+        frames: frames.len(),
+        inputs: 0,
+        matched: 0,
+        divergence: None,
+    };
+
+    for (tick, frame) in frames.iter().enumerate() {
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_micros(
+            frame.delta_micros,
+        )));
+
+        for event in &frame.events {
+            if let RecordedEvent::Input { .. } = event {
+                report.inputs += 1;
+            }
+
+            apply(app.world_mut(), event);
+        }
+
+        app.update();
+
+        let produced = std::mem::take(&mut app.world_mut().resource_mut::<MatchRecorder>().events);
+
+        for event in produced {
+            let RecordedEvent::Output { client_id, message } = event else {
+                continue;
+            };
+
+            if !simulated(&message) {
+                continue;
+            }
+
+            let next = expected
+                .get_mut(&(client_id, message.kind()))
+                .and_then(VecDeque::pop_front);
+
+            match next {
+                Some((_, expected_message)) if expected_message == message => {
+                    report.matched += 1;
+                }
+                Some((recorded_tick, expected_message)) => {
+                    report.divergence = Some(format!(
+                        "tick {}: sent {:?} to {}, recording has {:?} at tick {}",
+                        tick, message, client_id, expected_message, recorded_tick
+                    ));
+                    return Ok(report);
+                }
+                None => {
+                    report.divergence = Some(format!(
+                        "tick {}: sent {:?} to {}, recording has no more of those",
+                        tick, message, client_id
+                    ));
+                    return Ok(report);
+                }
+            }
+        }
+    }
+
+    let missing = expected
+        .into_iter()
+        .filter_map(|((client_id, _), mut messages)| {
+            messages
+                .pop_front()
+                .map(|(tick, message)| (tick, client_id, message))
+        })
+        .min_by_key(|(tick, _, _)| *tick);
+
+    if let Some((tick, client_id, message)) = missing {
+        report.divergence = Some(format!(
+            "recording sent {:?} to {} at tick {}, replay never did",
+            message, client_id, tick
+        ));
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,380 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_renet::renet::ServerEvent;
+use zeroize::Zeroizing;
+
+use crate::{
+    common::{
+        network::{ConnectedUsers, PartnerStatus, ResumeToken, Role, ServerMessage},
+        world::{WorldGenerator, WorldSeed},
+    },
+    server::{encryption::SecureChannel, shutdown::SavedWorld},
+};
+
+pub struct SessionPlugin;
+
+/// A client finished the KEM handshake and asked to join, optionally resuming an old session.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ClientHello {
+    pub client_id: u64,
+    pub resume_token: Option<ResumeToken>,
+}
+
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ClientPlayerState {
+    pub client_id: u64,
+    pub translation: Vec3,
+}
+
+/// Server-side entity standing in for a player, kept alive while its client is reconnecting.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ServerPlayer {
+    pub role: Role,
+}
+
+pub struct Session {
+    pub username: String,
+    pub role: Role,
+    pub entity: Entity,
+    pub client_id: Option<u64>,
+    pub resume_token: Zeroizing<ResumeToken>,
+    /// Elapsed time at which the client dropped, while it is inside the grace period.
+    pub disconnected_at: Option<Duration>,
+}
+
+#[derive(Resource, Default)]
+pub struct Sessions(pub Vec<Session>);
+
+impl Sessions {
+    pub fn by_client(&self, client_id: u64) -> Option<&Session> {
+        self.0
+            .iter()
+            .find(|session| session.client_id == Some(client_id))
+    }
+
+    pub fn partner_of(&self, role: Role) -> Option<&Session> {
+        self.0.iter().find(|session| session.role != role)
+    }
+
+    fn partner_client(&self, role: Role) -> Option<u64> {
+        self.partner_of(role).and_then(|partner| partner.client_id)
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct SessionConfig {
+    /// How long a dropped player's role and entity are held for them.
+    pub grace_period: Duration,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            grace_period: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchState {
+    #[default]
+    WaitingForPlayers,
+    InProgress,
+    /// One player dropped and is inside their grace period.
+    WaitingForTeammate,
+}
+
+impl SessionPlugin {
+    fn join_or_resume(
+        mut commands: Commands,
+        mut hellos: MessageReader<ClientHello>,
+        mut sessions: ResMut<Sessions>,
+        users: Res<ConnectedUsers>,
+        seed: Res<WorldSeed>,
+        mut saved: Option<ResMut<SavedWorld>>,
+        mut channel: SecureChannel,
+        players: Query<&Transform, With<ServerPlayer>>,
+    ) {
+        for hello in hellos.read() {
+            let client_id = hello.client_id;
+
+            if sessions.by_client(client_id).is_some() {
+                warn!("Client {} sent Hello twice.", client_id);
+                continue;
+            }
+
+            let username = users
+                .0
+                .get(&client_id)
+                .map(|user| user.to_username().to_string())
+                .unwrap_or_default();
+
+            let resumable = hello.resume_token.and_then(|token| {
+                sessions.0.iter().position(|session| {
+                    session.disconnected_at.is_some()
+                        && session.username == username
+                        && *session.resume_token == token
+                })
+            });
+
+            if let Some(index) = resumable {
+                let session = &mut sessions.0[index];
+
+                session.client_id = Some(client_id);
+                session.disconnected_at = None;
+
+                let translation = players
+                    .get(session.entity)
+                    .map(|transform| transform.translation)
+                    .unwrap_or_default();
+
+                info!(
+                    "Resumed session => username: {} id: {} role: {:?}",
+                    username, client_id, session.role
+                );
+
+                let welcome = ServerMessage::Welcome {
+                    role: session.role,
+                    resume_token: *session.resume_token,
+                    resumed: true,
+                    translation: translation.to_array(),
+                    seed: seed.0,
+                };
+                let role = session.role;
+
+                channel.send(client_id, &welcome);
+
+                if let Some(partner_id) = sessions.partner_client(role) {
+                    channel.send(
+                        partner_id,
+                        &ServerMessage::PartnerStatus(PartnerStatus::Connected),
+                    );
+                    channel.send(
+                        client_id,
+                        &ServerMessage::PartnerStatus(PartnerStatus::Connected),
+                    );
+                }
+
+                continue;
+            }
+
+            let free = |role: &Role| sessions.0.iter().all(|session| session.role != *role);
+
+            // A player saved by the last shutdown gets their role back if it is still free.
+            let restored = saved.as_mut().and_then(|saved| saved.take(&username));
+
+            let Some(role) = restored
+                .as_ref()
+                .map(|player| player.role)
+                .filter(free)
+                .or_else(|| [Role::Gray, Role::Note].into_iter().find(free))
+            else {
+                warn!(
+                    "No free role for client: {} id: {}, disconnecting.",
+                    username, client_id
+                );
+                channel.server.disconnect(client_id);
+                continue;
+            };
+
+            let resume_token = channel.recorder.resume_token();
+
+            let translation = restored
+                .map(|player| player.translation)
+                .unwrap_or_default();
+
+            let entity = commands
+                .spawn((
+                    ServerPlayer { role },
+                    Transform::from_translation(Vec3::from_array(translation)),
+                ))
+                .id();
+
+            info!(
+                "New session => username: {} id: {} role: {:?}",
+                username, client_id, role
+            );
+
+            channel.send(
+                client_id,
+                &ServerMessage::Welcome {
+                    role,
+                    resume_token: *resume_token,
+                    resumed: false,
+                    translation,
+                    seed: seed.0,
+                },
+            );
+
+            sessions.0.push(Session {
+                username,
+                role,
+                entity,
+                client_id: Some(client_id),
+                resume_token,
+                disconnected_at: None,
+            });
+
+            if let Some(partner_id) = sessions.partner_client(role) {
+                channel.send(
+                    partner_id,
+                    &ServerMessage::PartnerStatus(PartnerStatus::Connected),
+                );
+                channel.send(
+                    client_id,
+                    &ServerMessage::PartnerStatus(PartnerStatus::Connected),
+                );
+            }
+        }
+    }
+
+    fn apply_player_state(
+        mut states: MessageReader<ClientPlayerState>,
+        sessions: Res<Sessions>,
+        seed: Res<WorldSeed>,
+        mut players: Query<&mut Transform, With<ServerPlayer>>,
+    ) {
+        let generator = WorldGenerator::new(*seed);
+
+        for state in states.read() {
+            let Some(session) = sessions.by_client(state.client_id) else {
+                continue;
+            };
+
+            if !state.translation.is_finite() {
+                warn!(
+                    "Dropped impossible position {} from {}",
+                    state.translation, session.username
+                );
+                continue;
+            }
+
+            // Nobody stands inside the terrain, players who say they do are put back on top.
+            let mut translation = state.translation;
+            translation.y = translation
+                .y
+                .max(generator.ground_at(translation.x, translation.z));
+
+            if let Ok(mut transform) = players.get_mut(session.entity) {
+                transform.translation = translation;
+            }
+        }
+    }
+
+    fn hold_dropped_sessions(
+        mut event_reader: MessageReader<ServerEvent>,
+        mut sessions: ResMut<Sessions>,
+        mut channel: SecureChannel,
+        config: Res<SessionConfig>,
+        time: Res<Time>,
+    ) {
+        for event in event_reader.read() {
+            let ServerEvent::ClientDisconnected { client_id, .. } = event else {
+                continue;
+            };
+
+            let Some(session) = sessions
+                .0
+                .iter_mut()
+                .find(|session| session.client_id == Some(*client_id))
+            else {
+                continue;
+            };
+
+            session.client_id = None;
+            session.disconnected_at = Some(time.elapsed());
+
+            info!(
+                "Holding {:?} for {} for {:?}.",
+                session.role, session.username, config.grace_period
+            );
+
+            let role = session.role;
+
+            if let Some(partner_id) = sessions.partner_client(role) {
+                let seconds = config.grace_period.as_secs() as u32;
+
+                channel.send(
+                    partner_id,
+                    &ServerMessage::PartnerStatus(PartnerStatus::Reconnecting(seconds)),
+                );
+            }
+        }
+    }
+
+    fn expire_sessions(
+        mut commands: Commands,
+        mut sessions: ResMut<Sessions>,
+        mut channel: SecureChannel,
+        config: Res<SessionConfig>,
+        time: Res<Time>,
+    ) {
+        let now = time.elapsed();
+        let mut expired = vec![];
+
+        sessions.0.retain(|session| {
+            let keep = session
+                .disconnected_at
+                .is_none_or(|at| now.saturating_sub(at) < config.grace_period);
+
+            if !keep {
+                expired.push((session.role, session.entity));
+                info!("Session expired => username: {}", session.username);
+            }
+
+            keep
+        });
+
+        for (role, entity) in expired {
+            commands.entity(entity).despawn();
+
+            if let Some(partner_id) = sessions.partner_client(role) {
+                channel.send(
+                    partner_id,
+                    &ServerMessage::PartnerStatus(PartnerStatus::Absent),
+                );
+            }
+        }
+    }
+
+    fn update_match_state(sessions: Res<Sessions>, mut match_state: ResMut<MatchState>) {
+        let next = if sessions.0.len() < 2 {
+            MatchState::WaitingForPlayers
+        } else if sessions
+            .0
+            .iter()
+            .any(|session| session.disconnected_at.is_some())
+        {
+            MatchState::WaitingForTeammate
+        } else {
+            MatchState::InProgress
+        };
+
+        if *match_state != next {
+            info!("Match state: {:?} -> {:?}", *match_state, next);
+            *match_state = next;
+        }
+    }
+}
+
+impl Plugin for SessionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Sessions::default());
+        app.insert_resource(SessionConfig::default());
+        app.insert_resource(MatchState::default());
+        app.add_message::<ClientHello>();
+        app.add_message::<ClientPlayerState>();
+        app.add_systems(
+            Update,
+            (
+                Self::join_or_resume,
+                Self::apply_player_state,
+                Self::hold_dropped_sessions,
+                Self::expire_sessions,
+                Self::update_match_state,
+            )
+                .chain(),
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_renet::{
+    netcode::NetcodeServerTransport,
+    renet::{RenetServer, ServerEvent},
+};
+use bincode::{Decode, Encode};
+
+use crate::{
+    common::network::{Role, ServerMessage},
+    server::{
+        config::ServerSettings,
+        encryption::SecureChannel,
+        network::KickClient,
+        session::{ServerPlayer, Sessions},
+    },
+};
+
+pub struct ShutdownPlugin;
+
+/// Asks the server to notify every client and exit once reliable channels had time to drain.
+#[derive(Message, Debug, Clone)]
+pub struct RequestShutdown {
+    pub reason: String,
+}
+
+/// Raised from the SIGINT/SIGTERM handler thread.
+#[derive(Resource, Clone, Default)]
+struct SignalFlag(Arc<AtomicBool>);
+
+/// Set once shutdown began. New clients are turned away with `reason` until the server exits.
+#[derive(Resource)]
+struct Draining {
+    /// Elapsed time at which draining ends and the server exits.
+    until: Duration,
+    reason: String,
+}
+
+#[derive(Encode, Decode, Debug, Clone)]
+pub struct SavedPlayer {
+    pub username: String,
+    pub role: Role,
+    pub translation: [f32; 3],
+}
+
+/// Players saved by the last shutdown, each handed their role and place back when they next join.
+#[derive(Resource, Debug, Default)]
+pub struct SavedWorld(pub Vec<SavedPlayer>);
+
+impl SavedWorld {
+    /// Reads a snapshot written on shutdown. A missing or unreadable file is an empty world.
+    pub fn load(path: &Path) -> Self {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(error) => {
+                if error.kind() != io::ErrorKind::NotFound {
+                    warn!("Could not read save file {}: {}", path.display(), error);
+                }
+                return Self::default();
+            }
+        };
+
+        match bincode::decode_from_std_read(&mut BufReader::new(file), bincode::config::standard())
+        {
+            Ok(players) => Self(players),
+            Err(error) => {
+                warn!("Could not load world state {}: {}", path.display(), error);
+                Self::default()
+            }
+        }
+    }
+
+    /// Removes and returns `username`'s saved state, so it is only restored once.
+    pub fn take(&mut self, username: &str) -> Option<SavedPlayer> {
+        let index = self
+            .0
+            .iter()
+            .position(|player| player.username.eq_ignore_ascii_case(username))?;
+
+        Some(self.0.remove(index))
+    }
+}
+
+impl ShutdownPlugin {
+    fn install_signal_handler(flag: Res<SignalFlag>) {
+        let flag = flag.0.clone();
+
+        if let Err(error) = ctrlc::try_set_handler(move || flag.store(true, Ordering::SeqCst)) {
+            warn!("Could not install SIGINT/SIGTERM handler: {}", error);
+        }
+    }
+
+    fn load_world(mut commands: Commands, settings: Res<ServerSettings>) {
+        let Some(path) = &settings.save_path else {
+            return;
+        };
+
+        let saved = SavedWorld::load(path);
+
+        if !saved.0.is_empty() {
+            info!(
+                "Loaded {} saved players from {}",
+                saved.0.len(),
+                path.display()
+            );
+        }
+
+        commands.insert_resource(saved);
+    }
+
+    fn watch_signal(flag: Res<SignalFlag>, mut requests: MessageWriter<RequestShutdown>) {
+        if flag.0.swap(false, Ordering::SeqCst) {
+            requests.write(RequestShutdown {
+                reason: "Server is shutting down.".to_string(),
+            });
+        }
+    }
+
+    fn begin_shutdown(
+        mut commands: Commands,
+        mut requests: MessageReader<RequestShutdown>,
+        draining: Option<Res<Draining>>,
+        mut channel: SecureChannel,
+        sessions: Res<Sessions>,
+        players: Query<&Transform, With<ServerPlayer>>,
+        settings: Res<ServerSettings>,
+        time: Res<Time>,
+    ) {
+        let Some(request) = requests.read().last() else {
+            return;
+        };
+
+        if draining.is_some() {
+            return;
+        }
+
+        info!("Shutting down => reason: {}", request.reason);
+
+        let message = ServerMessage::Shutdown {
+            reason: request.reason.clone(),
+        };
+
+        for client_id in channel.server.clients_id() {
+            channel.send(client_id, &message);
+        }
+
+        if let Some(path) = &settings.save_path {
+            save_world(path, &sessions, &players);
+        }
+
+        commands.insert_resource(Draining {
+            until: time.elapsed() + settings.shutdown_drain,
+            reason: request.reason.clone(),
+        });
+    }
+
+    fn turn_away(
+        mut event_reader: MessageReader<ServerEvent>,
+        draining: Option<Res<Draining>>,
+        mut kicks: MessageWriter<KickClient>,
+    ) {
+        let Some(draining) = draining else {
+            event_reader.clear();
+            return;
+        };
+
+        for event in event_reader.read() {
+            if let ServerEvent::ClientConnected { client_id } = event {
+                kicks.write(KickClient {
+                    client_id: *client_id,
+                    reason: draining.reason.clone(),
+                });
+            }
+        }
+    }
+
+    fn finish_shutdown(
+        draining: Option<Res<Draining>>,
+        time: Res<Time>,
+        mut server: ResMut<RenetServer>,
+        transport: Option<ResMut<NetcodeServerTransport>>,
+        mut exit: MessageWriter<AppExit>,
+    ) {
+        let Some(draining) = draining else {
+            return;
+        };
+
+        if time.elapsed() < draining.until {
+            return;
+        }
+
+        match transport {
+            Some(mut transport) => transport.disconnect_all(&mut server),
+            None => server.disconnect_all(),
+        }
+
+        info!("Server stopped.");
+
+        exit.write(AppExit::Success);
+    }
+}
+
+fn save_world(path: &Path, sessions: &Sessions, players: &Query<&Transform, With<ServerPlayer>>) {
+    let snapshot: Vec<SavedPlayer> = sessions
+        .0
+        .iter()
+        .map(|session| SavedPlayer {
+            username: session.username.clone(),
+            role: session.role,
+            translation: players
+                .get(session.entity)
+                .map(|transform| transform.translation.to_array())
+                .unwrap_or_default(),
+        })
+        .collect();
+
+    let file = match File::create(path) {
+        Ok(file) => file,
+        Err(error) => {
+            warn!("Could not create save file {}: {}", path.display(), error);
+            return;
+        }
+    };
+
+    match bincode::encode_into_std_write(
+        &snapshot,
+        &mut BufWriter::new(file),
+        bincode::config::standard(),
+    ) {
+        Ok(_) => info!("Saved world state to {}", path.display()),
+        Err(error) => warn!("Could not save world state: {}", error),
+    }
+}
+
+impl Plugin for ShutdownPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SignalFlag::default());
+        app.add_message::<RequestShutdown>();
+        app.add_systems(Startup, (Self::install_signal_handler, Self::load_world));
+        app.add_systems(
+            Update,
+            (
+                Self::watch_signal,
+                Self::begin_shutdown,
+                Self::turn_away,
+                Self::finish_shutdown,
+            )
+                .chain(),
+        );
+    }
+}
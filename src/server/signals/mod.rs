@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+
+use crate::{
+    common::{network::ServerMessage, signals::valid_signal_id},
+    server::{
+        encryption::SecureChannel,
+        session::{ServerPlayer, Sessions},
+        tether::Tether,
+    },
+};
+
+/// Relays quick signals to both players, placed where the sender stands. The partner only gets
+/// them while the [`Tether`] holds.
+///
+/// The server does not know the signal definitions. Clients ignore ids they do not have.
+pub struct SignalsPlugin;
+
+#[derive(Message, Debug, Clone)]
+pub struct ClientSignal {
+    pub client_id: u64,
+    pub id: String,
+}
+
+impl SignalsPlugin {
+    fn relay_signals(
+        mut signals: MessageReader<ClientSignal>,
+        sessions: Res<Sessions>,
+        tether: Res<Tether>,
+        players: Query<&Transform, With<ServerPlayer>>,
+        mut channel: SecureChannel,
+    ) {
+        for signal in signals.read() {
+            if !valid_signal_id(&signal.id) {
+                continue;
+            }
+
+            let Some(session) = sessions.by_client(signal.client_id) else {
+                continue;
+            };
+
+            let Ok(from) = players.get(session.entity) else {
+                continue;
+            };
+
+            let message = ServerMessage::Signal {
+                from: session.role,
+                id: signal.id.clone(),
+                translation: from.translation.to_array(),
+            };
+
+            channel.send(signal.client_id, &message);
+
+            if tether.is_lost() {
+                continue;
+            }
+
+            if let Some(partner_id) = sessions
+                .partner_of(session.role)
+                .and_then(|partner| partner.client_id)
+            {
+                channel.send(partner_id, &message);
+            }
+        }
+    }
+}
+
+impl Plugin for SignalsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<ClientSignal>();
+        app.add_systems(Update, Self::relay_signals);
+    }
+}
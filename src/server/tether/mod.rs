@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    common::{
+        network::ServerMessage,
+        tether::{CommState, clarity},
+    },
+    server::{
+        encryption::SecureChannel,
+        session::{ServerPlayer, Sessions},
+    },
+};
+
+/// Keeps track of how well the two players can reach each other and tells them when it changes.
+///
+/// Chat, voice and signals read [`Tether`] to decide what still gets through.
+pub struct TetherPlugin;
+
+/// Link between the two players, recomputed every tick. Clear while there is only one player.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct Tether {
+    pub state: CommState,
+    /// See [`clarity`].
+    pub clarity: f32,
+    pub distance: f32,
+}
+
+impl Default for Tether {
+    fn default() -> Self {
+        Self {
+            state: CommState::Clear,
+            clarity: 1.0,
+            distance: 0.0,
+        }
+    }
+}
+
+impl Tether {
+    pub fn is_lost(&self) -> bool {
+        self.state == CommState::Lost
+    }
+}
+
+impl TetherPlugin {
+    fn measure(
+        sessions: Res<Sessions>,
+        players: Query<&Transform, With<ServerPlayer>>,
+        mut tether: ResMut<Tether>,
+    ) {
+        let [first, second] = sessions.0.as_slice() else {
+            *tether = Tether::default();
+            return;
+        };
+
+        let (Ok(first), Ok(second)) = (players.get(first.entity), players.get(second.entity))
+        else {
+            return;
+        };
+
+        let distance = first.translation.distance(second.translation);
+        let state = tether.state.next(distance);
+
+        if state != tether.state {
+            info!(
+                "Comm link: {:?} -> {:?} at {:.1} m",
+                tether.state, state, distance
+            );
+        }
+
+        *tether = Tether {
+            state,
+            clarity: clarity(distance),
+            distance,
+        };
+    }
+
+    /// Sends each connected player the link state and clarity in tenths, whenever either changes
+    /// or the player has not been told yet.
+    fn announce(
+        sessions: Res<Sessions>,
+        tether: Res<Tether>,
+        mut channel: SecureChannel,
+        mut told: Local<HashMap<u64, (CommState, u8)>>,
+    ) {
+        let tenths = (tether.clarity * 10.0).round() as u8;
+        let current = (tether.state, tenths);
+
+        told.retain(|client_id, _| sessions.by_client(*client_id).is_some());
+
+        for client_id in sessions.0.iter().filter_map(|session| session.client_id) {
+            if told.get(&client_id) == Some(&current) {
+                continue;
+            }
+
+            channel.send(
+                client_id,
+                &ServerMessage::Comm {
+                    state: tether.state,
+                    clarity: tenths as f32 / 10.0,
+                },
+            );
+            told.insert(client_id, current);
+        }
+    }
+}
+
+impl Plugin for TetherPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Tether::default());
+        app.add_systems(Update, (Self::measure, Self::announce).chain());
+    }
+}
@@ -0,0 +1,78 @@
+use bevy::prelude::*;
+
+use crate::{
+    common::{
+        network::{ServerMessage, VOICE_CHANNEL},
+        voice::{MAX_FRAME_BYTES, VoiceFrame},
+    },
+    server::{
+        encryption::SecureChannel,
+        session::{ServerPlayer, Sessions},
+        tether::Tether,
+    },
+};
+
+/// Relays voice frames to the partner, quieter with distance and not at all once the [`Tether`]
+/// is lost.
+pub struct VoicePlugin;
+
+#[derive(Message, Debug, Clone)]
+pub struct ClientVoice {
+    pub client_id: u64,
+    pub frame: VoiceFrame,
+}
+
+impl VoicePlugin {
+    fn relay_voice(
+        mut voices: MessageReader<ClientVoice>,
+        sessions: Res<Sessions>,
+        tether: Res<Tether>,
+        players: Query<&Transform, With<ServerPlayer>>,
+        mut channel: SecureChannel,
+    ) {
+        for voice in voices.read() {
+            if voice.frame.data.len() > MAX_FRAME_BYTES {
+                continue;
+            }
+
+            let Some(session) = sessions.by_client(voice.client_id) else {
+                continue;
+            };
+
+            let Some(partner_id) = sessions
+                .partner_of(session.role)
+                .and_then(|partner| partner.client_id)
+            else {
+                continue;
+            };
+
+            let Ok(from) = players.get(session.entity) else {
+                continue;
+            };
+
+            if tether.is_lost() {
+                channel.metrics.voice_out_of_range += 1;
+                continue;
+            }
+
+            channel.metrics.voice_relayed += 1;
+
+            channel.send_on(
+                partner_id,
+                VOICE_CHANNEL,
+                &ServerMessage::Voice {
+                    frame: voice.frame.clone(),
+                    gain: tether.clarity,
+                    translation: from.translation.to_array(),
+                },
+            );
+        }
+    }
+}
+
+impl Plugin for VoicePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<ClientVoice>();
+        app.add_systems(Update, Self::relay_voice);
+    }
+}
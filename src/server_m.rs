@@ -1,14 +1,48 @@
-use std::time::Duration;
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
 
 use bevy::{app::ScheduleRunnerPlugin, log::LogPlugin, prelude::*};
 use bevy_renet::{RenetServerPlugin, netcode::NetcodeServerPlugin};
+use rand::TryRngCore;
 
-use crate::server::network::NetworkPlugin;
-
-mod common;
-pub mod server;
+use absent_chroma::{
+    common::world::WorldSeed,
+    server::{
+        accounts::AccountsPlugin,
+        chat::ChatPlugin,
+        config::ServerSettings,
+        console::ConsolePlugin,
+        discovery::DiscoveryPlugin,
+        listing::ListingPlugin,
+        metrics::MetricsPlugin,
+        netlog::NetLogPlugin,
+        network::{NetworkPlugin, TransportPlugin},
+        profiles::ProfilesPlugin,
+        replay::{self, RecordingPlugin},
+        session::SessionPlugin,
+        shutdown::ShutdownPlugin,
+        signals::SignalsPlugin,
+        tether::TetherPlugin,
+        voice::VoicePlugin,
+    },
+};
 
 fn main() {
+    let mut args = env::args().skip(1);
+
+    if let Some("--replay") = args.next().as_deref() {
+        let Some(path) = args.next().map(PathBuf::from) else {
+            eprintln!("Usage: server --replay <recording>");
+            process::exit(2);
+        };
+
+        replay(&path);
+    }
+
     let mut app = App::new();
 
     app.add_plugins(
@@ -19,10 +53,57 @@ fn main() {
 
     app.add_plugins(LogPlugin::default());
 
+    let settings = ServerSettings::from_env();
+
+    let seed = settings
+        .seed
+        .unwrap_or_else(|| rand::rngs::OsRng.try_next_u64().unwrap_or_default());
+
+    app.insert_resource(WorldSeed(seed));
+    app.insert_resource(settings);
+
     app.add_plugins(RenetServerPlugin);
     app.add_plugins(NetcodeServerPlugin);
 
     app.add_plugins(NetworkPlugin);
+    app.add_plugins(TransportPlugin);
+    app.add_plugins(AccountsPlugin);
+    app.add_plugins(ProfilesPlugin);
+    app.add_plugins(DiscoveryPlugin);
+    app.add_plugins(ListingPlugin);
+    app.add_plugins(SessionPlugin);
+    app.add_plugins(TetherPlugin);
+    app.add_plugins(ChatPlugin);
+    app.add_plugins(VoicePlugin);
+    app.add_plugins(SignalsPlugin);
+    app.add_plugins(ShutdownPlugin);
+    app.add_plugins(ConsolePlugin);
+    app.add_plugins(MetricsPlugin);
+    app.add_plugins(NetLogPlugin);
+    app.add_plugins(RecordingPlugin);
 
     app.run();
 }
+
+fn replay(path: &Path) -> ! {
+    match replay::replay(path) {
+        Ok(report) => {
+            info!(
+                "Replayed {} frames, {} inputs, {} matching messages.",
+                report.frames, report.inputs, report.matched
+            );
+
+            match report.divergence {
+                Some(divergence) => {
+                    error!("Replay diverged => {}", divergence);
+                    process::exit(1);
+                }
+                None => process::exit(0),
+            }
+        }
+        Err(error) => {
+            eprintln!("Could not replay {}: {}", path.display(), error);
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use std::net::IpAddr;
+
+use absent_chroma::server::access::{AccessEntry, AccessList};
+
+#[test]
+fn username_bans_ignore_case() {
+    let mut access = AccessList::default();
+
+    let entry = AccessEntry::parse("user", "Gray").expect("valid entry");
+    assert_eq!(entry, AccessEntry::Username("gray".to_string()));
+    assert!(access.ban(entry));
+
+    for username in ["gray", "Gray", "GRAY"] {
+        assert!(
+            access.check(username, 1, None).is_err(),
+            "{} got in",
+            username
+        );
+    }
+    assert!(access.check("note", 1, None).is_ok());
+}
+
+#[test]
+fn ids_and_addresses_are_banned_too() {
+    let mut access = AccessList::default();
+
+    access.ban(AccessEntry::parse("id", "7").expect("valid entry"));
+    access.ban(AccessEntry::parse("ip", "10.0.0.2").expect("valid entry"));
+
+    let ip: IpAddr = "10.0.0.2".parse().expect("valid address");
+
+    assert!(access.check("gray", 7, None).is_err());
+    assert!(access.check("gray", 8, Some(ip)).is_err());
+    assert!(access.check("gray", 8, None).is_ok());
+    assert!(AccessEntry::parse("id", "seven").is_err());
+}
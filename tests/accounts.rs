@@ -0,0 +1,144 @@
+mod harness;
+
+use std::{env, fs, process};
+
+use absent_chroma::{
+    common::{
+        account::validate_username,
+        network::{ClientMessage, ServerMessage},
+    },
+    server::accounts::{AccountStore, LoggedInUsers},
+};
+
+use crate::harness::{Harness, PASSWORD};
+
+#[test]
+fn accounts_survive_a_reload() {
+    let path = env::temp_dir().join(format!("absent_chroma_store_{}.txt", process::id()));
+    let _ = fs::remove_file(&path);
+
+    let mut store = AccountStore::load(&path);
+    assert!(store.is_empty());
+
+    assert_eq!(store.register("Gray", "password1"), Ok("Gray".to_string()));
+    assert!(store.register("gray", "password2").is_err());
+    assert_eq!(store.register("gray", "password1"), Ok("Gray".to_string()));
+
+    let store = AccountStore::load(&path);
+    assert_eq!(store.len(), 1);
+    assert_eq!(store.verify("GRAY", "password1"), Ok("Gray".to_string()));
+    assert!(store.verify("Gray", "password2").is_err());
+    assert!(store.verify("note", "password1").is_err());
+    assert_eq!(
+        store.verify("note", "password1"),
+        store.verify("Gray", "password2"),
+        "unknown usernames are refused like wrong passwords"
+    );
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn reserved_and_malformed_usernames_are_refused() {
+    for username in ["Anon", "unknown", "ADMIN", "ab", "with space", "semi;colon"] {
+        assert!(
+            validate_username(username).is_err(),
+            "{} was accepted",
+            username
+        );
+    }
+
+    assert!(validate_username("gray").is_ok());
+
+    let mut harness = Harness::new();
+    let index = harness.connect("Server");
+
+    assert!(harness.run_until(200, |harness| {
+        harness.has_received(index, |message| {
+            matches!(message, ServerMessage::LoginRejected { .. })
+        })
+    }));
+}
+
+#[test]
+fn wrong_password_is_rejected() {
+    let mut harness = Harness::new();
+
+    let gray = harness.join("gray");
+    harness.disconnect(gray);
+
+    let login = ClientMessage::Login {
+        username: "gray".to_string(),
+        password: format!("not {}", PASSWORD),
+    };
+    let index = harness.connect_with_login("gray", None, Some(login));
+
+    assert!(harness.run_until(200, |harness| {
+        harness.has_received(index, |message| {
+            matches!(message, ServerMessage::LoginRejected { .. })
+        })
+    }));
+    assert!(!harness.has_received(index, |message| {
+        matches!(message, ServerMessage::Welcome { .. })
+    }));
+}
+
+#[test]
+fn hello_before_login_is_dropped() {
+    let mut harness = Harness::new();
+
+    let index = harness.connect_with_login("gray", None, None);
+
+    assert!(harness.run_until(200, |harness| {
+        harness.has_received(index, |message| {
+            *message == ServerMessage::HandshakeComplete
+        })
+    }));
+
+    harness.send(index, &ClientMessage::Hello { resume_token: None });
+
+    harness.run_until(50, |_| false);
+
+    assert!(!harness.has_received(index, |message| {
+        matches!(message, ServerMessage::Welcome { .. })
+    }));
+}
+
+#[test]
+fn plaintext_logins_on_the_handshake_channel_are_dropped() {
+    let mut harness = Harness::new();
+
+    let index = harness.connect_with_login("gray", None, None);
+
+    // Before the handshake there is no session key to answer a Ping with.
+    let ping = bincode::encode_to_vec(&ClientMessage::Ping, bincode::config::standard())
+        .expect("Error encoding client message.");
+    harness.send_raw(index, 3, ping);
+
+    assert!(harness.run_until(200, |harness| {
+        harness.has_received(index, |message| {
+            *message == ServerMessage::HandshakeComplete
+        })
+    }));
+
+    let register = ClientMessage::Register {
+        username: "gray".to_string(),
+        password: PASSWORD.to_string(),
+    };
+    let register = bincode::encode_to_vec(&register, bincode::config::standard())
+        .expect("Error encoding client message.");
+    harness.send_raw(index, 3, register);
+
+    harness.run_until(50, |_| false);
+
+    assert!(!harness.has_received(index, |message| {
+        matches!(message, ServerMessage::LoginAccepted { .. })
+    }));
+    assert!(
+        !harness
+            .server
+            .world()
+            .resource::<LoggedInUsers>()
+            .contains(harness.clients[index].client_id)
+    );
+}
@@ -0,0 +1,127 @@
+mod harness;
+
+use absent_chroma::{
+    common::{
+        chat::{MAX_CHAT_CHARS, STATIC_CHAR, degrade, sanitize},
+        network::{ClientMessage, ServerMessage},
+        tether::{CLEAR_RANGE, LOST_RANGE},
+    },
+    server::metrics::ServerMetrics,
+};
+use rand::{SeedableRng, rngs::StdRng};
+
+use crate::harness::Harness;
+
+fn chat(text: &str) -> ClientMessage {
+    ClientMessage::Chat {
+        text: text.to_string(),
+    }
+}
+
+/// What Note reads of a long message from Gray, said from halfway to losing the link.
+fn degraded_chat() -> String {
+    let mut harness = Harness::new();
+
+    let gray = harness.join("gray");
+    let note = harness.join("note");
+
+    harness.move_to(note, [(CLEAR_RANGE + LOST_RANGE) / 2.0, 0.0, 0.0]);
+
+    harness.send(
+        gray,
+        &chat("the beacon is past the ridge, follow the river north"),
+    );
+
+    assert!(harness.run_until(200, |harness| {
+        harness.has_received(note, |message| {
+            matches!(message, ServerMessage::Chat { .. })
+        })
+    }));
+
+    harness
+        .received(note)
+        .iter()
+        .find_map(|message| match message {
+            ServerMessage::Chat { text, .. } => Some(text.clone()),
+            _ => None,
+        })
+        .expect("Note got no chat.")
+}
+
+#[test]
+fn degrading_keeps_the_shape_of_the_message() {
+    let mut rng = StdRng::seed_from_u64(7);
+
+    assert_eq!(degrade("come here", 1.0, &mut rng), "come here");
+
+    let lost = degrade("come here", 0.0, &mut rng);
+    assert_eq!(lost, format!("{0}{0}{0}{0} {0}{0}{0}{0}", STATIC_CHAR));
+
+    let partial = degrade("come here", 0.5, &mut rng);
+    assert_eq!(partial.chars().count(), "come here".chars().count());
+    assert_eq!(partial.chars().nth(4), Some(' '));
+}
+
+#[test]
+fn sanitizing_trims_and_limits_messages() {
+    assert_eq!(sanitize("  hi\u{7}  "), Some("hi".to_string()));
+    assert_eq!(sanitize("   "), None);
+    assert!(sanitize(&"a".repeat(MAX_CHAT_CHARS)).is_some());
+    assert!(sanitize(&"a".repeat(MAX_CHAT_CHARS + 1)).is_none());
+}
+
+#[test]
+fn nearby_partner_gets_chat_word_for_word() {
+    let mut harness = Harness::new();
+
+    let gray = harness.join("gray");
+    let note = harness.join("note");
+
+    harness.send(gray, &chat("beacon is north"));
+
+    assert!(harness.run_until(200, |harness| {
+        harness.has_received(note, |message| {
+            matches!(
+                message,
+                ServerMessage::Chat { text, clarity, .. }
+                    if text == "beacon is north" && *clarity == 1.0
+            )
+        })
+    }));
+    assert!(!harness.has_received(gray, |message| {
+        matches!(message, ServerMessage::Chat { .. })
+    }));
+}
+
+#[test]
+fn distant_partner_gets_nothing() {
+    let mut harness = Harness::new();
+
+    let gray = harness.join("gray");
+    let note = harness.join("note");
+
+    harness.move_to(note, [LOST_RANGE + 5.0, 0.0, 0.0]);
+
+    harness.send(gray, &chat("anyone there?"));
+    harness.run_until(50, |_| false);
+
+    assert!(!harness.has_received(note, |message| {
+        matches!(message, ServerMessage::Chat { .. })
+    }));
+    assert_eq!(
+        harness
+            .server
+            .world()
+            .resource::<ServerMetrics>()
+            .chat_out_of_range,
+        1
+    );
+}
+
+#[test]
+fn degraded_chat_is_the_same_in_the_same_world() {
+    let first = degraded_chat();
+
+    assert!(first.contains(STATIC_CHAR));
+    assert_eq!(first, degraded_chat());
+}
@@ -0,0 +1,35 @@
+use absent_chroma::common::discovery::{DiscoveryMessage, PROBE_BYTES, ServerAnnouncement};
+
+#[test]
+fn announcements_round_trip_and_fit_in_a_probe() {
+    let announcement = DiscoveryMessage::Announcement(ServerAnnouncement {
+        name: "x".repeat(500),
+        version: "0.1.0".to_string(),
+        port: 42069,
+        rooms: 1,
+        free_slots: 2,
+    });
+
+    let packet = announcement.encode();
+    assert!(packet.len() <= PROBE_BYTES);
+
+    let Some(DiscoveryMessage::Announcement(decoded)) = DiscoveryMessage::decode(&packet) else {
+        panic!("Announcement did not decode.");
+    };
+    assert_eq!(decoded.port, 42069);
+    assert_eq!(decoded.free_slots, 2);
+    assert!(decoded.name.starts_with("xxx"));
+}
+
+#[test]
+fn short_probes_and_stray_packets_are_ignored() {
+    let probe = DiscoveryMessage::Probe.encode();
+    assert_eq!(probe.len(), PROBE_BYTES);
+    assert_eq!(
+        DiscoveryMessage::decode(&probe),
+        Some(DiscoveryMessage::Probe)
+    );
+
+    assert_eq!(DiscoveryMessage::decode(&probe[..16]), None);
+    assert_eq!(DiscoveryMessage::decode(b"hello"), None);
+}
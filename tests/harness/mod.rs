@@ -0,0 +1,472 @@
+// Shared by several test crates, each of which only uses part of it.
+#![allow(dead_code)]
+
+use std::{
+    env, fs, process,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::Duration,
+};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_renet::{
+    RenetClientPlugin, RenetServerPlugin,
+    renet::{DefaultChannel, RenetClient, RenetServer},
+};
+use fips203::{
+    ml_kem_512::EncapsKey,
+    traits::{Encaps, SerDes},
+};
+use zeroize::Zeroizing;
+
+use absent_chroma::{
+    client::{
+        ClientCorePlugin,
+        network::login::{Credentials, UserLogin},
+    },
+    common::{
+        encryption::{Direction, ReceivedNonces, decrypt, encrypt},
+        network::{
+            ClientMessage, ConnectedUsers, NETWORK_CHANNELS, ResumeToken, ServerMessage, UserData,
+            connection_config,
+        },
+        world::WorldSeed,
+    },
+    server::{
+        accounts::{AccountsPlugin, PendingLogins},
+        chat::ChatPlugin,
+        config::ServerSettings,
+        metrics::MetricsPlugin,
+        netlog::NetLogPlugin,
+        network::NetworkPlugin,
+        profiles::ProfilesPlugin,
+        replay::RecordingPlugin,
+        session::SessionPlugin,
+        signals::SignalsPlugin,
+        tether::TetherPlugin,
+        voice::VoicePlugin,
+    },
+};
+
+/// Time both apps advance by on every step, so runs do not depend on the wall clock.
+pub const STEP: Duration = Duration::from_millis(16);
+
+/// Password every harness client registers with.
+pub const PASSWORD: &str = "correct horse";
+
+const RELIABLE: u8 = DefaultChannel::ReliableOrdered as u8;
+
+static HARNESSES: AtomicUsize = AtomicUsize::new(0);
+
+/// What a headless test client has seen from the server.
+#[derive(Resource, Default)]
+pub struct ClientState {
+    pub ssk: Option<Zeroizing<[u8; 32]>>,
+    pub nonce: [u8; 12],
+    pub received_nonces: ReceivedNonces,
+    pub resume_token: Option<ResumeToken>,
+    /// Login or Register sent once the handshake completes. Hello follows when it is accepted.
+    pub login: Option<ClientMessage>,
+    pub received: Vec<ServerMessage>,
+    pub undecryptable: usize,
+}
+
+/// Client side of the protocol with nothing but the network: answers the KEM handshake, logs
+/// in, says Hello and keeps every server message it receives.
+fn receive_server_messages(mut client: ResMut<RenetClient>, mut state: ResMut<ClientState>) {
+    while let Some(message) = client.receive_message(3) {
+        let Ok((server_message, _)) =
+            bincode::decode_from_slice::<ServerMessage, _>(&message, bincode::config::standard())
+        else {
+            continue;
+        };
+
+        if let ServerMessage::KEMEncapsKey(e_key) = &server_message {
+            let e_key = EncapsKey::try_from_bytes(*e_key).expect("Encaps key parse failed.");
+            let (ssk, ct) = e_key.try_encaps().expect("Failed ssk generation.");
+
+            let reply = ClientMessage::KEMCipherText(ct.into_bytes());
+            let reply = bincode::encode_to_vec(reply, bincode::config::standard())
+                .expect("Error encoding ciphertext.");
+
+            client.send_message(3, reply);
+
+            state.ssk = Some(ssk.into_bytes().into());
+            state.nonce = [0u8; 12];
+            state.received_nonces = ReceivedNonces::default();
+        }
+
+        state.received.push(server_message);
+    }
+
+    for channel_id in NETWORK_CHANNELS {
+        if channel_id == 3 {
+            continue;
+        }
+
+        while let Some(message) = client.receive_message(channel_id) {
+            let plaintext = state
+                .ssk
+                .as_ref()
+                .and_then(|ssk| decrypt(ssk, Direction::ServerToClient, &message));
+
+            let Some(plaintext) = plaintext else {
+                state.undecryptable += 1;
+                continue;
+            };
+
+            if !state.received_nonces.advance(channel_id, &message) {
+                state.undecryptable += 1;
+                continue;
+            }
+
+            let (server_message, _) = bincode::decode_from_slice::<ServerMessage, _>(
+                &plaintext,
+                bincode::config::standard(),
+            )
+            .expect("Server sent a message the client cannot decode.");
+
+            match &server_message {
+                ServerMessage::HandshakeComplete => {
+                    if let Some(login) = state.login.clone() {
+                        send_encrypted(&mut client, &mut state, RELIABLE, &login);
+                    }
+                }
+                ServerMessage::LoginAccepted { .. } => {
+                    let hello = ClientMessage::Hello {
+                        resume_token: state.resume_token,
+                    };
+                    send_encrypted(&mut client, &mut state, RELIABLE, &hello);
+                }
+                ServerMessage::Welcome { resume_token, .. } => {
+                    state.resume_token = Some(*resume_token);
+                }
+                _ => {}
+            }
+
+            state.received.push(server_message);
+        }
+    }
+}
+
+fn send_encrypted(
+    client: &mut RenetClient,
+    state: &mut ClientState,
+    channel_id: u8,
+    message: &ClientMessage,
+) {
+    let ssk = **state.ssk.as_ref().expect("Handshake has not finished.");
+
+    let input = bincode::encode_to_vec(message, bincode::config::standard())
+        .expect("Error encoding client message.");
+
+    let output = encrypt(&ssk, &mut state.nonce, Direction::ClientToServer, &input);
+
+    client.send_message(channel_id, output);
+}
+
+pub struct TestClient {
+    pub client_id: u64,
+    pub app: App,
+    pub connected: bool,
+}
+
+/// A headless server and any number of headless clients, joined by an in-memory transport.
+pub struct Harness {
+    pub server: App,
+    pub clients: Vec<TestClient>,
+    next_client_id: u64,
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        let settings = self.server.world().resource::<ServerSettings>();
+
+        let _ = fs::remove_file(&settings.accounts);
+        let _ = fs::remove_file(&settings.profiles);
+
+        if let Some(path) = &settings.record_path {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        let mut server = App::new();
+
+        server.add_plugins(MinimalPlugins);
+        server.add_plugins(RenetServerPlugin);
+        server.insert_resource(TimeUpdateStrategy::ManualDuration(STEP));
+        let harness = HARNESSES.fetch_add(1, Ordering::Relaxed);
+        server.insert_resource(ServerSettings {
+            accounts: env::temp_dir().join(format!(
+                "absent_chroma_accounts_{}_{}.txt",
+                process::id(),
+                harness
+            )),
+            profiles: env::temp_dir().join(format!(
+                "absent_chroma_profiles_{}_{}.txt",
+                process::id(),
+                harness
+            )),
+            record_path: Some(env::temp_dir().join(format!(
+                "absent_chroma_recording_{}_{}.bin",
+                process::id(),
+                harness
+            ))),
+            ..default()
+        });
+        server.insert_resource(WorldSeed(42));
+
+        server.add_plugins(NetworkPlugin);
+        server.add_plugins(AccountsPlugin);
+        server.add_plugins(ProfilesPlugin);
+        server.add_plugins(SessionPlugin);
+        server.add_plugins(TetherPlugin);
+        server.add_plugins(ChatPlugin);
+        server.add_plugins(VoicePlugin);
+        server.add_plugins(SignalsPlugin);
+        server.add_plugins(MetricsPlugin);
+        server.add_plugins(NetLogPlugin);
+        server.add_plugins(RecordingPlugin);
+
+        server.finish();
+        server.cleanup();
+        server.update();
+
+        Self {
+            server,
+            clients: vec![],
+            next_client_id: 1,
+        }
+    }
+
+    /// Connects a new client and returns its index. Nothing is exchanged until the next step.
+    pub fn connect(&mut self, username: &str) -> usize {
+        self.connect_with_token(username, None)
+    }
+
+    pub fn connect_with_token(
+        &mut self,
+        username: &str,
+        resume_token: Option<ResumeToken>,
+    ) -> usize {
+        let register = ClientMessage::Register {
+            username: username.to_string(),
+            password: PASSWORD.to_string(),
+        };
+
+        self.connect_with_login(username, resume_token, Some(register))
+    }
+
+    /// Connects a client that sends `login` after the handshake, or never logs in without one.
+    pub fn connect_with_login(
+        &mut self,
+        username: &str,
+        resume_token: Option<ResumeToken>,
+        login: Option<ClientMessage>,
+    ) -> usize {
+        let mut app = App::new();
+
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(RenetClientPlugin);
+        app.insert_resource(ClientState {
+            resume_token,
+            login,
+            ..default()
+        });
+        app.add_systems(Update, receive_server_messages);
+
+        self.add_client(username, app)
+    }
+
+    /// Connects the game's own headless client instead of the protocol-only test client.
+    /// [`Harness::received`] is not available for it, tests look at its resources instead.
+    pub fn connect_core(&mut self, username: &str) -> usize {
+        let mut app = App::new();
+
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(ClientCorePlugin);
+        app.insert_resource(UserLogin::LoggingIn(Credentials {
+            username: username.to_string(),
+            password: Zeroizing::new(PASSWORD.to_string()),
+            register: true,
+        }));
+
+        self.add_client(username, app)
+    }
+
+    fn add_client(&mut self, username: &str, mut app: App) -> usize {
+        let client_id = self.next_client_id;
+        self.next_client_id += 1;
+
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(STEP));
+
+        let mut client = RenetClient::new(connection_config());
+        client.set_connected();
+        app.insert_resource(client);
+
+        app.finish();
+        app.cleanup();
+
+        // The netcode transport would carry the username in the connect token.
+        self.server
+            .world_mut()
+            .resource_mut::<ConnectedUsers>()
+            .0
+            .insert(client_id, UserData::from_str(username));
+        self.server
+            .world_mut()
+            .resource_mut::<RenetServer>()
+            .add_connection(client_id);
+
+        self.clients.push(TestClient {
+            client_id,
+            app,
+            connected: true,
+        });
+
+        self.clients.len() - 1
+    }
+
+    pub fn disconnect(&mut self, index: usize) {
+        let client = &mut self.clients[index];
+
+        client.connected = false;
+        client
+            .app
+            .world_mut()
+            .resource_mut::<RenetClient>()
+            .disconnect();
+
+        self.server
+            .world_mut()
+            .resource_mut::<RenetServer>()
+            .disconnect(client.client_id);
+    }
+
+    /// Updates every app once and delivers the packets they produced.
+    pub fn step(&mut self) {
+        for client in self.clients.iter_mut().filter(|client| client.connected) {
+            client.app.update();
+
+            let packets = client
+                .app
+                .world_mut()
+                .resource_mut::<RenetClient>()
+                .get_packets_to_send();
+
+            let mut server = self.server.world_mut().resource_mut::<RenetServer>();
+
+            for packet in packets {
+                let _ = server.process_packet_from(&packet, client.client_id);
+            }
+        }
+
+        // Password checks run on the task pool, wait for them so runs do not depend on the wall
+        // clock.
+        while !self
+            .server
+            .world()
+            .resource::<PendingLogins>()
+            .all_finished()
+        {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        self.server.update();
+
+        for client in self.clients.iter_mut().filter(|client| client.connected) {
+            let Ok(packets) = self
+                .server
+                .world_mut()
+                .resource_mut::<RenetServer>()
+                .get_packets_to_send(client.client_id)
+            else {
+                continue;
+            };
+
+            let mut renet_client = client.app.world_mut().resource_mut::<RenetClient>();
+
+            for packet in packets {
+                renet_client.process_packet(&packet);
+            }
+        }
+    }
+
+    /// Steps until `done` holds, giving up after `max_steps`.
+    pub fn run_until(&mut self, max_steps: usize, done: impl Fn(&Harness) -> bool) -> bool {
+        for _ in 0..max_steps {
+            if done(self) {
+                return true;
+            }
+
+            self.step();
+        }
+
+        done(self)
+    }
+
+    pub fn client_state(&self, index: usize) -> &ClientState {
+        self.clients[index].app.world().resource::<ClientState>()
+    }
+
+    pub fn received(&self, index: usize) -> &[ServerMessage] {
+        &self.client_state(index).received
+    }
+
+    pub fn has_received(&self, index: usize, matches: impl Fn(&ServerMessage) -> bool) -> bool {
+        self.received(index).iter().any(matches)
+    }
+
+    /// Sends an encrypted message from a client that finished the handshake.
+    pub fn send(&mut self, index: usize, message: &ClientMessage) {
+        self.send_on(index, RELIABLE, message);
+    }
+
+    /// Sends an encrypted message on a specific channel.
+    pub fn send_on(&mut self, index: usize, channel_id: u8, message: &ClientMessage) {
+        let world = self.clients[index].app.world_mut();
+
+        world.resource_scope(|world, mut state: Mut<ClientState>| {
+            let mut client = world.resource_mut::<RenetClient>();
+            send_encrypted(&mut client, &mut state, channel_id, message);
+        });
+    }
+
+    /// Sends raw bytes on a channel, bypassing encryption.
+    pub fn send_raw(&mut self, index: usize, channel_id: u8, bytes: Vec<u8>) {
+        self.clients[index]
+            .app
+            .world_mut()
+            .resource_mut::<RenetClient>()
+            .send_message(channel_id, bytes);
+    }
+
+    /// Connects a client and steps until it has been welcomed into a session.
+    pub fn join(&mut self, username: &str) -> usize {
+        let index = self.connect(username);
+
+        let welcomed = self.run_until(200, |harness| {
+            harness.has_received(index, |message| {
+                matches!(message, ServerMessage::Welcome { .. })
+            })
+        });
+        assert!(welcomed, "{} was never welcomed", username);
+
+        index
+    }
+
+    /// Reports a new position for a client and steps until the server has caught up.
+    pub fn move_to(&mut self, index: usize, translation: [f32; 3]) {
+        self.send(index, &ClientMessage::PlayerState { translation });
+        self.run_until(10, |_| false);
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use absent_chroma::{
+    common::master::{MASTER_PACKET_BYTES, MasterMessage, SERVERS_PER_PAGE},
+    master::{MasterPlugin, MasterSettings, MasterSocket},
+};
+use bevy::prelude::*;
+
+fn master() -> (App, SocketAddr) {
+    let mut app = App::new();
+
+    app.add_plugins(MinimalPlugins);
+    app.insert_resource(MasterSettings {
+        bind: SocketAddr::from(([127, 0, 0, 1], 0)),
+    });
+    app.add_plugins(MasterPlugin);
+
+    app.finish();
+    app.cleanup();
+    app.update();
+
+    let addr = app
+        .world()
+        .resource::<MasterSocket>()
+        .0
+        .local_addr()
+        .expect("Master socket has no address.");
+
+    (app, addr)
+}
+
+fn socket() -> UdpSocket {
+    let socket = UdpSocket::bind("127.0.0.1:0").expect("Could not bind test socket.");
+    socket
+        .set_read_timeout(Some(Duration::from_millis(10)))
+        .expect("Could not set read timeout.");
+    socket
+}
+
+fn heartbeat(socket: &UdpSocket, master: SocketAddr, port: u16, name: &str) {
+    let message = MasterMessage::Heartbeat {
+        port,
+        name: name.to_string(),
+        version: "0.1.0".to_string(),
+        players: 1,
+        max_players: 2,
+    };
+
+    socket
+        .send_to(&message.encode(), master)
+        .expect("Could not send heartbeat.");
+}
+
+/// Requests one page of the list, stepping the master until it answers.
+fn list(app: &mut App, master: SocketAddr, page: u16) -> MasterMessage {
+    let client = socket();
+    let request = MasterMessage::ListRequest { page };
+
+    client
+        .send_to(&request.encode(), master)
+        .expect("Could not send list request.");
+
+    let mut buffer = [0u8; MASTER_PACKET_BYTES];
+
+    for _ in 0..100 {
+        app.update();
+
+        if let Ok((len, _)) = client.recv_from(&mut buffer) {
+            return MasterMessage::decode(&buffer[..len]).expect("Master sent garbage.");
+        }
+    }
+
+    panic!("Master never answered.");
+}
+
+#[test]
+fn registered_servers_are_listed() {
+    let (mut app, master) = master();
+    let server = socket();
+
+    heartbeat(&server, master, 42069, "gray's room");
+
+    let MasterMessage::ServerList { servers, more, .. } = list(&mut app, master, 0) else {
+        panic!("Expected a server list.");
+    };
+
+    assert!(!more);
+    assert_eq!(servers.len(), 1);
+    assert_eq!(servers[0].name, "gray's room");
+    assert_eq!(servers[0].addr, SocketAddr::from(([127, 0, 0, 1], 42069)));
+    assert_eq!((servers[0].players, servers[0].max_players), (1, 2));
+
+    server
+        .send_to(&MasterMessage::Unregister { port: 42069 }.encode(), master)
+        .expect("Could not unregister.");
+
+    let MasterMessage::ServerList { servers, .. } = list(&mut app, master, 0) else {
+        panic!("Expected a server list.");
+    };
+    assert!(servers.is_empty());
+}
+
+#[test]
+fn long_lists_are_paged() {
+    let (mut app, master) = master();
+    let server = socket();
+
+    for port in 0..SERVERS_PER_PAGE as u16 + 3 {
+        heartbeat(&server, master, 40000 + port, &"x".repeat(200));
+    }
+
+    let MasterMessage::ServerList { servers, more, .. } = list(&mut app, master, 0) else {
+        panic!("Expected a server list.");
+    };
+    assert!(more);
+    assert_eq!(servers.len(), SERVERS_PER_PAGE);
+
+    let MasterMessage::ServerList { servers, more, .. } = list(&mut app, master, 1) else {
+        panic!("Expected a server list.");
+    };
+    assert!(!more);
+    assert_eq!(servers.len(), 3);
+}
+
+#[test]
+fn short_list_requests_are_ignored() {
+    let mut packet = MasterMessage::ListRequest { page: 0 }.encode();
+    packet.truncate(32);
+
+    assert_eq!(MasterMessage::decode(&packet), None);
+}
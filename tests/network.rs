@@ -0,0 +1,357 @@
+mod harness;
+
+use absent_chroma::{
+    client::network::{
+        connection::{ConnectionState, disconnect},
+        encryption::{Nonce as ClientNonce, SskStore},
+        session::ClientSession,
+    },
+    common::{
+        encryption::{Direction, encrypt},
+        network::{ClientMessage, ConnectedUsers, PartnerStatus, Role, ServerMessage},
+    },
+    server::{
+        accounts::LoggedInUsers,
+        encryption::{DKeyStore, Nonce, SSKStore},
+        metrics::ServerMetrics,
+        session::{MatchState, ServerPlayer, Sessions},
+    },
+};
+use bevy::prelude::*;
+use bevy_renet::renet::{DefaultChannel, RenetClient};
+
+use crate::harness::{ClientState, Harness};
+
+#[test]
+fn handshake_assigns_both_roles() {
+    let mut harness = Harness::new();
+
+    let gray = harness.join("gray");
+    let note = harness.join("note");
+
+    let role = |index| {
+        harness
+            .received(index)
+            .iter()
+            .find_map(|message| match message {
+                ServerMessage::Welcome { role, resumed, .. } => Some((*role, *resumed)),
+                _ => None,
+            })
+            .expect("No Welcome received.")
+    };
+
+    let (gray_role, gray_resumed) = role(gray);
+    let (note_role, note_resumed) = role(note);
+
+    assert_ne!(gray_role, note_role);
+    assert!(!gray_resumed && !note_resumed);
+
+    assert!(harness.run_until(50, |harness| {
+        harness.has_received(gray, |message| {
+            *message == ServerMessage::PartnerStatus(PartnerStatus::Connected)
+        })
+    }));
+
+    let metrics = harness.server.world().resource::<ServerMetrics>();
+    assert_eq!(metrics.handshakes_completed, 2);
+    // One encapsulation key each on the handshake channel, encrypted replies after that.
+    assert_eq!(metrics.sent.get(&3).map(|(messages, _)| *messages), Some(2));
+    assert!(
+        metrics
+            .sent
+            .values()
+            .all(|(messages, bytes)| bytes > messages)
+    );
+    assert!(metrics.sent.len() > 1);
+    assert_eq!(
+        *harness.server.world().resource::<MatchState>(),
+        MatchState::InProgress
+    );
+}
+
+#[test]
+fn ping_is_answered_with_pong() {
+    let mut harness = Harness::new();
+    let client = harness.join("pinger");
+
+    harness.send(client, &ClientMessage::Ping);
+
+    assert!(harness.run_until(50, |harness| {
+        harness.has_received(client, |message| *message == ServerMessage::Pong)
+    }));
+}
+
+#[test]
+fn player_state_moves_the_server_player() {
+    let mut harness = Harness::new();
+    let client = harness.join("walker");
+
+    harness.send(
+        client,
+        &ClientMessage::PlayerState {
+            translation: [1.0, 2.0, 3.0],
+        },
+    );
+
+    let moved = harness.run_until(50, |harness| {
+        let world = harness.server.world();
+        let Some(session) = world.resource::<Sessions>().0.first() else {
+            return false;
+        };
+
+        world
+            .get::<Transform>(session.entity)
+            .is_some_and(|transform| transform.translation == Vec3::new(1.0, 2.0, 3.0))
+    });
+
+    assert!(moved);
+    assert!(
+        harness
+            .server
+            .world()
+            .get::<ServerPlayer>(harness.server.world().resource::<Sessions>().0[0].entity)
+            .is_some()
+    );
+}
+
+#[test]
+fn tampered_messages_are_dropped() {
+    let mut harness = Harness::new();
+    let client = harness.join("tamperer");
+
+    harness.send_raw(
+        client,
+        DefaultChannel::ReliableOrdered.into(),
+        vec![0u8; 64],
+    );
+
+    assert!(harness.run_until(50, |harness| {
+        harness
+            .server
+            .world()
+            .resource::<ServerMetrics>()
+            .decrypt_failures
+            == 1
+    }));
+}
+
+#[test]
+fn replayed_messages_are_dropped() {
+    let mut harness = Harness::new();
+    let client = harness.join("replayer");
+
+    let mut state = harness.clients[client]
+        .app
+        .world_mut()
+        .resource_mut::<ClientState>();
+    let ssk = **state.ssk.as_ref().expect("Handshake has not finished.");
+    let ping = bincode::encode_to_vec(&ClientMessage::Ping, bincode::config::standard())
+        .expect("Error encoding client message.");
+    let ciphertext = encrypt(&ssk, &mut state.nonce, Direction::ClientToServer, &ping);
+
+    let reliable = DefaultChannel::ReliableOrdered.into();
+    harness.send_raw(client, reliable, ciphertext.clone());
+    harness.send_raw(client, reliable, ciphertext);
+
+    assert!(harness.run_until(50, |harness| {
+        harness.has_received(client, |message| *message == ServerMessage::Pong)
+    }));
+
+    for _ in 0..10 {
+        harness.step();
+    }
+
+    let pongs = harness
+        .received(client)
+        .iter()
+        .filter(|message| **message == ServerMessage::Pong)
+        .count();
+
+    assert_eq!(pongs, 1);
+    assert_eq!(
+        harness
+            .server
+            .world()
+            .resource::<ServerMetrics>()
+            .decrypt_failures,
+        0
+    );
+}
+
+#[test]
+fn dropped_player_can_resume() {
+    let mut harness = Harness::new();
+
+    let gray = harness.join("gray");
+    let note = harness.join("note");
+
+    let token = harness
+        .client_state(gray)
+        .resume_token
+        .expect("No resume token.");
+
+    harness.disconnect(gray);
+
+    assert!(harness.run_until(50, |harness| {
+        harness.has_received(note, |message| {
+            matches!(
+                message,
+                ServerMessage::PartnerStatus(PartnerStatus::Reconnecting(_))
+            )
+        })
+    }));
+    assert_eq!(
+        *harness.server.world().resource::<MatchState>(),
+        MatchState::WaitingForTeammate
+    );
+
+    let rejoined = harness.connect_with_token("gray", Some(token));
+
+    assert!(harness.run_until(200, |harness| {
+        harness.has_received(rejoined, |message| {
+            matches!(message, ServerMessage::Welcome { resumed: true, .. })
+        })
+    }));
+    assert!(harness.run_until(50, |harness| {
+        *harness.server.world().resource::<MatchState>() == MatchState::InProgress
+    }));
+}
+
+#[test]
+fn disconnects_release_per_client_state() {
+    let mut harness = Harness::new();
+
+    let gray = harness.join("gray");
+    let note = harness.join("note");
+
+    harness.send(note, &ClientMessage::Ping);
+    harness.disconnect(gray);
+    harness.disconnect(note);
+
+    assert!(harness.run_until(50, |harness| {
+        let world = harness.server.world();
+
+        world.resource::<DKeyStore>().0.is_empty()
+            && world.resource::<SSKStore>().0.is_empty()
+            && world.resource::<Nonce>().0.is_empty()
+            && world.resource::<ConnectedUsers>().0.is_empty()
+            && world.resource::<LoggedInUsers>().0.is_empty()
+    }));
+}
+
+#[test]
+fn client_core_joins_headless() {
+    let mut harness = Harness::new();
+
+    let other = harness.join("gray");
+    let core = harness.connect_core("note");
+
+    assert!(harness.run_until(200, |harness| {
+        harness.clients[core]
+            .app
+            .world()
+            .resource::<ClientSession>()
+            .role
+            .is_some()
+    }));
+    assert!(harness.has_received(other, |message| {
+        *message == ServerMessage::PartnerStatus(PartnerStatus::Connected)
+    }));
+}
+
+fn connection_state(harness: &Harness, index: usize) -> ConnectionState {
+    harness.clients[index]
+        .app
+        .world()
+        .resource::<State<ConnectionState>>()
+        .get()
+        .clone()
+}
+
+#[test]
+fn lost_connection_keeps_resume_token() {
+    let mut harness = Harness::new();
+
+    harness.join("gray");
+    let core = harness.connect_core("note");
+
+    harness.clients[core]
+        .app
+        .world_mut()
+        .resource_mut::<NextState<ConnectionState>>()
+        .set(ConnectionState::Handshaking);
+
+    assert!(harness.run_until(200, |harness| {
+        connection_state(harness, core) == ConnectionState::Connected
+    }));
+
+    harness.disconnect(core);
+
+    let app = &mut harness.clients[core].app;
+    app.update();
+    app.update();
+
+    assert!(matches!(
+        connection_state(&harness, core),
+        ConnectionState::Disconnected(_)
+    ));
+
+    let world = harness.clients[core].app.world();
+    let session = world.resource::<ClientSession>();
+
+    assert!(session.role.is_none());
+    assert!(session.resume_token.is_some());
+    assert!(!world.contains_resource::<RenetClient>());
+    assert_eq!(*world.resource::<SskStore>().0, [0u8; 32]);
+}
+
+#[test]
+fn disconnecting_forgets_the_session() {
+    let mut harness = Harness::new();
+
+    harness.join("gray");
+    let core = harness.connect_core("note");
+
+    assert!(harness.run_until(200, |harness| {
+        harness.clients[core]
+            .app
+            .world()
+            .resource::<ClientSession>()
+            .role
+            .is_some()
+    }));
+
+    let app = &mut harness.clients[core].app;
+    disconnect(&mut app.world_mut().commands());
+    app.world_mut().flush();
+    app.update();
+
+    let world = app.world();
+
+    assert_eq!(
+        *world.resource::<State<ConnectionState>>().get(),
+        ConnectionState::Offline
+    );
+    assert!(!world.contains_resource::<RenetClient>());
+    assert!(world.resource::<ClientSession>().resume_token.is_none());
+    assert_eq!(*world.resource::<SskStore>().0, [0u8; 32]);
+    assert_eq!(world.resource::<ClientNonce>().0, [0u8; 12]);
+}
+
+#[test]
+fn resume_tokens_stay_out_of_captured_payloads() {
+    let welcome = ServerMessage::Welcome {
+        role: Role::Gray,
+        resume_token: [7; 32],
+        resumed: false,
+        translation: [0.0; 3],
+        seed: 0,
+    };
+    let hello = ClientMessage::Hello {
+        resume_token: Some([7; 32]),
+    };
+
+    assert!(welcome.is_private());
+    assert!(hello.is_private());
+    assert!(!ServerMessage::Pong.is_private());
+}
@@ -0,0 +1,108 @@
+mod harness;
+
+use std::{env, fs, process};
+
+use absent_chroma::{
+    common::network::{ClientMessage, Role, ServerMessage},
+    server::profiles::{MAX_ROLE_HISTORY, ProfileStore},
+};
+
+use crate::harness::Harness;
+
+#[test]
+fn profiles_survive_a_reload() {
+    let path = env::temp_dir().join(format!("absent_chroma_profile_store_{}.txt", process::id()));
+    let _ = fs::remove_file(&path);
+
+    let mut store = ProfileStore::load(&path);
+    assert!(store.is_empty());
+    assert_eq!(store.get("Gray").matches_played, 0);
+
+    store.record_match("Gray", Role::Gray);
+    store.record_match("gray", Role::Note);
+
+    let store = ProfileStore::load(&path);
+    assert_eq!(store.len(), 1);
+
+    let profile = store.get("GRAY");
+    assert_eq!(profile.username, "Gray");
+    assert_eq!(profile.matches_played, 2);
+    assert_eq!(profile.role_history, vec![Role::Gray, Role::Note]);
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn role_history_keeps_the_latest_matches() {
+    let path = env::temp_dir().join(format!(
+        "absent_chroma_profile_history_{}.txt",
+        process::id()
+    ));
+    let _ = fs::remove_file(&path);
+
+    let mut store = ProfileStore::load(&path);
+    for _ in 0..MAX_ROLE_HISTORY {
+        store.record_match("note", Role::Gray);
+    }
+    store.record_match("note", Role::Note);
+
+    let profile = store.get("note");
+    assert_eq!(profile.matches_played, MAX_ROLE_HISTORY as u32 + 1);
+    assert_eq!(profile.role_history.len(), MAX_ROLE_HISTORY);
+    assert_eq!(profile.role_history.last(), Some(&Role::Note));
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn started_match_shows_up_in_the_profile() {
+    let mut harness = Harness::new();
+
+    let gray = harness.join("gray");
+    harness.join("note");
+
+    let role = harness
+        .received(gray)
+        .iter()
+        .find_map(|message| match message {
+            ServerMessage::Welcome { role, .. } => Some(*role),
+            _ => None,
+        })
+        .expect("No Welcome received.");
+
+    harness.run_until(10, |_| false);
+    harness.send(gray, &ClientMessage::ProfileRequest);
+
+    assert!(harness.run_until(200, |harness| {
+        harness.has_received(gray, |message| matches!(message, ServerMessage::Profile(_)))
+    }));
+
+    let profile = harness
+        .received(gray)
+        .iter()
+        .find_map(|message| match message {
+            ServerMessage::Profile(profile) => Some(profile.clone()),
+            _ => None,
+        })
+        .expect("No Profile received.");
+
+    assert_eq!(profile.username, "gray");
+    assert_eq!(profile.matches_played, 1);
+    assert_eq!(profile.role_history, vec![role]);
+}
+
+#[test]
+fn stores_with_the_old_stat_columns_still_load() {
+    let path = env::temp_dir().join(format!("absent_chroma_profile_old_{}.txt", process::id()));
+    fs::write(&path, "Gray 3 1 0 2 GNG\nnote 1 N\nbroken 1 2 N\n").expect("Could not write store.");
+
+    let store = ProfileStore::load(&path);
+    assert_eq!(store.len(), 2);
+
+    let gray = store.get("gray");
+    assert_eq!(gray.matches_played, 3);
+    assert_eq!(gray.role_history, vec![Role::Gray, Role::Note, Role::Gray]);
+    assert_eq!(store.get("note").matches_played, 1);
+
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,40 @@
+use std::{env, fs, process};
+
+use absent_chroma::client::network::recent::{MAX_RECENT_SERVERS, RecentServer, RecentServers};
+
+fn server(host: &str, username: &str) -> RecentServer {
+    RecentServer {
+        host: host.to_string(),
+        port: 42069,
+        username: username.to_string(),
+    }
+}
+
+#[test]
+fn recent_servers_survive_a_reload_newest_first() {
+    let path = env::temp_dir().join(format!("absent_chroma_recent_{}.txt", process::id()));
+    let _ = fs::remove_file(&path);
+
+    let mut recent = RecentServers::load(&path);
+    assert!(recent.servers.is_empty());
+
+    for index in 0..MAX_RECENT_SERVERS + 2 {
+        recent.remember(server(&format!("10.0.0.{}", index), "gray"));
+    }
+    recent.remember(server("10.0.0.3", "gray"));
+
+    let reloaded = RecentServers::load(&path);
+    let _ = fs::remove_file(&path);
+
+    assert_eq!(reloaded.servers.len(), MAX_RECENT_SERVERS);
+    assert_eq!(reloaded.servers[0], server("10.0.0.3", "gray"));
+    assert_eq!(reloaded.servers[1], server("10.0.0.9", "gray"));
+    assert_eq!(
+        reloaded
+            .servers
+            .iter()
+            .filter(|recent| recent.host == "10.0.0.3")
+            .count(),
+        1
+    );
+}
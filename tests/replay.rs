@@ -0,0 +1,101 @@
+mod harness;
+
+use std::{fs, path::PathBuf};
+
+use absent_chroma::{
+    common::{
+        network::{ClientMessage, ServerMessage, VOICE_CHANNEL},
+        tether::{CLEAR_RANGE, LOST_RANGE},
+        voice::{TestTone, VoiceEncoder},
+    },
+    server::{config::ServerSettings, replay::replay},
+};
+
+use crate::harness::{Harness, PASSWORD};
+
+fn recording(harness: &Harness) -> PathBuf {
+    harness
+        .server
+        .world()
+        .resource::<ServerSettings>()
+        .record_path
+        .clone()
+        .expect("The harness records every match.")
+}
+
+/// Both players talk, signal and move apart far enough for the link to degrade.
+fn play(harness: &mut Harness) -> (usize, usize) {
+    let gray = harness.join("gray");
+    let note = harness.join("note");
+
+    harness.move_to(note, [(CLEAR_RANGE + LOST_RANGE) / 2.0, 0.0, 0.0]);
+
+    let frame = VoiceEncoder::default().encode(&TestTone::default().frame());
+
+    harness.send(
+        gray,
+        &ClientMessage::Chat {
+            text: "the beacon is past the ridge".to_string(),
+        },
+    );
+    harness.send_on(gray, VOICE_CHANNEL, &ClientMessage::Voice(frame));
+    harness.send(
+        note,
+        &ClientMessage::Signal {
+            id: "danger".to_string(),
+        },
+    );
+    harness.send(note, &ClientMessage::ProfileRequest);
+
+    assert!(harness.run_until(200, |harness| {
+        harness.has_received(note, |message| {
+            matches!(message, ServerMessage::Chat { .. })
+        }) && harness.has_received(note, |message| {
+            matches!(message, ServerMessage::Voice { .. })
+        }) && harness.has_received(gray, |message| {
+            matches!(message, ServerMessage::Signal { .. })
+        }) && harness.has_received(note, |message| matches!(message, ServerMessage::Profile(_)))
+    }));
+
+    (gray, note)
+}
+
+#[test]
+fn replay_sends_what_the_match_did() {
+    let mut harness = Harness::new();
+    play(&mut harness);
+
+    let report = replay(&recording(&harness)).expect("Recording could not be read.");
+
+    assert_eq!(report.divergence, None);
+    assert!(report.inputs >= 5);
+    // Welcomes, partner status, link changes, the chat, the voice frame and both signals.
+    assert!(report.matched >= 8, "only {} matched", report.matched);
+}
+
+#[test]
+fn recordings_keep_no_secrets() {
+    let mut harness = Harness::new();
+    let (gray, note) = play(&mut harness);
+
+    let bytes = fs::read(recording(&harness)).expect("Recording was not written.");
+
+    for index in [gray, note] {
+        let token = harness
+            .received(index)
+            .iter()
+            .find_map(|message| match message {
+                ServerMessage::Welcome { resume_token, .. } => Some(*resume_token),
+                _ => None,
+            })
+            .expect("Player was never welcomed.");
+
+        assert!(!bytes.windows(token.len()).any(|window| window == token));
+    }
+
+    assert!(
+        !bytes
+            .windows(PASSWORD.len())
+            .any(|window| window == PASSWORD.as_bytes())
+    );
+}
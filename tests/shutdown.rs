@@ -0,0 +1,42 @@
+use std::{env, fs, process};
+
+use absent_chroma::{
+    common::network::Role,
+    server::shutdown::{SavedPlayer, SavedWorld},
+};
+
+#[test]
+fn saved_players_are_restored_once() {
+    let path = env::temp_dir().join(format!("absent_chroma_save_{}.bin", process::id()));
+
+    assert!(SavedWorld::load(&path).0.is_empty());
+
+    let players = vec![
+        SavedPlayer {
+            username: "Gray".to_string(),
+            role: Role::Gray,
+            translation: [1.0, 2.0, 3.0],
+        },
+        SavedPlayer {
+            username: "note".to_string(),
+            role: Role::Note,
+            translation: [-4.0, 0.0, 8.0],
+        },
+    ];
+    let bytes = bincode::encode_to_vec(&players, bincode::config::standard())
+        .expect("Error encoding saved players.");
+    fs::write(&path, bytes).expect("Could not write the save file.");
+
+    let mut saved = SavedWorld::load(&path);
+    assert_eq!(saved.0.len(), 2);
+
+    let gray = saved.take("gray").expect("gray was saved");
+    assert_eq!(gray.role, Role::Gray);
+    assert_eq!(gray.translation, [1.0, 2.0, 3.0]);
+    assert!(saved.take("gray").is_none());
+
+    fs::write(&path, b"not a save").expect("Could not write the save file.");
+    assert!(SavedWorld::load(&path).0.is_empty());
+
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,115 @@
+mod harness;
+
+use std::path::Path;
+
+use absent_chroma::common::{
+    network::{ClientMessage, Role, ServerMessage},
+    signals::{SignalDefs, valid_signal_id},
+};
+
+use crate::harness::Harness;
+
+fn signal(harness: &Harness, index: usize) -> Option<(Role, String)> {
+    harness
+        .received(index)
+        .iter()
+        .find_map(|message| match message {
+            ServerMessage::Signal { from, id, .. } => Some((*from, id.clone())),
+            _ => None,
+        })
+}
+
+#[test]
+fn signal_definitions_skip_bad_lines() {
+    let defs = SignalDefs::parse(
+        "# comment\n\
+         danger 880 3 ff3030 Danger ahead\n\
+         danger 440 1 ffffff Duplicate\n\
+         loud 5 1 ffffff Too low to hear\n\
+         badcolor 440 1 red Bad color\n\
+         no_label 440 1 ffffff\n\
+         bad!id 440 1 ffffff Bad id\n\
+         \n\
+         heal_me 659 20 40ff70 Heal me\n",
+    );
+
+    assert_eq!(defs.0.len(), 2);
+
+    let danger = defs.get("danger").expect("danger is defined");
+    assert_eq!(danger.pitch, 880.0);
+    assert_eq!(danger.pulses, 3);
+    assert_eq!(danger.color, [0xff, 0x30, 0x30]);
+    assert_eq!(danger.label, "Danger ahead");
+
+    assert_eq!(defs.get("heal_me").map(|def| def.pulses), Some(8));
+    assert!(defs.get("bad!id").is_none());
+}
+
+#[test]
+fn shipped_signals_load() {
+    let defs = SignalDefs::load(&Path::new("assets").join("signals.txt"));
+
+    for id in ["danger", "come_here", "heal_me", "beacon"] {
+        assert!(defs.get(id).is_some(), "{} is missing", id);
+    }
+
+    assert!(
+        SignalDefs::load(Path::new("no_such_signals.txt"))
+            .0
+            .is_empty()
+    );
+}
+
+#[test]
+fn signals_reach_both_players() {
+    let mut harness = Harness::new();
+
+    let gray = harness.join("gray");
+    let note = harness.join("note");
+
+    let role = harness
+        .received(gray)
+        .iter()
+        .find_map(|message| match message {
+            ServerMessage::Welcome { role, .. } => Some(*role),
+            _ => None,
+        })
+        .expect("No Welcome received.");
+
+    harness.send(
+        gray,
+        &ClientMessage::Signal {
+            id: "danger".to_string(),
+        },
+    );
+
+    assert!(harness.run_until(200, |harness| {
+        signal(harness, gray).is_some() && signal(harness, note).is_some()
+    }));
+
+    assert_eq!(signal(&harness, note), Some((role, "danger".to_string())));
+    assert_eq!(signal(&harness, gray), signal(&harness, note));
+}
+
+#[test]
+fn malformed_signal_ids_are_dropped() {
+    assert!(valid_signal_id("come_here"));
+    assert!(!valid_signal_id(""));
+    assert!(!valid_signal_id("come here"));
+    assert!(!valid_signal_id(&"a".repeat(33)));
+
+    let mut harness = Harness::new();
+
+    let gray = harness.join("gray");
+    let note = harness.join("note");
+
+    harness.send(
+        gray,
+        &ClientMessage::Signal {
+            id: "<script>".to_string(),
+        },
+    );
+    harness.run_until(50, |_| false);
+
+    assert!(signal(&harness, note).is_none());
+}
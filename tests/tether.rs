@@ -0,0 +1,122 @@
+mod harness;
+
+use absent_chroma::{
+    common::{
+        network::{ClientMessage, ServerMessage},
+        tether::{CLEAR_RANGE, CommState, HYSTERESIS, LOST_RANGE, clarity},
+    },
+    server::tether::Tether,
+};
+
+use crate::harness::Harness;
+
+/// Last link state the server told `index` about.
+fn comm(harness: &Harness, index: usize) -> Option<(CommState, f32)> {
+    harness
+        .received(index)
+        .iter()
+        .rev()
+        .find_map(|message| match message {
+            ServerMessage::Comm { state, clarity } => Some((*state, *clarity)),
+            _ => None,
+        })
+}
+
+fn got_signal(harness: &Harness, index: usize) -> bool {
+    harness.has_received(index, |message| {
+        matches!(message, ServerMessage::Signal { .. })
+    })
+}
+
+#[test]
+fn clarity_falls_off_with_distance() {
+    assert_eq!(clarity(0.0), 1.0);
+    assert_eq!(clarity(CLEAR_RANGE), 1.0);
+    assert_eq!(clarity(LOST_RANGE), 0.0);
+    assert_eq!(clarity(LOST_RANGE * 2.0), 0.0);
+
+    let halfway = clarity((CLEAR_RANGE + LOST_RANGE) / 2.0);
+    assert!((halfway - 0.5).abs() < 1e-4);
+}
+
+#[test]
+fn comm_state_does_not_flicker_on_a_boundary() {
+    let clear = CommState::Clear;
+    assert_eq!(clear.next(CLEAR_RANGE), CommState::Clear);
+    assert_eq!(clear.next(CLEAR_RANGE + 0.1), CommState::Degraded);
+    assert_eq!(clear.next(LOST_RANGE), CommState::Lost);
+
+    let degraded = CommState::Degraded;
+    assert_eq!(degraded.next(CLEAR_RANGE - 0.1), CommState::Degraded);
+    assert_eq!(degraded.next(CLEAR_RANGE - HYSTERESIS), CommState::Clear);
+    assert_eq!(degraded.next(LOST_RANGE - 0.1), CommState::Degraded);
+    assert_eq!(degraded.next(LOST_RANGE), CommState::Lost);
+
+    let lost = CommState::Lost;
+    assert_eq!(lost.next(LOST_RANGE - 0.1), CommState::Lost);
+    assert_eq!(
+        lost.next(LOST_RANGE - HYSTERESIS - 0.1),
+        CommState::Degraded
+    );
+    assert_eq!(lost.next(0.0), CommState::Clear);
+}
+
+#[test]
+fn players_are_told_when_the_link_changes() {
+    let mut harness = Harness::new();
+
+    let gray = harness.join("gray");
+    let note = harness.join("note");
+    harness.run_until(10, |_| false);
+
+    assert_eq!(comm(&harness, gray), Some((CommState::Clear, 1.0)));
+    assert_eq!(comm(&harness, note), Some((CommState::Clear, 1.0)));
+
+    harness.move_to(note, [(CLEAR_RANGE + LOST_RANGE) / 2.0, 0.0, 0.0]);
+    assert_eq!(comm(&harness, gray), Some((CommState::Degraded, 0.5)));
+
+    harness.move_to(note, [LOST_RANGE + 5.0, 0.0, 0.0]);
+    assert_eq!(comm(&harness, gray), Some((CommState::Lost, 0.0)));
+    assert_eq!(comm(&harness, note), Some((CommState::Lost, 0.0)));
+
+    harness.move_to(note, [0.0, 0.0, 0.0]);
+    assert_eq!(comm(&harness, note), Some((CommState::Clear, 1.0)));
+}
+
+#[test]
+fn lost_link_keeps_signals_to_the_sender() {
+    let mut harness = Harness::new();
+
+    let gray = harness.join("gray");
+    let note = harness.join("note");
+
+    harness.move_to(note, [0.0, 0.0, LOST_RANGE + 5.0]);
+
+    harness.send(
+        gray,
+        &ClientMessage::Signal {
+            id: "danger".to_string(),
+        },
+    );
+
+    assert!(harness.run_until(200, |harness| got_signal(harness, gray)));
+    harness.run_until(20, |_| false);
+
+    assert!(!got_signal(&harness, note));
+}
+
+#[test]
+fn impossible_positions_are_ignored() {
+    let mut harness = Harness::new();
+
+    harness.join("gray");
+    let note = harness.join("note");
+
+    harness.move_to(note, [5.0, 0.0, 0.0]);
+    harness.move_to(note, [f32::NAN, 0.0, 0.0]);
+    harness.move_to(note, [0.0, f32::INFINITY, 0.0]);
+
+    let tether = harness.server.world().resource::<Tether>();
+    assert_eq!(tether.distance, 5.0);
+    assert_eq!(tether.state, CommState::Clear);
+}
@@ -0,0 +1,127 @@
+mod harness;
+
+use absent_chroma::{
+    common::{
+        network::{ClientMessage, ServerMessage, VOICE_CHANNEL},
+        tether::LOST_RANGE,
+        voice::{FRAME_SAMPLES, MAX_FRAME_BYTES, TestTone, VoiceEncoder, VoiceFrame, decode},
+    },
+    server::metrics::ServerMetrics,
+};
+
+use crate::harness::Harness;
+
+/// Signal to noise ratio of `decoded` against `original`, in dB.
+fn snr(original: &[i16], decoded: &[i16]) -> f64 {
+    let (signal, noise) =
+        original
+            .iter()
+            .zip(decoded)
+            .fold((0.0, 0.0), |(signal, noise), (a, b)| {
+                let a = *a as f64;
+                let error = a - *b as f64;
+                (signal + a * a, noise + error * error)
+            });
+
+    10.0 * (signal / noise.max(1.0)).log10()
+}
+
+fn voice_frames(harness: &Harness, index: usize) -> Vec<(VoiceFrame, f32)> {
+    harness
+        .received(index)
+        .iter()
+        .filter_map(|message| match message {
+            ServerMessage::Voice { frame, gain, .. } => Some((frame.clone(), *gain)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn adpcm_round_trip_keeps_the_signal() {
+    let mut tone = TestTone::default();
+    let mut encoder = VoiceEncoder::default();
+
+    for sequence in 0..10 {
+        let samples = tone.frame();
+        let frame = encoder.encode(&samples);
+
+        assert_eq!(frame.sequence, sequence);
+        assert_eq!(frame.data.len(), MAX_FRAME_BYTES);
+
+        let decoded = decode(&frame);
+        assert_eq!(decoded.len(), FRAME_SAMPLES);
+
+        // The first frame starts from silence and needs a few samples to catch up.
+        if sequence > 0 {
+            assert!(snr(&samples, &decoded) > 20.0);
+        }
+    }
+}
+
+#[test]
+fn frames_decode_without_the_ones_before() {
+    let mut tone = TestTone::default();
+    let mut encoder = VoiceEncoder::default();
+
+    let frames: Vec<VoiceFrame> = (0..3).map(|_| encoder.encode(&tone.frame())).collect();
+
+    let in_order: Vec<Vec<i16>> = frames.iter().map(decode).collect();
+
+    assert_eq!(decode(&frames[2]), in_order[2]);
+}
+
+#[test]
+fn nearby_partner_hears_voice() {
+    let mut harness = Harness::new();
+
+    let gray = harness.join("gray");
+    let note = harness.join("note");
+
+    let mut tone = TestTone::default();
+    let mut encoder = VoiceEncoder::default();
+    let sent: Vec<VoiceFrame> = (0..5).map(|_| encoder.encode(&tone.frame())).collect();
+
+    for frame in &sent {
+        harness.send_on(gray, VOICE_CHANNEL, &ClientMessage::Voice(frame.clone()));
+    }
+
+    assert!(
+        harness.run_until(200, |harness| voice_frames(harness, note).len()
+            == sent.len())
+    );
+
+    for ((frame, gain), sent) in voice_frames(&harness, note).iter().zip(&sent) {
+        assert_eq!(frame, sent);
+        assert_eq!(*gain, 1.0);
+    }
+    assert!(voice_frames(&harness, gray).is_empty());
+}
+
+#[test]
+fn voice_does_not_carry_out_of_range() {
+    let mut harness = Harness::new();
+
+    let gray = harness.join("gray");
+    let note = harness.join("note");
+
+    harness.move_to(note, [0.0, 0.0, LOST_RANGE + 5.0]);
+
+    let mut encoder = VoiceEncoder::default();
+    let frame = encoder.encode(&TestTone::default().frame());
+    harness.send_on(gray, VOICE_CHANNEL, &ClientMessage::Voice(frame));
+
+    let oversized = VoiceFrame {
+        data: vec![0; MAX_FRAME_BYTES + 1],
+        ..VoiceFrame::default()
+    };
+    harness.send_on(gray, VOICE_CHANNEL, &ClientMessage::Voice(oversized));
+
+    harness.run_until(50, |_| false);
+
+    assert!(voice_frames(&harness, note).is_empty());
+
+    let metrics = harness.server.world().resource::<ServerMetrics>();
+    assert_eq!(metrics.voice_out_of_range, 1);
+    assert_eq!(metrics.voice_relayed, 0);
+}
@@ -0,0 +1,153 @@
+mod harness;
+
+use absent_chroma::{
+    common::{
+        network::ServerMessage,
+        world::{CHUNK_SIDE, CHUNK_SIZE, HEIGHT_SCALE, WorldGenerator, WorldSeed},
+    },
+    server::session::Sessions,
+};
+use bevy::prelude::*;
+
+use crate::harness::Harness;
+
+fn bits(heights: &[f32]) -> Vec<u32> {
+    heights.iter().map(|height| height.to_bits()).collect()
+}
+
+/// Heights taken from the generator as it shipped. Terrain must not change under a seed, or
+/// clients and servers of different builds stop agreeing on it.
+#[test]
+fn heights_match_the_shipped_generator() {
+    let samples = [
+        (0, 3.5, -7.25, 0x3e944467),
+        (0, 12.3, 45.6, 0x3ecf39d8),
+        (42, 3.5, -7.25, 0x3e0f9e31),
+        (42, 12.3, 45.6, 0x3e2ad1db),
+        (0xdead_beef, 3.5, -7.25, 0x3ee0aec1),
+        (0xdead_beef, 12.3, 45.6, 0x3ea7e845),
+    ];
+
+    for (seed, x, z, expected) in samples {
+        let height = WorldGenerator::new(WorldSeed(seed)).height_at(x, z);
+        assert_eq!(height.to_bits(), expected, "seed {} at {}, {}", seed, x, z);
+    }
+
+    let chunks = [
+        (0, IVec2::new(0, 0), 40, 0xbea71a32),
+        (0, IVec2::new(-2, 5), 1000, 0x3ef1b731),
+        (42, IVec2::new(3, -1), 0, 0xbecac16e),
+        (0xdead_beef, IVec2::new(-2, 5), 1000, 0x3f305b7e),
+    ];
+
+    for (seed, coord, index, expected) in chunks {
+        let heights = WorldGenerator::new(WorldSeed(seed)).chunk_heights(coord);
+        assert_eq!(
+            heights[index].to_bits(),
+            expected,
+            "seed {} chunk {}",
+            seed,
+            coord
+        );
+    }
+}
+
+#[test]
+fn seeds_change_the_terrain() {
+    let coord = IVec2::new(2, -1);
+
+    assert_ne!(
+        bits(&WorldGenerator::new(WorldSeed(1)).chunk_heights(coord)),
+        bits(&WorldGenerator::new(WorldSeed(2)).chunk_heights(coord))
+    );
+}
+
+#[test]
+fn heightfields_are_smooth_and_in_range() {
+    let generator = WorldGenerator::new(WorldSeed(u64::MAX));
+
+    // Every octave is zero on integer noise coordinates, the origin included.
+    assert_eq!(generator.height_at(0.0, 0.0), 0.0);
+
+    let heights = generator.chunk_heights(IVec2::new(-1, -1));
+    assert_eq!(heights.len(), CHUNK_SIDE * CHUNK_SIDE);
+    assert!(heights.iter().all(|height| height.abs() <= HEIGHT_SCALE));
+    assert!(heights.iter().any(|height| *height != 0.0));
+
+    for x in 0..CHUNK_SIDE - 1 {
+        let step = (heights[(x + 1) * CHUNK_SIDE] - heights[x * CHUNK_SIDE]).abs();
+        assert!(step < HEIGHT_SCALE / 2.0, "step of {} at {}", step, x);
+    }
+}
+
+#[test]
+fn neighbouring_chunks_share_their_edges() {
+    let generator = WorldGenerator::new(WorldSeed(99));
+
+    let chunk = generator.chunk_heights(IVec2::new(-1, 0));
+    let east = generator.chunk_heights(IVec2::new(0, 0));
+    let north = generator.chunk_heights(IVec2::new(-1, 1));
+
+    for i in 0..CHUNK_SIDE {
+        assert_eq!(
+            chunk[CHUNK_SIZE * CHUNK_SIDE + i].to_bits(),
+            east[i].to_bits()
+        );
+        assert_eq!(
+            chunk[i * CHUNK_SIDE + CHUNK_SIZE].to_bits(),
+            north[i * CHUNK_SIDE].to_bits()
+        );
+    }
+}
+
+#[test]
+fn clients_take_the_seed_from_the_server() {
+    let mut harness = Harness::new();
+
+    let gray = harness.join("gray");
+    let note = harness.connect_core("note");
+
+    let sent = harness
+        .received(gray)
+        .iter()
+        .find_map(|message| match message {
+            ServerMessage::Welcome { seed, .. } => Some(*seed),
+            _ => None,
+        })
+        .expect("No Welcome received.");
+    assert_eq!(sent, 42);
+
+    // The game client only knows the seed from the welcome.
+    assert!(harness.run_until(200, |harness| {
+        harness.clients[note]
+            .app
+            .world()
+            .get_resource::<WorldSeed>()
+            .is_some_and(|seed| *seed == WorldSeed(42))
+    }));
+}
+
+#[test]
+fn players_are_kept_out_of_the_ground() {
+    let mut harness = Harness::new();
+
+    let gray = harness.join("gray");
+    let ground = WorldGenerator::new(WorldSeed(42)).ground_at(3.5, -7.25);
+
+    harness.move_to(gray, [3.5, ground - 10.0, -7.25]);
+    assert_eq!(player_y(&harness), ground);
+
+    harness.move_to(gray, [3.5, ground + 1.0, -7.25]);
+    assert_eq!(player_y(&harness), ground + 1.0);
+}
+
+fn player_y(harness: &Harness) -> f32 {
+    let world = harness.server.world();
+    let session = &world.resource::<Sessions>().0[0];
+
+    world
+        .get::<Transform>(session.entity)
+        .expect("Player has no transform.")
+        .translation
+        .y
+}